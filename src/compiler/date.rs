@@ -13,9 +13,10 @@ use crate::session::Session;
 use crate::compiler::duration::DurationItem;
 use crate::config::SmartCalcConfig;
 use crate::formatter::{MONTH, YEAR, get_month_info, left_padding, uppercase_first_letter};
-use crate::types::{TokenType, TimeOffset};
+use crate::types::{Number, TokenType, TimeOffset};
 
-use super::{DataItem, OperationType, UnaryType};
+use super::{DataItem, OperationType, UnaryType, incompatible_types, not_supported_by_function};
+use crate::error::SmartCalcError;
 
 #[derive(Debug)]
 
@@ -51,14 +52,14 @@ impl DataItem for DateItem {
     }
     fn as_any(&self) -> &dyn Any { self }
     
-    fn calculate(&self, _: &SmartCalcConfig, _: bool, other: &dyn DataItem, operation_type: OperationType) -> Option<Rc<dyn DataItem>> {
+    fn calculate(&self, _: &SmartCalcConfig, _: &Session, _: bool, other: &dyn DataItem, operation_type: OperationType) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
         /* If both item is money and current money is on left side, skip calculation */
         if other.type_name() != "DURATION" {
-            return None;
+            return Err(incompatible_types(self, other));
         }
 
         let mut date = self.0;
-        let mut duration = other.as_any().downcast_ref::<DurationItem>()?.get_duration();
+        let mut duration = other.as_any().downcast_ref::<DurationItem>().ok_or_else(|| incompatible_types(self, other))?.get_duration();
 
         match operation_type {
             OperationType::Add => {
@@ -80,7 +81,7 @@ impl DataItem for DateItem {
                         duration = Duration::seconds(duration.num_seconds() - (MONTH * n))
                     }
                 };
-                Some(Rc::new(DateItem(date + duration, self.1.clone())))
+                Ok(Rc::new(DateItem(date + duration, self.1.clone())))
             },
 
             OperationType::Sub => {
@@ -106,9 +107,9 @@ impl DataItem for DateItem {
                         duration = Duration::seconds(duration.num_seconds() - (MONTH * n))
                     }
                 };
-                Some(Rc::new(DateItem(date - duration, self.1.clone())))
+                Ok(Rc::new(DateItem(date - duration, self.1.clone())))
             },
-            _ => None
+            _ => Err(incompatible_types(self, other))
         }
     }
     
@@ -129,18 +130,19 @@ impl DataItem for DateItem {
             }
         };
         
-        let date_format = match self.0.year() == Utc::now().date().year() {
-            true => format.date.get("current_year"),
-            false => format.date.get("full_date")
+        let format_name = match self.0.year() == Utc::now().date().year() {
+            true => "current_year",
+            false => "full_date"
         };
+        let date_format = session.locale(config).date_format(format_name).or_else(|| format.date.get(format_name).cloned());
 
-        let tz_offset = chrono::FixedOffset::east(self.1.offset * 60);
+        let tz_offset = chrono::FixedOffset::east(self.1.offset_at(&self.0.and_hms_opt(0, 0, 0).unwrap_or_default()) * 60);
         let datetime = tz_offset.from_utc_date(&self.0);
         
         match date_format {
             Some(data) => {
                 match get_month_info(config, &format.language, datetime.month() as u8) {
-                    Some(month_info) => data.clone()
+                    Some(month_info) => data
                         .replace("{day}", &datetime.day().to_string())
                         .replace("{month}", &datetime.month().to_string())
                         .replace("{day_pad}", &left_padding(datetime.day().into(), 2))
@@ -158,6 +160,10 @@ impl DataItem for DateItem {
     fn unary(&self, _: UnaryType) -> Rc<dyn DataItem> {
         Rc::new(Self(self.0, self.1.clone()))
     }
+
+    fn map_number(&self, _: &dyn Fn(Number) -> Result<Number, SmartCalcError>) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        Err(not_supported_by_function(self))
+    }
 }
 
 
@@ -174,14 +180,14 @@ fn date_test() {
 
     let left = DateItem(NaiveDate::from_ymd(2020, 1, 1), config.get_time_offset());
     let right = DateItem(NaiveDate::from_ymd(2020, 1, 1), config.get_time_offset());
-    let result = left.calculate(&config, true, &right, OperationType::Sub);
+    let result = left.calculate(&config, &session, true, &right, OperationType::Sub);
     
-    assert!(result.is_none());
+    assert!(result.is_err());
 
     let left = DateItem(NaiveDate::from_ymd(2020, 1, 1), config.get_time_offset());
     let right = DurationItem(Duration::hours(24 * 20));
-    let result = left.calculate(&config, true, &right, OperationType::Add);
+    let result = left.calculate(&config, &session, true, &right, OperationType::Add);
     
-    assert!(result.is_some());
+    assert!(result.is_ok());
     assert_eq!(result.unwrap().print(&config, &session), "21 Jan 2020".to_string());
 }
\ No newline at end of file
@@ -15,9 +15,10 @@ use crate::session::Session;
 use crate::compiler::duration::DurationItem;
 use crate::config::SmartCalcConfig;
 use crate::formatter::{get_month_info, left_padding, uppercase_first_letter};
-use crate::types::{TokenType, TimeOffset};
+use crate::types::{Number, TokenType, TimeOffset};
 
-use super::{DataItem, OperationType, UnaryType};
+use super::{DataItem, OperationType, UnaryType, incompatible_types, not_supported_by_function};
+use crate::error::SmartCalcError;
 
 #[derive(Debug)]
 
@@ -45,18 +46,18 @@ impl DataItem for DateTimeItem {
     }
     fn as_any(&self) -> &dyn Any { self }
     
-    fn calculate(&self, _: &SmartCalcConfig, _: bool, other: &dyn DataItem, operation_type: OperationType) -> Option<Rc<dyn DataItem>> {
+    fn calculate(&self, _: &SmartCalcConfig, _: &Session, _: bool, other: &dyn DataItem, operation_type: OperationType) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
         /* If both item is money and current money is on left side, skip calculation */
         if other.type_name() != "DURATION" {
-            return None;
+            return Err(incompatible_types(self, other));
         }
 
         let date = self.0;
-        let duration = other.as_any().downcast_ref::<DurationItem>()?.get_duration();
+        let duration = other.as_any().downcast_ref::<DurationItem>().ok_or_else(|| incompatible_types(self, other))?.get_duration();
         match operation_type {
-            OperationType::Add => Some(Rc::new(DateTimeItem(date + duration, self.1.clone()))),
-            OperationType::Sub => Some(Rc::new(DateTimeItem(date - duration, self.1.clone()))),
-            _ => None
+            OperationType::Add => Ok(Rc::new(DateTimeItem(date + duration, self.1.clone()))),
+            OperationType::Sub => Ok(Rc::new(DateTimeItem(date - duration, self.1.clone()))),
+            _ => Err(incompatible_types(self, other))
         }
     }
     
@@ -77,18 +78,19 @@ impl DataItem for DateTimeItem {
             }
         };
         
-        let tz_offset = chrono::FixedOffset::east(self.1.offset * 60);
+        let tz_offset = chrono::FixedOffset::east(self.1.offset_at(&self.0) * 60);
         let datetime = tz_offset.from_utc_datetime(&self.0);
         
-        let date_format = match datetime.year() == Utc::now().date().year() {
-            true => format.date.get("current_year_with_time"),
-            false => format.date.get("full_date_time")
+        let format_name = match datetime.year() == Utc::now().date().year() {
+            true => "current_year_with_time",
+            false => "full_date_time"
         };
+        let date_format = session.locale(config).date_format(format_name).or_else(|| format.date.get(format_name).cloned());
 
         match date_format {
             Some(data) => {
                 match get_month_info(config, &format.language, datetime.month() as u8) {
-                    Some(month_info) => data.clone()
+                    Some(month_info) => data
                         .replace("{second_pad}", &format!("{:02}", datetime.second()))
                         .replace("{minute_pad}", &format!("{:02}", datetime.minute()))
                         .replace("{hour_pad}", &format!("{:02}", datetime.hour()))
@@ -112,6 +114,10 @@ impl DataItem for DateTimeItem {
     fn unary(&self, _: UnaryType) -> Rc<dyn DataItem> {
         Rc::new(Self(self.0, self.1.clone()))
     }
+
+    fn map_number(&self, _: &dyn Fn(Number) -> Result<Number, SmartCalcError>) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        Err(not_supported_by_function(self))
+    }
 }
 
 #[cfg(test)]
@@ -130,15 +136,15 @@ fn date_time_test() {
 
     let left = DateTimeItem(NaiveDate::from_ymd(2020, 1, 1).and_hms(1, 1, 1), config.get_time_offset());
     let right = DateTimeItem(NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0), config.get_time_offset());
-    let result = left.calculate(&config, true, &right, OperationType::Sub);
+    let result = left.calculate(&config, &session, true, &right, OperationType::Sub);
     
-    assert!(result.is_none());
+    assert!(result.is_err());
 
     let left = DateTimeItem(NaiveDate::from_ymd(2020, 1, 1).and_hms(1, 0, 0), config.get_time_offset());
     let right = DurationItem(Duration::hours(1));
-    let result = left.calculate(&config, true, &right, OperationType::Sub);
+    let result = left.calculate(&config, &session, true, &right, OperationType::Sub);
     
     
-    assert!(result.is_some());
+    assert!(result.is_ok());
     assert_eq!(result.unwrap().print(&config, &session), "1 Jan 2020 00:00:00 UTC".to_string());
 }
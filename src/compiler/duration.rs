@@ -19,11 +19,15 @@ use crate::formatter::MINUTE;
 use crate::formatter::MONTH;
 use crate::formatter::WEEK;
 use crate::formatter::YEAR;
-use crate::types::TokenType;
+use crate::types::{Number, TokenType};
 use core::write;
 use alloc::fmt::Write;
 
-use super::{DataItem, OperationType, UnaryType};
+use super::{DataItem, OperationType, UnaryType, incompatible_types};
+use super::number::NumberItem;
+use crate::error::SmartCalcError;
+use crate::types::NumberType;
+use crate::tools::{check_divider, do_divition, number_from_f64, number_to_f64};
 
 #[derive(Debug)]
 
@@ -113,16 +117,41 @@ impl DataItem for DurationItem {
     }
     fn as_any(&self) -> &dyn Any { self }
     
-    fn calculate(&self, _: &SmartCalcConfig, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> Option<Rc<dyn DataItem>> {
-        /* If both item is money and current money is on left side, skip calculation */
-        if TypeId::of::<Self>() != other.type_id() && on_left {
-            return None;
+    fn calculate(&self, config: &SmartCalcConfig, session: &Session, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+        /* "2 hours * 60 km/h" */
+        if let ("QUANTITY" | "DYNAMIC_TYPE", OperationType::Mul | OperationType::Div) = (other.type_name(), operation_type) {
+            return other.calculate(config, session, !on_left, self, operation_type);
         }
 
+        /* Duration can only be scaled by a number when it is on the left side */
+        if TypeId::of::<NumberItem>() == other.type_id() && on_left {
+            let number = other.get_underlying_number();
+            let milliseconds = self.0.num_milliseconds() as f64;
+
+            return match operation_type {
+                OperationType::Mul => Ok(Rc::new(DurationItem(Duration::milliseconds((milliseconds * number) as i64)))),
+                OperationType::Div => {
+                    check_divider(config, number)?;
+                    Ok(Rc::new(DurationItem(Duration::milliseconds(do_divition(milliseconds, number) as i64))))
+                },
+                _ => Err(incompatible_types(self, other))
+            };
+        }
+
+        let other_duration = match other.as_any().downcast_ref::<Self>() {
+            Some(duration) => duration.get_duration(),
+            None => return Err(incompatible_types(self, other))
+        };
+
         match operation_type {
-            OperationType::Add => Some(Rc::new(DurationItem(self.0 + other.as_any().downcast_ref::<Self>()?.get_duration()))),
-            OperationType::Sub => Some(Rc::new(DurationItem(self.0 - other.as_any().downcast_ref::<Self>()?.get_duration()))),
-            _ => None
+            OperationType::Add => Ok(Rc::new(DurationItem(self.0 + other_duration))),
+            OperationType::Sub => Ok(Rc::new(DurationItem(self.0 - other_duration))),
+            OperationType::Div => {
+                let divider = other_duration.num_milliseconds() as f64;
+                check_divider(config, divider)?;
+                Ok(Rc::new(NumberItem(number_from_f64(do_divition(self.0.num_milliseconds() as f64, divider))?, NumberType::Decimal)))
+            },
+            _ => Err(incompatible_types(self, other))
         }
     }
     
@@ -185,6 +214,10 @@ impl DataItem for DurationItem {
     fn unary(&self, _: UnaryType) -> Rc<dyn DataItem> {
         Rc::new(Self(self.0))
     }
+
+    fn map_number(&self, mapper: &dyn Fn(Number) -> Result<Number, SmartCalcError>) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        Ok(Rc::new(Self(Duration::seconds(number_to_f64(mapper(number_from_f64(self.0.num_seconds() as f64)?)?) as i64))))
+    }
 }
 
 
@@ -211,15 +244,15 @@ fn duration_test() {
 
     let left = DurationItem(Duration::hours(15));
     let right = DurationItem(Duration::minutes(1));
-    let result = left.calculate(&config, true, &right, OperationType::Add);
+    let result = left.calculate(&config, &session, true, &right, OperationType::Add);
     
-    assert!(result.is_some());
+    assert!(result.is_ok());
     assert_eq!(result.unwrap().print(&config, &session), "15 hours 1 minute".to_string());
 
     let left = DurationItem(Duration::hours(15));
     let right = DurationItem(Duration::minutes(1));
-    let result = left.calculate(&config, true, &right, OperationType::Sub);
+    let result = left.calculate(&config, &session, true, &right, OperationType::Sub);
     
-    assert!(result.is_some());
+    assert!(result.is_ok());
     assert_eq!(result.unwrap().print(&config, &session), "14 hours 59 minutes".to_string());
 }
\ No newline at end of file
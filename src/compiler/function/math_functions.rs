@@ -0,0 +1,163 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use core::cmp::Ordering;
+use core::ops::Deref;
+use alloc::rc::Rc;
+use alloc::format;
+
+use crate::config::SmartCalcConfig;
+use crate::session::Session;
+use crate::compiler::{DataItem, OperationType, not_supported_by_function};
+use crate::compiler::quantity::QuantityItem;
+use crate::error::SmartCalcError;
+use crate::tools::{number_from_f64, number_to_f64, round_number};
+use crate::types::Number;
+use crate::unit::coherent_unit;
+
+fn get_argument(arguments: &[Rc<dyn DataItem>], min: usize, max: usize) -> core::result::Result<&Rc<dyn DataItem>, SmartCalcError> {
+    match arguments.len() >= min && arguments.len() <= max {
+        true => Ok(&arguments[0]),
+        false => Err(SmartCalcError::invalid_argument("Function argument count not valid"))
+    }
+}
+
+fn get_number_argument(arguments: &[Rc<dyn DataItem>], index: usize, default: f64) -> core::result::Result<f64, SmartCalcError> {
+    match arguments.get(index) {
+        Some(item) if item.type_name() == "NUMBER" => Ok(item.get_underlying_number()),
+        Some(_) => Err(SmartCalcError::invalid_argument("Function argument must be a number")),
+        None => Ok(default)
+    }
+}
+
+/* Rounding and the sign keep the type of the item, "round($12,345)" is "$12,00" */
+fn map_item(item: &Rc<dyn DataItem>, mapper: &dyn Fn(Number) -> Number) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    item.map_number(&|number| Ok(mapper(number)))
+}
+
+fn float_result(result: f64) -> core::result::Result<f64, SmartCalcError> {
+    match result.is_finite() {
+        true => Ok(result),
+        false => Err(SmartCalcError::invalid_argument("Function result is not a valid number"))
+    }
+}
+
+/* Functions without an exact counterpart of the number backend are calculated with f64. A unit
+   or currency is not kept, "exp($2)" has no meaning, so only numbers are accepted */
+fn map_float_number(item: &Rc<dyn DataItem>, mapper: &dyn Fn(f64) -> f64) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    if item.type_name() != "NUMBER" {
+        return Err(not_supported_by_function(item.deref()));
+    }
+
+    item.map_number(&|number| number_from_f64(float_result(mapper(number_to_f64(number)))?))
+}
+
+/* "sqrt(16 m^2)" is "4 m". Units that can not be split are converted to the coherent unit first,
+   roots of "5 km" or "4 days" have no unit */
+fn map_root(config: &SmartCalcConfig, item: &Rc<dyn DataItem>, degree: i32, mapper: &dyn Fn(f64) -> f64) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    if item.type_name() == "NUMBER" {
+        return map_float_number(item, mapper);
+    }
+
+    let (value, unit) = match QuantityItem::from_item(config, item.deref(), None) {
+        Some((value, unit)) if unit.offset().is_none() => (value, unit),
+        _ => return Err(not_supported_by_function(item.deref()))
+    };
+
+    let (value, root) = match unit.root(degree) {
+        Some(root) => (value, root),
+        None => match unit.dimension().root(degree).and_then(|dimension| coherent_unit(config, &dimension)) {
+            Some(coherent) => (unit.to_coherent(value), coherent),
+            None => return Err(not_supported_by_function(item.deref()))
+        }
+    };
+
+    QuantityItem::from_value(config, float_result(mapper(value))?, root)
+}
+
+fn compare_items(config: &SmartCalcConfig, session: &Session, left: &Rc<dyn DataItem>, right: &Rc<dyn DataItem>) -> core::result::Result<Ordering, SmartCalcError> {
+    let comparable = matches!(left.type_name(), "NUMBER" | "MONEY" | "PERCENT" | "DURATION" | "DYNAMIC_TYPE" | "QUANTITY");
+    if !comparable || left.type_name() != right.type_name() {
+        return Err(SmartCalcError::invalid_argument(format!("{} and {} can not be compared", left.type_name(), right.type_name())));
+    }
+
+    /* Subtraction converts the right item to left item's unit or currency */
+    match left.calculate(config, session, true, right.deref(), OperationType::Sub) {
+        Ok(difference) => Ok(difference.get_underlying_number().partial_cmp(&0.0).unwrap_or(Ordering::Equal)),
+        Err(_) => Err(SmartCalcError::invalid_argument(format!("{} and {} can not be compared", left.type_name(), right.type_name())))
+    }
+}
+
+fn select_item(config: &SmartCalcConfig, session: &Session, arguments: &[Rc<dyn DataItem>], expected: Ordering) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    let mut selected = get_argument(arguments, 1, usize::MAX)?.clone();
+    for item in arguments.iter().skip(1) {
+        if compare_items(config, session, item, &selected)? == expected {
+            selected = item.clone();
+        }
+    }
+
+    Ok(selected)
+}
+
+pub fn abs(_: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    map_item(get_argument(arguments, 1, 1)?, &|number| number.abs())
+}
+
+pub fn sqrt(config: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    map_root(config, get_argument(arguments, 1, 1)?, 2, &|number| number.sqrt())
+}
+
+pub fn cbrt(config: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    map_root(config, get_argument(arguments, 1, 1)?, 3, &|number| number.cbrt())
+}
+
+pub fn round(_: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    let item = get_argument(arguments, 1, 2)?;
+    let digits = get_number_argument(arguments, 1, 0.0)? as i32;
+    item.map_number(&|number| round_number(number, digits))
+}
+
+pub fn floor(_: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    map_item(get_argument(arguments, 1, 1)?, &|number| number.floor())
+}
+
+pub fn ceil(_: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    map_item(get_argument(arguments, 1, 1)?, &|number| number.ceil())
+}
+
+pub fn min(config: &SmartCalcConfig, session: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    select_item(config, session, arguments, Ordering::Less)
+}
+
+pub fn max(config: &SmartCalcConfig, session: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    select_item(config, session, arguments, Ordering::Greater)
+}
+
+pub fn log(_: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    let item = get_argument(arguments, 1, 2)?;
+    let base = get_number_argument(arguments, 1, 10.0)?;
+    map_float_number(item, &|number| number.log(base))
+}
+
+pub fn ln(_: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    map_float_number(get_argument(arguments, 1, 1)?, &|number| number.ln())
+}
+
+pub fn exp(_: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    map_float_number(get_argument(arguments, 1, 1)?, &|number| number.exp())
+}
+
+pub fn sin(_: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    map_float_number(get_argument(arguments, 1, 1)?, &|number| number.sin())
+}
+
+pub fn cos(_: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    map_float_number(get_argument(arguments, 1, 1)?, &|number| number.cos())
+}
+
+pub fn tan(_: &SmartCalcConfig, _: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+    map_float_number(get_argument(arguments, 1, 1)?, &|number| number.tan())
+}
@@ -0,0 +1,51 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+pub mod math_functions;
+
+use lazy_static::*;
+use alloc::string::ToString;
+use alloc::string::String;
+use alloc::collections::btree_map::BTreeMap;
+
+use crate::FunctionTrait;
+use crate::types::CallFunc;
+use crate::shared::Shared;
+
+use self::math_functions::*;
+
+#[allow(clippy::upper_case_acronyms)]
+pub enum FunctionType {
+    Internal {
+        function_name: String,
+        function: CallFunc
+    },
+    API {
+        arity: usize,
+        function: Shared<dyn FunctionTrait>
+    }
+}
+
+lazy_static! {
+        pub static ref BUILTIN_FUNCTIONS: BTreeMap<String, CallFunc> = {
+        let mut m = BTreeMap::new();
+        m.insert("abs".to_string(),   abs as CallFunc);
+        m.insert("sqrt".to_string(),  sqrt as CallFunc);
+        m.insert("cbrt".to_string(),  cbrt as CallFunc);
+        m.insert("round".to_string(), round as CallFunc);
+        m.insert("floor".to_string(), floor as CallFunc);
+        m.insert("ceil".to_string(),  ceil as CallFunc);
+        m.insert("min".to_string(),   min as CallFunc);
+        m.insert("max".to_string(),   max as CallFunc);
+        m.insert("log".to_string(),   log as CallFunc);
+        m.insert("ln".to_string(),    ln as CallFunc);
+        m.insert("exp".to_string(),   exp as CallFunc);
+        m.insert("sin".to_string(),   sin as CallFunc);
+        m.insert("cos".to_string(),   cos as CallFunc);
+        m.insert("tan".to_string(),   tan as CallFunc);
+        m
+    };
+}
@@ -12,11 +12,20 @@ use alloc::rc::Rc;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::format;
+use alloc::vec::Vec;
 
 use crate::session::Session;
 use crate::config::SmartCalcConfig;
 use crate::types::*;
 use crate::variable::VariableInfo;
+use crate::error::{SmartCalcError, Span};
+use crate::explain::ExplainStep;
+use crate::formatter::format_result;
+use crate::value::Value;
+
+use self::function::FunctionType;
+use self::quantity::QuantityItem;
+use crate::unit::Unit;
 
 pub mod number;
 pub mod percent;
@@ -26,6 +35,8 @@ pub mod duration;
 pub mod date;
 pub mod date_time;
 pub mod dynamic_type;
+pub mod quantity;
+pub mod function;
 
 #[derive(Clone)]
 #[derive(Copy)]
@@ -33,7 +44,10 @@ pub enum OperationType {
     Add,
     Div,
     Mul,
-    Sub
+    Sub,
+    Mod,
+    Pow,
+    IntDiv
 }
 
 
@@ -46,6 +60,7 @@ pub enum UnaryType {
 
 pub trait DataItem: alloc::fmt::Debug {
     fn unary(&self, unary: UnaryType) -> Rc<dyn DataItem>;
+    fn map_number(&self, mapper: &dyn Fn(Number) -> Result<Number, SmartCalcError>) -> Result<Rc<dyn DataItem>, SmartCalcError>;
     fn is_same(&self, other: &dyn Any) -> bool;
     fn as_token_type(&self) -> TokenType;
     fn as_any(&self) -> &dyn Any;
@@ -53,25 +68,35 @@ pub trait DataItem: alloc::fmt::Debug {
     fn get_underlying_number(&self) -> f64;
     fn type_name(&self) -> &'static str;
     fn type_id(&self) -> TypeId;
-    fn calculate(&self, config: &SmartCalcConfig, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> Option<Rc<dyn DataItem>>;
+    fn calculate(&self, config: &SmartCalcConfig, session: &Session, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError>;
     fn print(&self, config: &SmartCalcConfig, session: &Session) -> String;
 }
 
+pub(crate) fn incompatible_types(left: &dyn DataItem, right: &dyn DataItem) -> SmartCalcError {
+    SmartCalcError::IncompatibleTypes { left: left.type_name().to_string(), right: right.type_name().to_string(), span: Span::default() }
+}
+
+pub(crate) fn not_supported_by_function(item: &dyn DataItem) -> SmartCalcError {
+    SmartCalcError::invalid_argument(format!("{} is not supported by function", item.type_name()))
+}
+
 pub struct Interpreter;
 
 impl Interpreter {
-    pub fn execute(config: &SmartCalcConfig, ast: Rc<SmartCalcAstType>, session: &Session) -> Result<Rc<SmartCalcAstType>, String> {
-        Interpreter::execute_ast(config, session, ast)
+    pub fn execute(config: &SmartCalcConfig, ast: Rc<SmartCalcAstType>, session: &Session, span: Span) -> Result<Rc<SmartCalcAstType>, SmartCalcError> {
+        Interpreter::execute_ast(config, session, ast, span)
     }
 
-    fn execute_ast(config: &SmartCalcConfig, session: &Session, ast: Rc<SmartCalcAstType>) -> Result<Rc<SmartCalcAstType>, String> {
+    fn execute_ast(config: &SmartCalcConfig, session: &Session, ast: Rc<SmartCalcAstType>, span: Span) -> Result<Rc<SmartCalcAstType>, SmartCalcError> {
         match ast.deref() {
-            SmartCalcAstType::Binary { left, operator, right } => Interpreter::executer_binary(config, session, left.clone(), *operator, right.clone()),
-            SmartCalcAstType::Assignment { variable, expression } => Interpreter::executer_assignment(config, session, variable.clone(), expression.clone()),
+            SmartCalcAstType::Binary { left, operator, right, span } => Interpreter::executer_binary(config, session, left.clone(), *operator, right.clone(), *span),
+            SmartCalcAstType::Assignment { variable, expression } => Interpreter::executer_assignment(config, session, variable.clone(), expression.clone(), span),
             SmartCalcAstType::Variable(variable)               => Ok(Interpreter::executer_variable(variable.clone())),
             SmartCalcAstType::Item(_)                          => Ok(ast),
             SmartCalcAstType::Month(_)                         => Ok(ast),
-            SmartCalcAstType::PrefixUnary(ch, ast)             => Interpreter::executer_unary(config, session, *ch, ast.clone()),
+            SmartCalcAstType::PrefixUnary(ch, ast)             => Interpreter::executer_unary(config, session, *ch, ast.clone(), span),
+            SmartCalcAstType::Call { name, arguments, span }   => Interpreter::executer_call(config, session, name, arguments, *span),
+            SmartCalcAstType::Conversion { expression, unit, span } => Interpreter::executer_conversion(config, session, expression.clone(), unit, *span),
             SmartCalcAstType::None                             => Ok(Rc::new(SmartCalcAstType::None)),
             _ => {
                 log::debug!("Operation not implemented {:?}", ast);
@@ -84,57 +109,120 @@ impl Interpreter {
         variable.data.borrow().clone()
     }
 
-    fn executer_assignment(config: &SmartCalcConfig, session: &Session, variable: Rc<VariableInfo>, expression: Rc<SmartCalcAstType>) -> Result<Rc<SmartCalcAstType>, String> {
-        let computed  = Interpreter::execute_ast(config, session, expression)?;
+    fn executer_assignment(config: &SmartCalcConfig, session: &Session, variable: Rc<VariableInfo>, expression: Rc<SmartCalcAstType>, span: Span) -> Result<Rc<SmartCalcAstType>, SmartCalcError> {
+        let computed  = Interpreter::execute_ast(config, session, expression, span)?;
         *variable.data.borrow_mut() = computed.clone();
         Ok(computed)
     }
     
-    fn calculate_item(config: &SmartCalcConfig, operator: char, left: Rc<SmartCalcAstType>, right: Rc<SmartCalcAstType>) -> Result<Rc<SmartCalcAstType>, String> {
+    fn calculate_item(config: &SmartCalcConfig, session: &Session, operator: char, left: Rc<SmartCalcAstType>, right: Rc<SmartCalcAstType>, span: Span) -> Result<Rc<SmartCalcAstType>, SmartCalcError> {
         let left = match left.deref() {
             SmartCalcAstType::Item(left) => left.clone(),
-            _ => return Err("Unknown calculation".to_string())
+            _ => return Err(SmartCalcError::IncompatibleTypes { left: left.type_name(), right: right.type_name(), span })
         };
         
         let right = match right.deref() {
             SmartCalcAstType::Item(right) => right.clone(),
-            _ => return Err("Unknown calculation".to_string())
+            _ => return Err(SmartCalcError::IncompatibleTypes { left: left.type_name().to_string(), right: right.type_name(), span })
         };
         
         let result = match operator {
-            '+' => left.calculate(config, true, right.deref(), OperationType::Add),
-            '-' => left.calculate(config, true, right.deref(), OperationType::Sub),
-            '*' => left.calculate(config, true, right.deref(), OperationType::Mul),
-            '/' => left.calculate(config, true, right.deref(), OperationType::Div),
-            _ => return Err(format!("Unknown operator. ({})", operator))
+            '+' => left.calculate(config, session, true, right.deref(), OperationType::Add),
+            '-' => left.calculate(config, session, true, right.deref(), OperationType::Sub),
+            '*' => left.calculate(config, session, true, right.deref(), OperationType::Mul),
+            '/' => left.calculate(config, session, true, right.deref(), OperationType::Div),
+            '%' => left.calculate(config, session, true, right.deref(), OperationType::Mod),
+            '^' => left.calculate(config, session, true, right.deref(), OperationType::Pow),
+            '\\' => left.calculate(config, session, true, right.deref(), OperationType::IntDiv),
+            _ => return Err(SmartCalcError::UnknownOperator { operator, span })
         };
         
         match result {
-            Some(item) => Ok(Rc::new(SmartCalcAstType::Item(item.clone()))),
-            None => Err("Unknown calculation".to_string())
+            Ok(item) => Ok(Rc::new(SmartCalcAstType::Item(item.clone()))),
+            Err(error) => Err(error.with_span(span))
         }
     }
 
-    fn executer_binary(config: &SmartCalcConfig, session: &Session, left: Rc<SmartCalcAstType>, operator: char, right: Rc<SmartCalcAstType>) -> Result<Rc<SmartCalcAstType>, String> {
-        let computed_left  = Interpreter::execute_ast(config, session, left)?;
-        let computed_right = Interpreter::execute_ast(config, session, right)?;
+    fn executer_binary(config: &SmartCalcConfig, session: &Session, left: Rc<SmartCalcAstType>, operator: char, right: Rc<SmartCalcAstType>, span: Span) -> Result<Rc<SmartCalcAstType>, SmartCalcError> {
+        let (computed, steps) = session.trace_nested(|| -> Result<_, SmartCalcError> {
+            Ok((Interpreter::execute_ast(config, session, left, span)?, Interpreter::execute_ast(config, session, right, span)?))
+        });
+        let (computed_left, computed_right) = computed?;
+
+        let result = match (computed_left.deref(), computed_right.deref()) {
+            (SmartCalcAstType::Item(_), _)           | (_, SmartCalcAstType::Item(_))           => Interpreter::calculate_item(config, session, operator, computed_left.clone(), computed_right.clone(), span)?,
+            _ => return Err(SmartCalcError::IncompatibleTypes { left: computed_left.type_name(), right: computed_right.type_name(), span })
+        };
+
+        session.trace(|| ExplainStep::Binary {
+            operator,
+            left: format_result(config, session, computed_left),
+            right: format_result(config, session, computed_right),
+            result: format_result(config, session, result.clone()),
+            steps
+        });
+        Ok(result)
+    }
+
+    fn executer_call(config: &SmartCalcConfig, session: &Session, name: &str, arguments: &[Rc<SmartCalcAstType>], span: Span) -> Result<Rc<SmartCalcAstType>, SmartCalcError> {
+        let function = match config.functions.get(name) {
+            Some(function) => function,
+            None => return Err(SmartCalcError::UnknownFunction { name: name.to_string(), span })
+        };
+
+        let mut items = Vec::new();
+        for argument in arguments.iter() {
+            let computed = Interpreter::execute_ast(config, session, argument.clone(), span)?;
+            match computed.deref() {
+                SmartCalcAstType::Item(item) => items.push(item.clone()),
+                _ => return Err(SmartCalcError::InvalidArgument { message: "Function argument not valid".to_string(), span })
+            };
+        }
+
+        let result = match function {
+            FunctionType::Internal { function_name, function } => {
+                log::debug!("Function found: {:?}", function_name);
+                function(config, session, &items).map_err(|error| error.with_span(span))?
+            },
+            FunctionType::API { arity, function } => {
+                log::debug!("Function found: {:?}", function.name());
+                if items.len() != *arity {
+                    return Err(SmartCalcError::InvalidArgument { message: format!("{} function expects {} argument(s)", name, arity), span });
+                }
+
+                let mut arguments = Vec::new();
+                for item in items.iter() {
+                    match Value::from_item(config, item.deref()) {
+                        Some(value) => arguments.push(value),
+                        None => return Err(SmartCalcError::InvalidArgument { message: "Function argument not valid".to_string(), span })
+                    };
+                }
+
+                function.call(config, &arguments).and_then(|value| value.to_item(config)).map_err(|error| error.with_span(span))?
+            }
+        };
+
+        Ok(Rc::new(SmartCalcAstType::Item(result)))
+    }
 
-        match (computed_left.deref(), computed_right.deref()) {
-            (SmartCalcAstType::Item(_), _)           | (_, SmartCalcAstType::Item(_))           => Interpreter::calculate_item(config, operator, computed_left.clone(), computed_right.clone()),
-            _ => Err("Uknown calculation result".to_string())
+    fn executer_conversion(config: &SmartCalcConfig, session: &Session, expression: Rc<SmartCalcAstType>, unit: &Unit, span: Span) -> Result<Rc<SmartCalcAstType>, SmartCalcError> {
+        let computed = Interpreter::execute_ast(config, session, expression, span)?;
+        match computed.deref() {
+            SmartCalcAstType::Item(item) => Ok(Rc::new(SmartCalcAstType::Item(QuantityItem::convert(config, item.deref(), unit).map_err(|error| error.with_span(span))?))),
+            _ => Err(SmartCalcError::IncompatibleTypes { left: computed.type_name(), right: unit.to_string(), span })
         }
     }
 
-    fn executer_unary(config: &SmartCalcConfig, session: &Session, operator: char, ast: Rc<SmartCalcAstType>) -> Result<Rc<SmartCalcAstType>, String> {
-        let computed = Interpreter::execute_ast(config, session, ast)?;
+    fn executer_unary(config: &SmartCalcConfig, session: &Session, operator: char, ast: Rc<SmartCalcAstType>, span: Span) -> Result<Rc<SmartCalcAstType>, SmartCalcError> {
+        let computed = Interpreter::execute_ast(config, session, ast, span)?;
 
         let result = match operator {
             '+' => return Ok(computed),
             '-' => match computed.deref() {
                 SmartCalcAstType::Item(item) => SmartCalcAstType::Item(item.unary(UnaryType::Minus)),
-                _ => return Err("Syntax error".to_string())
+                _ => return Err(SmartCalcError::syntax_error("Syntax error", span))
             },
-            _ => return Err("Syntax error".to_string())
+            _ => return Err(SmartCalcError::UnknownOperator { operator, span })
         };
 
         Ok(Rc::new(result))
@@ -7,40 +7,37 @@
 use core::any::{Any, TypeId};
 use alloc::format;
 use alloc::rc::Rc;
-use alloc::string::ToString;
 use alloc::string::String;
 use core::ops::Deref;
 use crate::session::Session;
 use crate::config::SmartCalcConfig;
-use crate::types::{CurrencyInfo, TokenType, NumberType};
+use crate::types::{CurrencyInfo, TokenType, NumberType, Number};
 
 use super::number::NumberItem;
-use super::{DataItem, OperationType, UnaryType};
-use crate::formatter::format_number;
-use crate::tools::do_divition;
+use super::{DataItem, OperationType, UnaryType, incompatible_types};
+use crate::error::SmartCalcError;
+use crate::formatter::format_amount;
+use crate::locale::CurrencyPlacement;
+use crate::tools::{check_divider, convert_price, do_number_addition, do_number_divition, do_number_integer_divition, do_number_modulo, do_number_multiplication, do_number_subtraction, number_from_f64, number_to_f64};
+use crate::shared::Shared;
 
 #[derive(Debug)]
 
-pub struct MoneyItem(pub f64, pub Rc<CurrencyInfo>);
+pub struct MoneyItem(pub Number, pub Shared<CurrencyInfo>);
 
 impl MoneyItem {
-    pub fn get_currency(&self) -> Rc<CurrencyInfo> {
+    pub fn get_currency(&self) -> Shared<CurrencyInfo> {
         self.1.clone()
     }
     
-    pub fn get_price(&self) -> f64 {
+    pub fn get_price(&self) -> Number {
         self.0
     }
     
-    fn convert_currency(&self, config: &SmartCalcConfig, left: &MoneyItem) -> f64 {
-        let as_usd = match config.currency_rate.get(&left.get_currency()) {
-            Some(l_rate) => do_divition(left.get_price(), *l_rate),
-            _ => 0.0
-        };
-    
-        match config.currency_rate.get(&self.get_currency()) {
-            Some(r_rate) => as_usd * r_rate,
-            _ => 0.0
+    fn convert_currency(&self, config: &SmartCalcConfig, session: &Session, left: &MoneyItem) -> Result<Number, SmartCalcError> {
+        match (config.get_line_currency_rate(session, &left.get_currency(), None), config.get_line_currency_rate(session, &self.get_currency(), None)) {
+            (Some(l_rate), Some(r_rate)) => convert_price(left.get_price(), l_rate, r_rate),
+            _ => Ok(Number::default())
         }
     }
 }
@@ -50,21 +47,27 @@ impl DataItem for MoneyItem {
         TokenType::Money(self.0, self.1.clone())
     }
     fn is_same(&self, other: &dyn Any) -> bool {
-        match other.downcast_ref::<(f64, Rc<CurrencyInfo>)>() {
-            Some((l_value, l_symbol)) => (l_value - self.0).abs() < f64::EPSILON && l_symbol.deref() == self.1.deref(),
+        match other.downcast_ref::<(Number, Shared<CurrencyInfo>)>() {
+            Some((l_value, l_symbol)) => number_to_f64(*l_value - self.0).abs() < f64::EPSILON && l_symbol.deref() == self.1.deref(),
             None => false
         }
     }
     fn as_any(&self) -> &dyn Any { self }
     
-    fn calculate(&self, config: &SmartCalcConfig, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> Option<Rc<dyn DataItem>> {
+    fn calculate(&self, config: &SmartCalcConfig, session: &Session, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
         /* If both item is money and current money is on left side, skip calculation */
         let (other_amount, target_curreny, is_other_money)  = match other.type_name() {
-            "NUMBER" => (other.get_underlying_number(), self.1.clone(), false),
-            "MONEY" => (self.convert_currency(config, other.as_any().downcast_ref::<MoneyItem>()?), self.1.clone(), true),
-            "PERCENT" => (other.get_number(self), self.1.clone(), false),
-            "DURATION" => (other.get_number(self), self.1.clone(), false),
-            _ => return None
+            "NUMBER" => (other.as_any().downcast_ref::<NumberItem>().ok_or_else(|| incompatible_types(self, other))?.0, self.1.clone(), false),
+            "MONEY" => {
+                let other_money = other.as_any().downcast_ref::<MoneyItem>().ok_or_else(|| incompatible_types(self, other))?;
+                match other_money.get_currency() == self.get_currency() {
+                    true => (other_money.get_price(), self.1.clone(), true),
+                    false => (self.convert_currency(config, session, other_money)?, self.1.clone(), true)
+                }
+            },
+            "PERCENT" => (number_from_f64(other.get_number(self))?, self.1.clone(), false),
+            "DURATION" => (number_from_f64(other.get_number(self))?, self.1.clone(), false),
+            _ => return Err(incompatible_types(self, other))
         };
         
         let (left, right) = if on_left { 
@@ -73,36 +76,58 @@ impl DataItem for MoneyItem {
             (other_amount, self.0 ) 
         };
         
+        if let OperationType::Div | OperationType::IntDiv | OperationType::Mod = operation_type {
+            check_divider(config, number_to_f64(right))?;
+        }
+
         let result = match operation_type {
-            OperationType::Add => left + right,
+            OperationType::Add => do_number_addition(left, right)?,
             OperationType::Div => {
-                let div_result = do_divition(left, right);
+                let div_result = do_number_divition(left, right)?;
                 match is_other_money {
-                    true => return Some(Rc::new(NumberItem(div_result, NumberType::Decimal))),
+                    true => return Ok(Rc::new(NumberItem(div_result, NumberType::Decimal))),
                     false => div_result
                 }
             },
-            OperationType::Mul => left * right,
-            OperationType::Sub => left - right
+            OperationType::IntDiv => {
+                let div_result = do_number_integer_divition(left, right)?;
+                match is_other_money {
+                    true => return Ok(Rc::new(NumberItem(div_result, NumberType::Decimal))),
+                    false => div_result
+                }
+            },
+            /* "$10 ^ 2" has no currency */
+            OperationType::Pow => return Err(incompatible_types(self, other)),
+            OperationType::Mul => do_number_multiplication(left, right)?,
+            OperationType::Sub => do_number_subtraction(left, right)?,
+            OperationType::Mod => do_number_modulo(left, right)
         };
-        Some(Rc::new(MoneyItem(result, target_curreny)))
+        Ok(Rc::new(MoneyItem(result, target_curreny)))
     }
     
     fn get_number(&self, other: &dyn DataItem) -> f64 {
        if self.type_name() == other.type_name() {
-           return self.0 
+           return number_to_f64(self.0)
        }
        
-       other.get_underlying_number() * self.0
+       other.get_underlying_number() * number_to_f64(self.0)
     }
     
-    fn get_underlying_number(&self) -> f64 { self.0 }
+    fn get_underlying_number(&self) -> f64 { number_to_f64(self.0) }
     fn type_name(&self) -> &'static str { "MONEY" }
     fn type_id(&self) -> TypeId { TypeId::of::<MoneyItem>() }
-    fn print(&self, config: &SmartCalcConfig, _: &Session) -> String {
+    fn print(&self, config: &SmartCalcConfig, session: &Session) -> String {
         let currency = self.get_currency();
-        let formated_price = format_number(self.get_price(), config.thousand_separator.to_string(), config.decimal_seperator.to_string(), currency.decimal_digits, config.money_config.remove_fract_if_zero, config.money_config.use_fract_rounding);
-        match (currency.symbol_on_left, currency.space_between_amount_and_symbol) {
+        let locale = session.locale(config);
+        let formated_price = format_amount(self.get_price(), &locale, currency.decimal_digits, config.money_config.remove_fract_if_zero, config.money_config.use_fract_rounding);
+        let placement = match locale.currency_placement {
+            Some(CurrencyPlacement::Left) => (true, false),
+            Some(CurrencyPlacement::LeftWithSpace) => (true, true),
+            Some(CurrencyPlacement::Right) => (false, false),
+            Some(CurrencyPlacement::RightWithSpace) => (false, true),
+            None => (currency.symbol_on_left, currency.space_between_amount_and_symbol)
+        };
+        match placement {
             (true, true) => format!("{} {}", currency.symbol, formated_price),
             (true, false) => format!("{}{}", currency.symbol, formated_price),
             (false, true) => format!("{} {}", formated_price, currency.symbol),
@@ -111,16 +136,21 @@ impl DataItem for MoneyItem {
     }
     fn unary(&self, unary: UnaryType) -> Rc<dyn DataItem> {
         match unary {
-            UnaryType::Minus => Rc::new(Self(-1.0 * self.0, self.1.clone())),
+            UnaryType::Minus => Rc::new(Self(-self.0, self.1.clone())),
             UnaryType::Plus => Rc::new(Self(self.0, self.1.clone()))
         }
     }
+
+    fn map_number(&self, mapper: &dyn Fn(Number) -> Result<Number, SmartCalcError>) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        Ok(Rc::new(Self(mapper(self.0)?, self.1.clone())))
+    }
 }
 
 
 #[cfg(test)]
 #[test]
 fn format_result_test_1() {
+    use alloc::string::ToString;
     use crate::compiler::money::MoneyItem;
     use crate::config::SmartCalcConfig;
     let config = SmartCalcConfig::default();
@@ -131,39 +161,40 @@ fn format_result_test_1() {
     let uzs = config.get_currency("uzs".to_string()).unwrap();
     let uyu = config.get_currency("uyu".to_string()).unwrap();
 
-    assert_eq!(MoneyItem(0.0, usd.clone()).print(&config, &session), "$0,00".to_string());
-    assert_eq!(MoneyItem(0.05555, usd.clone()).print(&config, &session), "$0,06".to_string());
-    assert_eq!(MoneyItem(123.05555, usd.clone()).print(&config, &session), "$123,06".to_string());
-    assert_eq!(MoneyItem(1234.05555, usd.clone()).print(&config, &session), "$1.234,06".to_string());
-    assert_eq!(MoneyItem(123456.05555, usd.clone()).print(&config, &session), "$123.456,06".to_string());
-    assert_eq!(MoneyItem(123456.0, usd.clone()).print(&config, &session), "$123.456,00".to_string());
-
-    assert_eq!(MoneyItem(0.0, tl.clone()).print(&config, &session), "₺0,00".to_string());
-    assert_eq!(MoneyItem(0.05555, tl.clone()).print(&config, &session), "₺0,06".to_string());
-    assert_eq!(MoneyItem(123.05555, tl.clone()).print(&config, &session), "₺123,06".to_string());
-    assert_eq!(MoneyItem(1234.05555, tl.clone()).print(&config, &session), "₺1.234,06".to_string());
-    assert_eq!(MoneyItem(123456.05555, tl.clone()).print(&config, &session), "₺123.456,06".to_string());
-    assert_eq!(MoneyItem(123456.0, tl.clone()).print(&config, &session), "₺123.456,00".to_string());
-
-    assert_eq!(MoneyItem(0.0, uzs.clone()).print(&config, &session), "0,00 сўм".to_string());
-    assert_eq!(MoneyItem(0.05555, uzs.clone()).print(&config, &session), "0,06 сўм".to_string());
-    assert_eq!(MoneyItem(123.05555, uzs.clone()).print(&config, &session), "123,06 сўм".to_string());
-    assert_eq!(MoneyItem(1234.05555, uzs.clone()).print(&config, &session), "1.234,06 сўм".to_string());
-    assert_eq!(MoneyItem(123456.05555, uzs.clone()).print(&config, &session), "123.456,06 сўм".to_string());
-    assert_eq!(MoneyItem(123456.0, uzs.clone()).print(&config, &session), "123.456,00 сўм".to_string());
-
-    assert_eq!(MoneyItem(0.0, uyu.clone()).print(&config, &session), "$U 0,00".to_string());
-    assert_eq!(MoneyItem(0.05555, uyu.clone()).print(&config, &session), "$U 0,06".to_string());
-    assert_eq!(MoneyItem(123.05555, uyu.clone()).print(&config, &session), "$U 123,06".to_string());
-    assert_eq!(MoneyItem(1234.05555, uyu.clone()).print(&config, &session), "$U 1.234,06".to_string());
-    assert_eq!(MoneyItem(123456.05555, uyu.clone()).print(&config, &session), "$U 123.456,06".to_string());
-    assert_eq!(MoneyItem(123456.0, uyu.clone()).print(&config, &session), "$U 123.456,00".to_string());
+    assert_eq!(MoneyItem(number!(0.0), usd.clone()).print(&config, &session), "$0,00".to_string());
+    assert_eq!(MoneyItem(number!(0.05555), usd.clone()).print(&config, &session), "$0,06".to_string());
+    assert_eq!(MoneyItem(number!(123.05555), usd.clone()).print(&config, &session), "$123,06".to_string());
+    assert_eq!(MoneyItem(number!(1234.05555), usd.clone()).print(&config, &session), "$1.234,06".to_string());
+    assert_eq!(MoneyItem(number!(123456.05555), usd.clone()).print(&config, &session), "$123.456,06".to_string());
+    assert_eq!(MoneyItem(number!(123456.0), usd.clone()).print(&config, &session), "$123.456,00".to_string());
+
+    assert_eq!(MoneyItem(number!(0.0), tl.clone()).print(&config, &session), "₺0,00".to_string());
+    assert_eq!(MoneyItem(number!(0.05555), tl.clone()).print(&config, &session), "₺0,06".to_string());
+    assert_eq!(MoneyItem(number!(123.05555), tl.clone()).print(&config, &session), "₺123,06".to_string());
+    assert_eq!(MoneyItem(number!(1234.05555), tl.clone()).print(&config, &session), "₺1.234,06".to_string());
+    assert_eq!(MoneyItem(number!(123456.05555), tl.clone()).print(&config, &session), "₺123.456,06".to_string());
+    assert_eq!(MoneyItem(number!(123456.0), tl.clone()).print(&config, &session), "₺123.456,00".to_string());
+
+    assert_eq!(MoneyItem(number!(0.0), uzs.clone()).print(&config, &session), "0,00 сўм".to_string());
+    assert_eq!(MoneyItem(number!(0.05555), uzs.clone()).print(&config, &session), "0,06 сўм".to_string());
+    assert_eq!(MoneyItem(number!(123.05555), uzs.clone()).print(&config, &session), "123,06 сўм".to_string());
+    assert_eq!(MoneyItem(number!(1234.05555), uzs.clone()).print(&config, &session), "1.234,06 сўм".to_string());
+    assert_eq!(MoneyItem(number!(123456.05555), uzs.clone()).print(&config, &session), "123.456,06 сўм".to_string());
+    assert_eq!(MoneyItem(number!(123456.0), uzs.clone()).print(&config, &session), "123.456,00 сўм".to_string());
+
+    assert_eq!(MoneyItem(number!(0.0), uyu.clone()).print(&config, &session), "$U 0,00".to_string());
+    assert_eq!(MoneyItem(number!(0.05555), uyu.clone()).print(&config, &session), "$U 0,06".to_string());
+    assert_eq!(MoneyItem(number!(123.05555), uyu.clone()).print(&config, &session), "$U 123,06".to_string());
+    assert_eq!(MoneyItem(number!(1234.05555), uyu.clone()).print(&config, &session), "$U 1.234,06".to_string());
+    assert_eq!(MoneyItem(number!(123456.05555), uyu.clone()).print(&config, &session), "$U 123.456,06".to_string());
+    assert_eq!(MoneyItem(number!(123456.0), uyu.clone()).print(&config, &session), "$U 123.456,00".to_string());
 }
 
 
 #[cfg(test)]
 #[test]
 fn format_result_test_2() {
+    use alloc::string::ToString;
     use crate::config::SmartCalcConfig;
     let mut config = SmartCalcConfig::default();
     let tl = config.get_currency("try".to_string()).unwrap();
@@ -172,15 +203,16 @@ fn format_result_test_2() {
 
     let session = Session::default();
 
-    assert_eq!(MoneyItem(0.0, tl.clone()).print(&config, &session), "₺0".to_string());
-    assert_eq!(MoneyItem(10.0, tl.clone()).print(&config, &session), "₺10".to_string());
-    assert_eq!(MoneyItem(10.1, tl.clone()).print(&config, &session), "₺10,10".to_string());
+    assert_eq!(MoneyItem(number!(0.0), tl.clone()).print(&config, &session), "₺0".to_string());
+    assert_eq!(MoneyItem(number!(10.0), tl.clone()).print(&config, &session), "₺10".to_string());
+    assert_eq!(MoneyItem(number!(10.1), tl.clone()).print(&config, &session), "₺10,10".to_string());
 }
 
 
 #[cfg(test)]
 #[test]
 fn format_result_test_3() {
+    use alloc::string::ToString;
     use crate::config::SmartCalcConfig;
     let mut config = SmartCalcConfig::default();
     let tl = config.get_currency("try".to_string()).unwrap();
@@ -189,7 +221,7 @@ fn format_result_test_3() {
 
     let session = Session::default();
 
-    assert_eq!(MoneyItem(0.0, tl.clone()).print(&config, &session), "₺0,00".to_string());
-    assert_eq!(MoneyItem(10.0, tl.clone()).print(&config, &session), "₺10,00".to_string());
-    assert_eq!(MoneyItem(10.1, tl.clone()).print(&config, &session), "₺10,10".to_string());
+    assert_eq!(MoneyItem(number!(0.0), tl.clone()).print(&config, &session), "₺0,00".to_string());
+    assert_eq!(MoneyItem(number!(10.0), tl.clone()).print(&config, &session), "₺10,00".to_string());
+    assert_eq!(MoneyItem(number!(10.1), tl.clone()).print(&config, &session), "₺10,10".to_string());
 }
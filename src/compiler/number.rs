@@ -7,38 +7,44 @@
 use core::any::{Any, TypeId};
 use alloc::format;
 use alloc::rc::Rc;
-use alloc::string::{ToString, String};
+use alloc::string::String;
 use crate::session::Session;
 use crate::config::SmartCalcConfig;
-use crate::types::{TokenType, NumberType};
+use crate::types::{TokenType, NumberType, Number};
 use super::percent::PercentItem;
-use super::{DataItem, OperationType, UnaryType};
-use crate::formatter::format_number;
-use crate::tools::do_divition;
+use super::{DataItem, OperationType, UnaryType, incompatible_types};
+use crate::error::SmartCalcError;
+use crate::formatter::format_amount;
+use crate::tools::{check_divider, do_number_addition, do_number_divition, do_number_multiplication, do_number_subtraction, do_number_integer_divition, do_number_modulo, do_number_power, number_from_f64, number_to_f64};
 
 #[derive(Debug)]
 
-pub struct NumberItem(pub f64, pub NumberType);
+pub struct NumberItem(pub Number, pub NumberType);
 impl DataItem for NumberItem {
     fn as_token_type(&self) -> TokenType {
         TokenType::Number(self.0, self.1)
     }
     fn is_same(&self, other: &dyn Any) -> bool {
-        match other.downcast_ref::<f64>() {
-            Some(value) => (value - self.0).abs() < f64::EPSILON,
+        match other.downcast_ref::<Number>() {
+            Some(value) => number_to_f64(*value - self.0).abs() < f64::EPSILON,
             None => false
         }
     }
     fn as_any(&self) -> &dyn Any { self }
-    fn calculate(&self, _: &SmartCalcConfig, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> Option<Rc<dyn DataItem>> {
+    fn calculate(&self, config: &SmartCalcConfig, session: &Session, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+        /* "2 * 5 km", units know how to scale themselves */
+        if let "QUANTITY" | "DYNAMIC_TYPE" = other.type_name() {
+            return other.calculate(config, session, !on_left, self, operation_type);
+        }
+
         let other_number  = if TypeId::of::<NumberItem>() == other.type_id() { 
-            other.get_underlying_number()
+            other.as_any().downcast_ref::<NumberItem>().ok_or_else(|| incompatible_types(self, other))?.0
             
         } else if TypeId::of::<PercentItem>() == other.type_id() { 
-            other.get_number(self)
+            number_from_f64(other.get_number(self))?
             
         } else {
-            return None;
+            return Err(incompatible_types(self, other));
         };
         
         let (left, right) = if on_left { 
@@ -47,33 +53,44 @@ impl DataItem for NumberItem {
             (other_number, self.0 ) 
         };
         
+        if let OperationType::Div | OperationType::IntDiv | OperationType::Mod = operation_type {
+            check_divider(config, number_to_f64(right))?;
+        }
+
         let result = match operation_type {
-            OperationType::Add => left + right,
-            OperationType::Div => do_divition(left, right),
-            OperationType::Mul => left * right,
-            OperationType::Sub => left - right
+            OperationType::Add => do_number_addition(left, right)?,
+            OperationType::Div => do_number_divition(left, right)?,
+            OperationType::Mul => do_number_multiplication(left, right)?,
+            OperationType::Sub => do_number_subtraction(left, right)?,
+            OperationType::Mod => do_number_modulo(left, right),
+            OperationType::Pow => do_number_power(left, right)?,
+            OperationType::IntDiv => do_number_integer_divition(left, right)?
         };
-        Some(Rc::new(NumberItem(result, self.1)))
+        Ok(Rc::new(NumberItem(result, self.1)))
     }
-    fn get_number(&self, _: &dyn DataItem) -> f64 { self.0 }
-    fn get_underlying_number(&self) -> f64 { self.0 }
+    fn get_number(&self, _: &dyn DataItem) -> f64 { number_to_f64(self.0) }
+    fn get_underlying_number(&self) -> f64 { number_to_f64(self.0) }
     fn type_name(&self) -> &'static str { "NUMBER" }
     fn type_id(&self) -> TypeId { TypeId::of::<NumberItem>() }
-    fn print(&self, config: &SmartCalcConfig, _: &Session) -> String {
+    fn print(&self, config: &SmartCalcConfig, session: &Session) -> String {
         match self.1 {
-            NumberType::Decimal     => format_number(self.0, config.thousand_separator.to_string(), config.decimal_seperator.to_string(), config.number_config.decimal_digits, config.number_config.remove_fract_if_zero, config.number_config.use_fract_rounding),
-            NumberType::Binary      => format!("{:#b}", self.0 as i32),
-            NumberType::Octal       => format!("{:#o}", self.0 as i32),
-            NumberType::Hexadecimal => format!("{:#X}", self.0 as i32),
-            NumberType::Raw         => format!("{}", self.0 as i32)
+            NumberType::Decimal     => format_amount(self.0, &session.locale(config), config.number_config.decimal_digits, config.number_config.remove_fract_if_zero, config.number_config.use_fract_rounding),
+            NumberType::Binary      => format!("{:#b}", number_to_f64(self.0) as i32),
+            NumberType::Octal       => format!("{:#o}", number_to_f64(self.0) as i32),
+            NumberType::Hexadecimal => format!("{:#X}", number_to_f64(self.0) as i32),
+            NumberType::Raw         => format!("{}", number_to_f64(self.0) as i32)
         }
     }
     fn unary(&self, unary: UnaryType) -> Rc<dyn DataItem> {
         match unary {
-            UnaryType::Minus => Rc::new(Self(-1.0 * self.0, self.1)),
+            UnaryType::Minus => Rc::new(Self(-self.0, self.1)),
             UnaryType::Plus => Rc::new(Self(self.0, self.1))
         }
     }
+
+    fn map_number(&self, mapper: &dyn Fn(Number) -> Result<Number, SmartCalcError>) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        Ok(Rc::new(Self(mapper(self.0)?, self.1)))
+    }
 }
 
 
@@ -81,18 +98,20 @@ impl DataItem for NumberItem {
 #[cfg(test)]
 #[test]
 fn format_result_test_1() {
+    use alloc::string::ToString;
     use crate::config::SmartCalcConfig;
     let config = SmartCalcConfig::default();
     let session = Session::default();
 
-    assert_eq!(NumberItem(0.0, NumberType::Decimal).print(&config, &session), "0".to_string());
-    assert_eq!(NumberItem(10.0, NumberType::Decimal).print(&config, &session), "10".to_string());
-    assert_eq!(NumberItem(10.1, NumberType::Decimal).print(&config, &session), "10,10".to_string());
+    assert_eq!(NumberItem(number!(0.0), NumberType::Decimal).print(&config, &session), "0".to_string());
+    assert_eq!(NumberItem(number!(10.0), NumberType::Decimal).print(&config, &session), "10".to_string());
+    assert_eq!(NumberItem(number!(10.1), NumberType::Decimal).print(&config, &session), "10,10".to_string());
 }
 
 #[cfg(test)]
 #[test]
 fn format_result_test_2() {
+    use alloc::string::ToString;
     use crate::config::SmartCalcConfig;
     let mut config = SmartCalcConfig::default();
     config.number_config.decimal_digits = 0;
@@ -101,15 +120,16 @@ fn format_result_test_2() {
 
     let session = Session::default();
 
-    assert_eq!(NumberItem(0.0, NumberType::Decimal).print(&config, &session), "0".to_string());
-    assert_eq!(NumberItem(10.0, NumberType::Decimal).print(&config, &session), "10".to_string());
-    assert_eq!(NumberItem(10.1, NumberType::Decimal).print(&config, &session), "10".to_string());
+    assert_eq!(NumberItem(number!(0.0), NumberType::Decimal).print(&config, &session), "0".to_string());
+    assert_eq!(NumberItem(number!(10.0), NumberType::Decimal).print(&config, &session), "10".to_string());
+    assert_eq!(NumberItem(number!(10.1), NumberType::Decimal).print(&config, &session), "10".to_string());
 }
 
 
 #[cfg(test)]
 #[test]
 fn format_result_test_3() {
+    use alloc::string::ToString;
     use crate::config::SmartCalcConfig;
     let mut config = SmartCalcConfig::default();
     config.number_config.decimal_digits = 3;
@@ -118,7 +138,7 @@ fn format_result_test_3() {
 
     let session = Session::default();
 
-    assert_eq!(NumberItem(0.0, NumberType::Decimal).print(&config, &session), "0,000".to_string());
-    assert_eq!(NumberItem(10.0, NumberType::Decimal).print(&config, &session), "10,000".to_string());
-    assert_eq!(NumberItem(10.1, NumberType::Decimal).print(&config, &session), "10,100".to_string());
+    assert_eq!(NumberItem(number!(0.0), NumberType::Decimal).print(&config, &session), "0,000".to_string());
+    assert_eq!(NumberItem(number!(10.0), NumberType::Decimal).print(&config, &session), "10,000".to_string());
+    assert_eq!(NumberItem(number!(10.1), NumberType::Decimal).print(&config, &session), "10,100".to_string());
 }
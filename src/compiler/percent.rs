@@ -6,14 +6,15 @@
 
 use core::any::{Any, TypeId};
 use alloc::rc::Rc;
-use alloc::string::{ToString, String};
+use alloc::string::String;
 use crate::session::Session;
 use crate::config::SmartCalcConfig;
-use crate::types::TokenType;
-use super::{DataItem, OperationType, UnaryType};
+use crate::types::{Number, TokenType};
+use super::{DataItem, OperationType, UnaryType, incompatible_types};
+use crate::error::SmartCalcError;
 use crate::formatter::format_number;
 use alloc::format;
-use crate::tools::do_divition;
+use crate::tools::{check_divider, do_divition, do_integer_divition, do_modulo, do_power, number_from_f64, number_to_f64};
 
 
 #[derive(Debug)]
@@ -30,9 +31,9 @@ impl DataItem for PercentItem {
         }
     }
     fn as_any(&self) -> &dyn Any { self }
-    fn calculate(&self, _: &SmartCalcConfig, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> Option<Rc<dyn DataItem>> {
+    fn calculate(&self, config: &SmartCalcConfig, _: &Session, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
         if TypeId::of::<Self>() != other.type_id() {
-            return None;
+            return Err(incompatible_types(self, other));
         }
         
         let number = other.get_underlying_number();
@@ -42,13 +43,20 @@ impl DataItem for PercentItem {
             (number, self.0 ) 
         };
         
+        if let OperationType::Div | OperationType::IntDiv | OperationType::Mod = operation_type {
+            check_divider(config, right)?;
+        }
+
         let result = match operation_type {
             OperationType::Add => left + right,
-            OperationType::Div => left / right,
+            OperationType::Div => do_divition(left, right),
             OperationType::Mul => left * right,
-            OperationType::Sub => left - right
+            OperationType::Sub => left - right,
+            OperationType::Mod => do_modulo(left, right),
+            OperationType::Pow => do_power(left, right)?,
+            OperationType::IntDiv => do_integer_divition(left, right)
         };
-        Some(Rc::new(PercentItem(result)))
+        Ok(Rc::new(PercentItem(result)))
     }
     
     fn get_number(&self, other: &dyn DataItem) -> f64 {
@@ -62,18 +70,23 @@ impl DataItem for PercentItem {
     fn get_underlying_number(&self) -> f64 { self.0 }
     fn type_name(&self) -> &'static str { "PERCENT" }
     fn type_id(&self) -> TypeId { TypeId::of::<PercentItem>() }
-    fn print(&self, config: &SmartCalcConfig, _: &Session) -> String { format!("%{:}", format_number(self.0, config.thousand_separator.to_string(), config.decimal_seperator.to_string(), config.percentage_config.decimal_digits, config.percentage_config.remove_fract_if_zero, config.percentage_config.use_fract_rounding)) }
+    fn print(&self, config: &SmartCalcConfig, session: &Session) -> String { format!("%{:}", format_number(self.0, &session.locale(config), config.percentage_config.decimal_digits, config.percentage_config.remove_fract_if_zero, config.percentage_config.use_fract_rounding)) }
     fn unary(&self, unary: UnaryType) -> Rc<dyn DataItem> {
         match unary {
             UnaryType::Minus => Rc::new(Self(-1.0 * self.0)),
             UnaryType::Plus => Rc::new(Self(self.0))
         }
     }
+
+    fn map_number(&self, mapper: &dyn Fn(Number) -> Result<Number, SmartCalcError>) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        Ok(Rc::new(Self(number_to_f64(mapper(number_from_f64(self.0)?)?))))
+    }
 }
 
 #[cfg(test)]
 #[test]
 fn format_result_test_1() {
+    use alloc::string::ToString;
     use crate::config::SmartCalcConfig;
     let config = SmartCalcConfig::default();
     let session = Session::default();
@@ -86,6 +99,7 @@ fn format_result_test_1() {
 #[cfg(test)]
 #[test]
 fn format_result_test_2() {
+    use alloc::string::ToString;
     use crate::config::SmartCalcConfig;
     let mut config = SmartCalcConfig::default();
     config.percentage_config.decimal_digits = 0;
@@ -103,6 +117,7 @@ fn format_result_test_2() {
 #[cfg(test)]
 #[test]
 fn format_result_test_3() {
+    use alloc::string::ToString;
     use crate::config::SmartCalcConfig;
     let mut config = SmartCalcConfig::default();
     config.percentage_config.decimal_digits = 3;
@@ -0,0 +1,289 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use core::any::{Any, TypeId};
+use core::ops::Deref;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use chrono::Duration;
+use crate::session::Session;
+use crate::config::SmartCalcConfig;
+use crate::types::{Number, TokenType, NumberType};
+use crate::compiler::number::NumberItem;
+use crate::compiler::duration::DurationItem;
+use crate::compiler::dynamic_type::DynamicTypeItem;
+use super::{DataItem, OperationType, UnaryType, incompatible_types};
+use crate::error::{SmartCalcError, Span};
+use crate::formatter::format_number;
+use crate::tools::{check_divider, do_divition, do_integer_divition, do_modulo, number_from_f64, number_to_f64};
+use crate::unit::{Dimension, Unit, UnitPart, TIME_DIMENSION, coherent_unit, dynamic_type_for_unit, find_unit_part};
+
+#[derive(Debug)]
+pub struct QuantityItem(pub f64, pub Rc<Unit>);
+
+/* Durations use the time unit of the other operand, otherwise the largest unit that keeps the value whole */
+fn duration_unit(config: &SmartCalcConfig, seconds: f64, hint: Option<&Unit>) -> Option<UnitPart> {
+    let time = Dimension::base(TIME_DIMENSION);
+    if let Some(part) = hint.and_then(|unit| unit.parts.iter().find(|part| part.dimension == time)) {
+        return Some(UnitPart { power: 1, ..part.clone() });
+    }
+
+    let symbol = match (seconds % 3600.0 == 0.0, seconds % 60.0 == 0.0) {
+        (true, _) => "h",
+        (false, true) => "min",
+        _ => "s"
+    };
+    find_unit_part(config, symbol)
+}
+
+fn incompatible_units(left: &Unit, right: &Unit) -> SmartCalcError {
+    SmartCalcError::IncompatibleTypes { left: left.to_string(), right: right.to_string(), span: Span::default() }
+}
+
+/* Absolute temperatures like "20 °C" can not be colder than 0 K, differences can */
+pub(crate) fn check_absolute(value: f64, unit: &Unit) -> Result<(), SmartCalcError> {
+    match unit.offset().is_some() && !unit.is_difference() && unit.to_coherent(value) < 0.0 {
+        true => Err(SmartCalcError::invalid_argument("Temperature is below absolute zero")),
+        false => Ok(())
+    }
+}
+
+impl QuantityItem {
+    /// Value and unit of quantities, dynamic types and durations.
+    pub(crate) fn from_item(config: &SmartCalcConfig, item: &dyn DataItem, hint: Option<&Unit>) -> Option<(f64, Unit)> {
+        let item = item.as_any();
+        if let Some(quantity) = item.downcast_ref::<QuantityItem>() {
+            Some((quantity.0, quantity.1.deref().clone()))
+        } else if let Some(dynamic_type) = item.downcast_ref::<DynamicTypeItem>() {
+            Some((dynamic_type.0, Unit::from(config.get_dynamic_type_unit(&dynamic_type.1)?)))
+        } else if let Some(duration) = item.downcast_ref::<DurationItem>() {
+            let seconds = duration.0.num_milliseconds() as f64 / 1000.0;
+            let part = duration_unit(config, seconds, hint)?;
+            Some((seconds / part.factor, Unit::from(part)))
+        } else {
+            None
+        }
+    }
+
+    /// Item for a calculated value. Dimensionless results become numbers and units
+    /// with a configured dynamic type are printed as that type.
+    pub(crate) fn from_value(config: &SmartCalcConfig, value: f64, unit: Unit) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        check_absolute(value, &unit)?;
+        if unit.is_dimensionless() {
+            return Ok(Rc::new(NumberItem(number_from_f64(value * unit.factor())?, NumberType::Decimal)));
+        }
+
+        Ok(match dynamic_type_for_unit(config, &unit) {
+            Some(dynamic_type) => Rc::new(DynamicTypeItem(value, dynamic_type)),
+            None => Rc::new(QuantityItem(value, Rc::new(unit)))
+        })
+    }
+
+    /// Convert a quantity, dynamic type or duration to the unit.
+    pub(crate) fn convert(config: &SmartCalcConfig, item: &dyn DataItem, unit: &Unit) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        let (value, source_unit) = QuantityItem::from_item(config, item, Some(unit)).ok_or_else(|| SmartCalcError::IncompatibleTypes { left: item.type_name().to_string(), right: unit.to_string(), span: Span::default() })?;
+        if source_unit.dimension() != unit.dimension() {
+            return Err(incompatible_units(&source_unit, unit));
+        }
+
+        /* Differences stay differences, "5 Δ°C to °F" is "9 Δ°F" */
+        let unit = match source_unit.is_difference() {
+            true => unit.difference(),
+            false => unit.clone()
+        };
+        QuantityItem::from_value(config, unit.to_unit(source_unit.to_coherent(value)), unit)
+    }
+
+    /* Absolute temperatures like "20 °C" can be moved by a difference, and two of them give the
+       difference in the coherent unit, "20 °C - 15 °C" is "5 ΔK". An absolute temperature added
+       to another one is taken as a difference on its own scale, "20 °C + 5 °C" is "25 °C" */
+    fn calculate_absolute(&self, config: &SmartCalcConfig, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        if !matches!(operation_type, OperationType::Add | OperationType::Sub) {
+            return Err(incompatible_types(self, other));
+        }
+
+        let (other_value, other_unit) = match other.type_name() {
+            "NUMBER" => (other.get_underlying_number(), None),
+            "PERCENT" => (do_divition(self.0, 100.0) * other.get_underlying_number(), None),
+            _ => {
+                let (value, unit) = QuantityItem::from_item(config, other, Some(&self.1)).ok_or_else(|| incompatible_types(self, other))?;
+                (value, Some(unit))
+            }
+        };
+
+        /* Plain numbers are differences in the unit of the temperature */
+        let other_is_absolute = other_unit.as_ref().is_some_and(|unit| unit.offset().is_some());
+        let other = (other_value, other_unit.unwrap_or_else(|| self.1.deref().clone()), other_is_absolute);
+        let this = (self.0, self.1.deref().clone(), self.1.offset().is_some());
+        let (left, right) = if on_left { (this, other) } else { (other, this) };
+
+        if left.1.dimension() != right.1.dimension() {
+            return Err(incompatible_units(&left.1, &right.1));
+        }
+
+        let (absolute, difference, sign) = match (operation_type, left.2, right.2) {
+            (OperationType::Add, true, _) => (left, right, 1.0),
+            (OperationType::Add, false, true) => (right, left, 1.0),
+            (OperationType::Sub, true, false) => (left, right, -1.0),
+            (OperationType::Sub, true, true) => {
+                let unit = coherent_unit(config, &left.1.dimension()).ok_or_else(|| incompatible_units(&left.1, &right.1))?;
+                let value = unit.to_unit(left.1.to_coherent(left.0) - right.1.to_coherent(right.0));
+                return QuantityItem::from_value(config, value, unit.difference());
+            },
+            _ => return Err(incompatible_units(&left.1, &right.1))
+        };
+
+        let value = absolute.0 + sign * difference.0 * difference.1.factor() / absolute.1.factor();
+        QuantityItem::from_value(config, value, absolute.1)
+    }
+}
+
+impl DataItem for QuantityItem {
+    fn as_token_type(&self) -> TokenType {
+        TokenType::Quantity(self.0, self.1.clone())
+    }
+    fn is_same(&self, other: &dyn Any) -> bool {
+        match other.downcast_ref::<(f64, Rc<Unit>)>() {
+            Some((l_value, l_unit)) => (l_value - self.0).abs() < f64::EPSILON && l_unit.deref() == self.1.deref(),
+            None => false
+        }
+    }
+    fn as_any(&self) -> &dyn Any { self }
+
+    fn calculate(&self, config: &SmartCalcConfig, _: &Session, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
+        let other_is_absolute = other.as_any().downcast_ref::<QuantityItem>().is_some_and(|quantity| quantity.1.offset().is_some());
+        if self.1.offset().is_some() || other_is_absolute {
+            return self.calculate_absolute(config, on_left, other, operation_type);
+        }
+
+        let is_addition = matches!(operation_type, OperationType::Add | OperationType::Sub | OperationType::Mod);
+        let (other_value, other_unit) = match other.type_name() {
+            "NUMBER" => (other.get_underlying_number(), Unit::default()),
+            "PERCENT" => match is_addition {
+                true => (do_divition(self.0, 100.0) * other.get_underlying_number(), self.1.deref().clone()),
+                false => (do_divition(other.get_underlying_number(), 100.0), Unit::default())
+            },
+            _ => QuantityItem::from_item(config, other, Some(&self.1)).ok_or_else(|| incompatible_types(self, other))?
+        };
+
+        if is_addition {
+            /* Plain numbers are taken in the unit of the quantity */
+            let other_value = match (other_unit.parts.is_empty(), other_unit.dimension() == self.1.dimension()) {
+                (true, _) => other_value,
+                (false, true) => other_value * other_unit.factor() / self.1.factor(),
+                (false, false) => return Err(incompatible_units(&self.1, &other_unit))
+            };
+
+            let (left, right) = if on_left { (self.0, other_value) } else { (other_value, self.0) };
+            let result = match operation_type {
+                OperationType::Add => left + right,
+                OperationType::Sub => left - right,
+                _ => {
+                    check_divider(config, right)?;
+                    do_modulo(left, right)
+                }
+            };
+
+            return QuantityItem::from_value(config, result, self.1.deref().clone());
+        }
+
+        if let OperationType::Pow = operation_type {
+            let power = other_value as i32;
+            return match on_left && other_unit.parts.is_empty() && power as f64 == other_value && power != 0 {
+                true => QuantityItem::from_value(config, self.0.powi(power), self.1.power(power)),
+                false => Err(incompatible_types(self, other))
+            };
+        }
+
+        let ((left, left_unit), (right, right_unit)) = match on_left {
+            true => ((self.0, self.1.deref().clone()), (other_value, other_unit)),
+            false => ((other_value, other_unit), (self.0, self.1.deref().clone()))
+        };
+
+        let (scale, unit, value) = match operation_type {
+            OperationType::Mul => {
+                let (scale, unit) = left_unit.multiply(&right_unit);
+                (scale, unit, left * right)
+            },
+            _ => {
+                check_divider(config, right)?;
+                let (scale, unit) = left_unit.multiply(&right_unit.power(-1));
+                (scale, unit, do_divition(left, right))
+            }
+        };
+
+        let value = match operation_type {
+            OperationType::IntDiv => do_integer_divition(value * scale, 1.0),
+            _ => value * scale
+        };
+
+        /* Rates like "°C/s" multiply into a difference, which is given in the coherent unit */
+        if let Some(coherent) = unit.offset().and_then(|_| coherent_unit(config, &unit.dimension())) {
+            return QuantityItem::from_value(config, value * unit.factor(), coherent.difference());
+        }
+
+        /* Sizes over rates like "4 GiB / 30 MB/s" give a duration */
+        if unit.dimension() == Dimension::base(TIME_DIMENSION) {
+            return Ok(Rc::new(DurationItem(Duration::milliseconds((value * unit.factor() * 1000.0).round() as i64))));
+        }
+
+        QuantityItem::from_value(config, value, unit)
+    }
+
+    fn get_number(&self, other: &dyn DataItem) -> f64 {
+       if self.type_name() == other.type_name() {
+           return self.0
+       }
+
+       other.get_underlying_number() * self.0
+    }
+
+    fn get_underlying_number(&self) -> f64 { self.0 }
+    fn type_name(&self) -> &'static str { "QUANTITY" }
+    fn type_id(&self) -> TypeId { TypeId::of::<QuantityItem>() }
+    fn print(&self, config: &SmartCalcConfig, session: &Session) -> String {
+        let formated_number = format_number(self.0, &session.locale(config), config.number_config.decimal_digits, config.number_config.remove_fract_if_zero, config.number_config.use_fract_rounding);
+        format!("{} {}", formated_number, self.1)
+    }
+    fn unary(&self, unary: UnaryType) -> Rc<dyn DataItem> {
+        match unary {
+            UnaryType::Minus => Rc::new(Self(-self.0, self.1.clone())),
+            UnaryType::Plus => Rc::new(Self(self.0, self.1.clone()))
+        }
+    }
+
+    fn map_number(&self, mapper: &dyn Fn(Number) -> Result<Number, SmartCalcError>) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        Ok(Rc::new(Self(number_to_f64(mapper(number_from_f64(self.0)?)?), self.1.clone())))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn quantity_calculation_test() {
+    let config = SmartCalcConfig::default();
+    let session = Session::default();
+    let quantity = |value: f64, unit: &str| QuantityItem(value, Rc::new(Unit::parse(&config, unit).unwrap()));
+
+    let distance = quantity(60.0, "km/h").calculate(&config, &session, true, &quantity(30.0, "min"), OperationType::Mul).unwrap();
+    assert_eq!(distance.print(&config, &session), "30 km".to_string());
+
+    let force = quantity(9.81, "m/s^2").calculate(&config, &session, false, &quantity(5.0, "kg"), OperationType::Mul).unwrap();
+    assert_eq!(force.print(&config, &session), "49,05 kg·m/s^2".to_string());
+    assert_eq!(QuantityItem::convert(&config, force.deref(), &Unit::parse(&config, "N").unwrap()).unwrap().print(&config, &session), "49,05 N".to_string());
+
+    let area = quantity(2.0, "m").calculate(&config, &session, true, &quantity(50.0, "cm"), OperationType::Mul).unwrap();
+    assert_eq!(area.print(&config, &session), "1 m^2".to_string());
+
+    assert!(quantity(1.0, "N").calculate(&config, &session, true, &quantity(1.0, "J"), OperationType::Add).is_err());
+    assert_eq!(quantity(1.0, "km").calculate(&config, &session, true, &quantity(500.0, "m"), OperationType::Div).unwrap().print(&config, &session), "2".to_string());
+
+    let difference = quantity(20.0, "°C").calculate(&config, &session, true, &quantity(50.0, "°F"), OperationType::Sub).unwrap();
+    assert_eq!(difference.print(&config, &session), "10 ΔK".to_string());
+    assert_eq!(QuantityItem::convert(&config, difference.deref(), &Unit::parse(&config, "°F").unwrap()).unwrap().print(&config, &session), "18 Δ°F".to_string());
+    assert_eq!(quantity(20.0, "°C").calculate(&config, &session, true, difference.deref(), OperationType::Add).unwrap().print(&config, &session), "30 °C".to_string());
+    assert!(quantity(5.0, "K").calculate(&config, &session, true, &quantity(20.0, "°C"), OperationType::Sub).is_err());
+}
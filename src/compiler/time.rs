@@ -12,10 +12,11 @@ use chrono::{Duration, Timelike, NaiveDateTime, FixedOffset};
 use chrono::TimeZone;
 use crate::session::Session;
 use crate::config::SmartCalcConfig;
-use crate::types::{TokenType, TimeOffset};
+use crate::types::{Number, TokenType, TimeOffset};
 
 use super::duration::DurationItem;
-use super::{DataItem, OperationType, UnaryType};
+use super::{DataItem, OperationType, UnaryType, incompatible_types, not_supported_by_function};
+use crate::error::SmartCalcError;
 
 #[derive(Debug)]
 
@@ -43,31 +44,31 @@ impl DataItem for TimeItem {
     }
     fn as_any(&self) -> &dyn Any { self }
     
-    fn calculate(&self, _: &SmartCalcConfig, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> Option<Rc<dyn DataItem>> {
+    fn calculate(&self, _: &SmartCalcConfig, _: &Session, on_left: bool, other: &dyn DataItem, operation_type: OperationType) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError> {
         /* If both item is money and current money is on left side, skip calculation */
         if TypeId::of::<Self>() == other.type_id() && !on_left {
-            return None;
+            return Err(incompatible_types(self, other));
         }
         
         let (right, is_negative) = match other.type_name() {
             "DURATION" => {
-                let duration = other.as_any().downcast_ref::<DurationItem>()?;
+                let duration = other.as_any().downcast_ref::<DurationItem>().ok_or_else(|| incompatible_types(self, other))?;
                 (duration.as_time(), duration.get_duration().num_seconds().is_negative())
             },
-            "TIME" => (other.as_any().downcast_ref::<TimeItem>()?.get_time(), false),
-            _ => return None
+            "TIME" => (other.as_any().downcast_ref::<TimeItem>().ok_or_else(|| incompatible_types(self, other))?.get_time(), false),
+            _ => return Err(incompatible_types(self, other))
         };
 
         let calculated_right = Duration::seconds(right.num_seconds_from_midnight() as i64);
 
         if is_negative {
-            return Some(Rc::new(TimeItem(self.0 - calculated_right, self.1.clone())));
+            return Ok(Rc::new(TimeItem(self.0 - calculated_right, self.1.clone())));
         }
         
         match operation_type {
-            OperationType::Add => Some(Rc::new(TimeItem(self.0 + calculated_right, self.1.clone()))),
-            OperationType::Sub => Some(Rc::new(TimeItem(self.0 - calculated_right, self.1.clone()))),
-            _ => None
+            OperationType::Add => Ok(Rc::new(TimeItem(self.0 + calculated_right, self.1.clone()))),
+            OperationType::Sub => Ok(Rc::new(TimeItem(self.0 - calculated_right, self.1.clone()))),
+            _ => Err(incompatible_types(self, other))
         }
     }
     
@@ -79,13 +80,17 @@ impl DataItem for TimeItem {
     fn type_name(&self) -> &'static str { "TIME" }
     fn type_id(&self) -> TypeId { TypeId::of::<TimeItem>() }
     fn print(&self, _: &SmartCalcConfig, _: &Session) -> String {
-        let tz_offset = FixedOffset::east(self.1.offset * 60);
+        let tz_offset = FixedOffset::east(self.1.offset_at(&self.0) * 60);
         let datetime = tz_offset.from_utc_datetime(&self.0);
         alloc::format!("{} {}", datetime.format("%H:%M:%S").to_string(), self.1.name)
     }
     fn unary(&self, _: UnaryType) -> Rc<dyn DataItem> {
         Rc::new(Self(self.0, self.1.clone()))
     }
+
+    fn map_number(&self, _: &dyn Fn(Number) -> Result<Number, SmartCalcError>) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        Err(not_supported_by_function(self))
+    }
 }
 
 
@@ -101,15 +106,15 @@ fn time_test() {
     assert_eq!(TimeItem(chrono::Utc::today().and_hms(15, 25, 35).naive_utc(), config.get_time_offset()).print(&config, &session), "15:25:35 UTC".to_string());
     let left = TimeItem(chrono::Utc::today().and_hms(15, 25, 35).naive_utc(), config.get_time_offset());
     let right = TimeItem(chrono::Utc::today().and_hms(1, 25, 1).naive_utc(), config.get_time_offset());
-    let result = left.calculate(&config, true, &right, OperationType::Add);
+    let result = left.calculate(&config, &session, true, &right, OperationType::Add);
     
-    assert!(result.is_some());
+    assert!(result.is_ok());
     assert_eq!(result.unwrap().deref().print(&config, &session), "16:50:36 UTC".to_string());
     
     let left = TimeItem(chrono::Utc::today().and_hms(15, 25, 35).naive_utc(), config.get_time_offset());
     let right = TimeItem(chrono::Utc::today().and_hms(1, 25, 1).naive_utc(), config.get_time_offset());
-    let result = left.calculate(&config, true, &right, OperationType::Sub);
+    let result = left.calculate(&config, &session, true, &right, OperationType::Sub);
     
-    assert!(result.is_some());
+    assert!(result.is_ok());
     assert_eq!(result.unwrap().deref().print(&config, &session), "14:00:34 UTC".to_string());
 }
\ No newline at end of file
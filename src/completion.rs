@@ -0,0 +1,351 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use core::ops::Deref;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::config::{DynamicType, SmartCalcConfig};
+use crate::session::Session;
+use crate::tokinizer::{RuleType, TokenInfo, TokenInfoStatus, Tokinizer};
+use crate::tools::IANA_TIMEZONES;
+use crate::types::{FieldType, PatternToken, SmartCalcAstType, TokenType};
+use crate::unit::Dimension;
+
+/// What a completion inserts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CompletionKind {
+    Variable,
+    Currency,
+    Unit,
+    Timezone,
+    Month,
+    Keyword
+}
+
+/// Suggested text for the word at the cursor.
+///
+/// `start` and `end` are character positions, like the ones of `UiToken`, of the text the
+/// completion replaces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub text: String,
+    pub kind: CompletionKind,
+    pub start: usize,
+    pub end: usize
+}
+
+/* Candidates by lowercase text, with the length of the longest rule prefix expecting them */
+#[derive(Default)]
+struct Candidates {
+    items: BTreeMap<(String, CompletionKind), (String, usize)>,
+    dimensions: BTreeMap<String, Dimension>
+}
+
+impl Candidates {
+    fn add<T: ToString>(&mut self, text: T, kind: CompletionKind) {
+        let text = text.to_string();
+        self.items.entry((text.to_lowercase(), kind)).or_insert((text, 0));
+    }
+
+    fn add_unit(&mut self, text: &str, dimension: Option<Dimension>) {
+        self.add(text, CompletionKind::Unit);
+        if let Some(dimension) = dimension {
+            self.dimensions.insert(text.to_lowercase(), dimension);
+        }
+    }
+
+    fn expect(&mut self, text: &str, kind: CompletionKind, score: usize) {
+        let (_, current) = self.items.entry((text.to_lowercase(), kind)).or_insert((text.to_string(), 0));
+        *current = (*current).max(score);
+    }
+
+    fn expect_kind(&mut self, kind: CompletionKind, score: usize) {
+        for ((_, item_kind), (_, current)) in self.items.iter_mut() {
+            if *item_kind == kind {
+                *current = (*current).max(score);
+            }
+        }
+    }
+
+    /* Units the value can be converted to come before the other units */
+    fn expect_dimension(&mut self, dimension: &Dimension, score: usize) {
+        for ((key, item_kind), (_, current)) in self.items.iter_mut() {
+            if *item_kind == CompletionKind::Unit && self.dimensions.get(key) == Some(dimension) {
+                *current = (*current).max(score);
+            }
+        }
+    }
+
+    /* Cities are common words, they are only offered where a timezone is expected */
+    fn expect_timezone(&mut self, score: usize) {
+        self.expect_kind(CompletionKind::Timezone, score);
+        for (key, timezone) in IANA_TIMEZONES.iter() {
+            if !key.contains('/') {
+                if let Some((_, city)) = timezone.name().rsplit_once('/') {
+                    self.expect(&city.replace('_', " "), CompletionKind::Timezone, score);
+                }
+            }
+        }
+    }
+}
+
+fn dynamic_type_dimension(config: &SmartCalcConfig, dynamic_type: &DynamicType) -> Option<Dimension> {
+    config.dynamic_type_units().get(&dynamic_type.group_name)
+        .and_then(|units| units.get(&dynamic_type.index))
+        .map(|part| part.dimension.clone())
+}
+
+fn token_dimension(config: &SmartCalcConfig, token: &TokenType) -> Option<Dimension> {
+    match token {
+        TokenType::DynamicType(_, dynamic_type) => dynamic_type_dimension(config, dynamic_type),
+        TokenType::Quantity(_, unit) => Some(unit.dimension()),
+        TokenType::Variable(variable) => match variable.data.borrow().deref().deref() {
+            SmartCalcAstType::Item(item) => token_dimension(config, &item.as_token_type()),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+fn token_matches(pattern: &PatternToken, token: &TokenInfo) -> bool {
+    match token.token_type.borrow().deref() {
+        Some(TokenType::Variable(variable)) => pattern.matches_variable(variable.data.borrow().clone()),
+        _ => pattern.matches(token)
+    }
+}
+
+/* Length of the longest pattern prefix at the end of the tokens, if the pattern continues after it */
+fn matched_prefix(pattern: &[PatternToken], tokens: &[&TokenInfo]) -> Option<usize> {
+    (1..pattern.len().min(tokens.len() + 1)).rev().find(|length| {
+        let tail = &tokens[tokens.len() - length..];
+        pattern.iter().zip(tail.iter()).all(|(pattern, token)| token_matches(pattern, token))
+    })
+}
+
+fn is_field(pattern: &PatternToken, accept: impl Fn(&FieldType) -> bool) -> bool {
+    matches!(pattern, PatternToken::Field(field) if accept(field.deref()))
+}
+
+fn expect_pattern(candidates: &mut Candidates, expected: &PatternToken, score: usize, unit: bool, dimension: Option<&Dimension>) {
+    match expected {
+        PatternToken::Text(text) => candidates.expect(text, CompletionKind::Keyword, score),
+        PatternToken::Field(field) => match field.deref() {
+            FieldType::Text(_, Some(text)) if unit => candidates.expect(text, CompletionKind::Unit, score),
+            FieldType::Text(_, Some(text)) => candidates.expect(text, CompletionKind::Keyword, score),
+
+            /* Free text fields are named after what they hold */
+            FieldType::Text(name, None) => match &name[..] {
+                "currency" => candidates.expect_kind(CompletionKind::Currency, score),
+                "type" => {
+                    candidates.expect_kind(CompletionKind::Unit, score);
+                    if let Some(dimension) = dimension {
+                        candidates.expect_dimension(dimension, score + 1);
+                    }
+                },
+                _ => ()
+            },
+            FieldType::Group(_, items) => items.iter().for_each(|item| candidates.expect(item, CompletionKind::Keyword, score)),
+            FieldType::Timezone(_) => candidates.expect_timezone(score),
+            FieldType::Month(_) => candidates.expect_kind(CompletionKind::Month, score),
+            FieldType::DynamicType(_, _) => candidates.expect_kind(CompletionKind::Unit, score),
+            FieldType::Money(_) => candidates.expect_kind(CompletionKind::Currency, score),
+            _ => ()
+        },
+        _ => ()
+    }
+}
+
+fn collect_candidates(config: &SmartCalcConfig, session: &Session, language: &str) -> Candidates {
+    let mut candidates = Candidates::default();
+
+    for variable in session.variables.borrow().values() {
+        candidates.add(variable.tokens.iter().map(|token| token.to_string()).collect::<Vec<_>>().join(" "), CompletionKind::Variable);
+    }
+
+    for currency in config.currency.values() {
+        candidates.add(&currency.code, CompletionKind::Currency);
+    }
+
+    for alias in config.currency_alias.keys() {
+        candidates.add(alias, CompletionKind::Currency);
+    }
+
+    for dynamic_type in config.types.values().flat_map(|items| items.values()) {
+        let dimension = dynamic_type_dimension(config, dynamic_type);
+        for name in dynamic_type.names.iter() {
+            candidates.add_unit(name, dimension.clone());
+        }
+    }
+
+    for timezone in config.timezones.keys() {
+        candidates.add(timezone, CompletionKind::Timezone);
+    }
+
+    for (key, timezone) in IANA_TIMEZONES.iter() {
+        if key.contains('/') {
+            candidates.add(timezone.name(), CompletionKind::Timezone);
+        }
+    }
+
+    for (_, month) in config.month_regex.get(language).into_iter().flatten() {
+        candidates.add(&month.long, CompletionKind::Month);
+        candidates.add(&month.short, CompletionKind::Month);
+    }
+
+    for words in config.word_group.get(language).into_iter().flat_map(|groups| groups.values()) {
+        for word in words.iter() {
+            candidates.add(word, CompletionKind::Keyword);
+        }
+    }
+
+    candidates
+}
+
+/* Rank the candidates with the rules and dynamic types that match the end of the text before the word */
+fn rank_candidates(config: &SmartCalcConfig, session: &Session, language: &str, text: &str, candidates: &mut Candidates) {
+    let mut probe = Session::new();
+    probe.set_language(language.to_string());
+    probe.set_text(text.to_string());
+    if let Some(locale) = session.get_locale() {
+        probe.set_locale(locale.clone());
+    }
+    *probe.variables.borrow_mut() = session.variables.borrow().clone();
+
+    let mut tokinizer = Tokinizer::new(config, &probe);
+    tokinizer.partial_tokinize();
+    let tokens = tokinizer.token_infos.iter()
+        .filter(|token| token.status.get() == TokenInfoStatus::Active && token.token_type.borrow().is_some())
+        .map(|token| token.deref())
+        .collect::<Vec<_>>();
+
+    let rule_patterns = config.get_rules(language).into_iter().flatten().flat_map(|rule| match rule {
+        RuleType::Internal { tokens_list, .. } => tokens_list.iter(),
+        RuleType::API { tokens_list, .. } => tokens_list.iter()
+    });
+
+    /* Dimension of the value before the word, like the one of "10 gb" in "10 gb to m" */
+    let dimension = |length: usize| tokens[tokens.len() - length..].iter()
+        .find_map(|token| token.token_type.borrow().as_ref().and_then(|token| token_dimension(config, token)));
+
+    /* Money is a number followed by a currency without a rule, timezones after a bare number are only for unix timestamps */
+    if tokens.last().is_some_and(|token| matches!(token.token_type.borrow().deref(), Some(TokenType::Number(..)))) {
+        candidates.expect_kind(CompletionKind::Currency, 1);
+    }
+
+    for pattern in rule_patterns {
+        if let Some(length) = matched_prefix(pattern, &tokens) {
+            let timestamp_timezone = length == 1 && is_field(&pattern[0], |field| matches!(field, FieldType::Number(_))) && is_field(&pattern[1], |field| matches!(field, FieldType::Timezone(_)));
+            if !timestamp_timezone {
+                expect_pattern(candidates, &pattern[length], length, false, dimension(length).as_ref());
+            }
+        }
+    }
+
+    for pattern in config.types.values().flat_map(|items| items.values()).flat_map(|dynamic_type| dynamic_type.parse.iter()) {
+        if let Some(length) = matched_prefix(pattern, &tokens) {
+            expect_pattern(candidates, &pattern[length], length, true, None);
+        }
+    }
+}
+
+fn is_word_letter(letter: char) -> bool {
+    letter.is_alphanumeric() || letter == '_'
+}
+
+/* Start of the word ending at "end", IANA names continue over "/" ("Europe/Ber") */
+fn word_start(characters: &[char], end: usize) -> usize {
+    let find_start = |end: usize| characters[..end].iter().rposition(|letter| !is_word_letter(*letter)).map_or(0, |index| index + 1);
+    let mut start = find_start(end);
+    while start > 0 && characters[start - 1] == '/' {
+        let area_start = find_start(start - 1);
+        if area_start == start - 1 || !characters[area_start].is_alphabetic() {
+            break;
+        }
+        start = area_start;
+    }
+    start
+}
+
+/* Starts of the words separated by spaces before the cursor, nearest first, so multi word names match as a whole */
+fn run_starts(characters: &[char], start: usize) -> Vec<usize> {
+    let mut starts = vec![start];
+    let mut end = start;
+    loop {
+        let word_end = characters[..end].iter().rposition(|letter| *letter != ' ').map_or(0, |index| index + 1);
+        if word_end == end || word_end == 0 || !is_word_letter(characters[word_end - 1]) {
+            break;
+        }
+
+        end = word_start(characters, word_end);
+        starts.push(end);
+    }
+    starts
+}
+
+fn normalize(text: &str) -> String {
+    text.to_lowercase().replace('_', " ")
+}
+
+/* Variables come first at the start of the line and after an operator, where a value is expected */
+fn value_expected(characters: &[char], start: usize) -> bool {
+    match characters[..start].iter().rev().find(|letter| !letter.is_whitespace()) {
+        Some(letter) => "=+-*/\\^%(,;".contains(*letter),
+        None => true
+    }
+}
+
+pub(crate) fn complete(config: &SmartCalcConfig, session: &Session, line: usize, cursor: usize) -> Vec<Completion> {
+    let text = match session.get_line(line) {
+        Some(text) => text,
+        None => return Vec::new()
+    };
+
+    let characters = text.chars().collect::<Vec<_>>();
+    let end = cursor.min(characters.len());
+    let start = word_start(&characters, end);
+    let word = characters[start..end].iter().collect::<String>();
+
+    if word.starts_with(|letter: char| letter.is_ascii_digit()) {
+        return Vec::new();
+    }
+
+    let language = session.get_language();
+    let mut candidates = collect_candidates(config, session, &language);
+    rank_candidates(config, session, &language, &characters[..start].iter().collect::<String>(), &mut candidates);
+
+    /* Without a word only the expected candidates are offered, otherwise the longest text before the cursor a candidate starts with */
+    let prefixes = match word.is_empty() {
+        true => Vec::new(),
+        false => run_starts(&characters, start).into_iter().rev()
+            .map(|start| (start, normalize(&characters[start..end].iter().collect::<String>())))
+            .collect::<Vec<_>>()
+    };
+
+    let mut completions = candidates.items.into_iter()
+        .filter_map(|((key, kind), (text, score))| {
+            let start = match word.is_empty() {
+                true if score > 0 => start,
+                true => return None,
+                false => {
+                    let key = normalize(&key);
+                    prefixes.iter().find(|(_, prefix)| key.starts_with(&prefix[..])).map(|(start, _)| *start)?
+                }
+            };
+            let variable_first = kind == CompletionKind::Variable && value_expected(&characters, start);
+            Some(((variable_first, score), Completion { text, kind, start, end }))
+        })
+        .collect::<Vec<_>>();
+
+    completions.sort_by(|(left_rank, left), (right_rank, right)| right_rank.cmp(left_rank)
+        .then(left.start.cmp(&right.start))
+        .then(left.text.len().cmp(&right.text.len()))
+        .then(left.kind.cmp(&right.kind))
+        .then(left.text.cmp(&right.text)));
+    completions.into_iter().map(|(_, completion)| completion).collect()
+}
@@ -6,25 +6,29 @@
 
 use core::borrow::Borrow;
 use alloc::format;
-use alloc::rc::Rc;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec::Vec;
 use alloc::collections::btree_map::BTreeMap;
+use chrono::{NaiveDate, NaiveDateTime};
 use regex::Regex;
 use serde_json::from_str;
 use crate::session::Session;
 use crate::tokinizer::RuleItemList;
 use crate::tokinizer::RuleType;
-use crate::types::CurrencyInfo;
+use crate::types::{CurrencyInfo, ExpressionFunc, PatternToken};
+use crate::rate_provider::{CurrencyRate, RateProvider};
 use crate::types::TimeOffset;
 use crate::tokinizer::Tokinizer;
-use crate::tokinizer::TokenInfo;
 use crate::tokinizer::RULE_FUNCTIONS;
+use crate::compiler::function::{FunctionType, BUILTIN_FUNCTIONS};
+use crate::unit::{Dimension, Unit, UnitDefinition, UnitPart, UnitPrefix, import_dynamic_types};
 use crate::constants::*;
+use crate::error::{ConfigDiagnostic, ConfigError, Severity};
+use crate::shared::{LazyCell, Shared, SharedCell};
 
 pub type LanguageData<T> = BTreeMap<String, T>;
-pub type CurrencyData<T> = BTreeMap<Rc<CurrencyInfo>, T>;
+pub type CurrencyData<T> = BTreeMap<Shared<CurrencyInfo>, T>;
 
 #[derive(Default)]
 #[derive(Clone)]
@@ -53,7 +57,7 @@ pub struct DynamicType {
     pub group_name: String,
     pub index: usize,
     pub format: String,
-    pub parse: Vec<Vec<Rc<TokenInfo>>>,
+    pub parse: Vec<Vec<PatternToken>>,
     pub upgrade_code: String,
     pub downgrade_code: String,
     pub names:Vec<String>,
@@ -63,7 +67,7 @@ pub struct DynamicType {
 }
 
 impl DynamicType {
-    pub fn new(group_name: String, index: usize, format: String, parse: Vec<Vec<Rc<TokenInfo>>>, upgrade_code: String, downgrade_code: String, names:Vec<String>, decimal_digits: Option<u8>, use_fract_rounding: Option<bool>, remove_fract_if_zero: Option<bool>) -> Self {
+    pub fn new(group_name: String, index: usize, format: String, parse: Vec<Vec<PatternToken>>, upgrade_code: String, downgrade_code: String, names:Vec<String>, decimal_digits: Option<u8>, use_fract_rounding: Option<bool>, remove_fract_if_zero: Option<bool>) -> Self {
         DynamicType {
             group_name,
             index,
@@ -79,21 +83,88 @@ impl DynamicType {
     }
 }
 
+/* Logs the problem as "load_from_json" always did, and keeps it for "validate_json" */
+fn report(diagnostics: &mut Vec<ConfigDiagnostic>, severity: Severity, path: String, message: String) {
+    match severity {
+        Severity::Error => log::error!("{}", message),
+        Severity::Warning => log::warn!("{}", message)
+    };
+    diagnostics.push(ConfigDiagnostic { path, severity, message });
+}
+
+/* "$.currency_alias.tl", keys that are not plain words are quoted like "$.currency_alias[\"₺\"]" */
+fn json_path(parent: &str, key: &str) -> String {
+    match !key.is_empty() && key.chars().all(|ch| ch.is_alphanumeric() || ch == '_') {
+        true => format!("{}.{}", parent, key),
+        false => format!("{}[{}]", parent, serde_json::to_string(key).unwrap_or_default())
+    }
+}
+
+/// Entry of a configuration pack, like the `xyz` entry of `currencies`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeEntry {
+    pub section: String,
+    pub name: String
+}
+
+/// Entry of a configuration pack that was skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    pub section: String,
+    pub name: String,
+    pub reason: String
+}
+
+/// Result of [`crate::SmartCalc::merge_json`]. Loaded entries are never replaced, entries
+/// conflicting with them are skipped and reported.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    pub added: Vec<MergeEntry>,
+    pub conflicts: Vec<MergeConflict>
+}
+
+impl MergeReport {
+    fn apply<T: ToString>(&mut self, section: &str, name: T, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.added.push(MergeEntry { section: section.to_string(), name: name.to_string() }),
+            Err(reason) => self.conflicts.push(MergeConflict { section: section.to_string(), name: name.to_string(), reason })
+        };
+    }
+}
+
+/// Rules of a language. Their patterns are tokinized on first use, so only the languages
+/// that are executed pay for it.
+#[derive(Default)]
+pub(crate) struct LanguageRules {
+    sources: Vec<(String, ExpressionFunc, Vec<String>)>,
+    rules: LazyCell<RuleItemList>
+}
+
 pub struct SmartCalcConfig {
     pub(crate) json_data: JsonConstant,
     pub(crate) format: LanguageData<JsonFormat>,
-    pub(crate) currency: LanguageData<Rc<CurrencyInfo>>,
-    pub(crate) currency_alias: LanguageData<Rc<CurrencyInfo>>,
+    pub(crate) currency: LanguageData<Shared<CurrencyInfo>>,
+    pub(crate) currency_alias: LanguageData<Shared<CurrencyInfo>>,
     pub(crate) timezones: BTreeMap<String, i32>,
     pub(crate) currency_rate: CurrencyData<f64>,
+    pub(crate) currency_rate_history: CurrencyData<BTreeMap<NaiveDate, f64>>,
+    pub(crate) rate_provider: Option<Shared<dyn RateProvider>>,
+    pub(crate) rate_cache: SharedCell<CurrencyData<Option<CurrencyRate>>>,
+
     pub(crate) token_parse_regex: LanguageData<Vec<Regex>>,
     pub(crate) word_group: LanguageData<BTreeMap<String, Vec<String>>>,
     pub(crate) constant_pair: LanguageData<BTreeMap<String, ConstantType>>,
     pub(crate) language_alias_regex: LanguageData<Vec<(Regex, String)>>,
     pub(crate) alias_regex: Vec<(Regex, String)>,
-    pub(crate) rule: LanguageData<RuleItemList>,
-    pub(crate) types: BTreeMap<String, BTreeMap<usize, Rc<DynamicType>>>,
+    pub(crate) rule: LanguageData<LanguageRules>,
+    pub(crate) rule_samples: LanguageData<Vec<(String, Sample)>>,
+    pub(crate) functions: BTreeMap<String, FunctionType>,
+    pub(crate) types: BTreeMap<String, BTreeMap<usize, Shared<DynamicType>>>,
     pub(crate) type_conversion: Vec<JsonTypeConversion>,
+    pub(crate) units: BTreeMap<String, Shared<UnitDefinition>>,
+    pub(crate) unit_names: BTreeMap<String, Shared<UnitDefinition>>,
+    pub(crate) unit_prefixes: Vec<UnitPrefix>,
+    dynamic_type_units: LazyCell<BTreeMap<String, BTreeMap<usize, UnitPart>>>,
     pub(crate) month_regex: LanguageData<MonthItemList>,
     pub(crate) money_config: MoneyConfig,
     pub(crate) number_config: NumberConfig,
@@ -101,7 +172,8 @@ pub struct SmartCalcConfig {
     pub(crate) decimal_seperator: String,
     pub(crate) thousand_separator: String,
     pub(crate) timezone: String,
-    pub(crate) timezone_offset: i32
+    pub(crate) timezone_offset: i32,
+    pub(crate) division_by_zero_as_zero: bool
 }
 
 impl Default for SmartCalcConfig {
@@ -118,36 +190,137 @@ impl SmartCalcConfig {
         }
     }
 
-    pub fn get_currency<T: Borrow<String>>(&self, currency: T) -> Option<Rc<CurrencyInfo>> {
+    pub fn get_currency<T: Borrow<String>>(&self, currency: T) -> Option<Shared<CurrencyInfo>> {
         self.currency
             .get(currency.borrow())
             .cloned()
     }
 
+    /// USD based rate of the currency. With a date, the closest rate on or before that date is used.
+    ///
+    /// Currencies without dated rates always use their current rate, which comes from the rate
+    /// provider when there is one and from the built-in rates otherwise.
+    pub fn get_currency_rate(&self, currency: &Shared<CurrencyInfo>, date: Option<NaiveDate>) -> Option<f64> {
+        self.find_currency_rate(currency, date).map(|(rate, _)| rate)
+    }
+
+    /// Same as `get_currency_rate`, the rate is also recorded as used by the executing line of the session.
+    pub(crate) fn get_line_currency_rate(&self, session: &Session, currency: &Shared<CurrencyInfo>, date: Option<NaiveDate>) -> Option<f64> {
+        let (rate, as_of) = self.find_currency_rate(currency, date)?;
+        session.use_rate(&currency.code, rate, as_of);
+        Some(rate)
+    }
+
+    /* Rate and its publish time, built-in rates have none */
+    fn find_currency_rate(&self, currency: &Shared<CurrencyInfo>, date: Option<NaiveDate>) -> Option<(f64, Option<NaiveDateTime>)> {
+        if let (Some(date), Some(history)) = (date, self.currency_rate_history.get(currency)) {
+            let (rate_date, rate) = history.range(..=date).next_back()?;
+            return Some((*rate, Some(rate_date.and_hms_opt(0, 0, 0).unwrap_or_default())));
+        }
+
+        let cached = self.rate_cache.with(|cache| cache.get(currency).copied());
+        let rate = match (cached, &self.rate_provider) {
+            (Some(rate), _) => rate,
+            (None, Some(provider)) => {
+                let rate = provider.get_rate(&currency.code);
+                self.rate_cache.with(|cache| cache.insert(currency.clone(), rate));
+                rate
+            },
+            (None, None) => None
+        };
+
+        match rate {
+            Some(rate) => Some((rate.rate, Some(rate.as_of))),
+            None => self.currency_rate.get(currency).map(|rate| (*rate, None))
+        }
+    }
+
+    pub(crate) fn get_dynamic_type_unit(&self, dynamic_type: &DynamicType) -> Option<UnitPart> {
+        self.dynamic_type_units().get(&dynamic_type.group_name)?.get(&dynamic_type.index).cloned()
+    }
+
+    /* Imported on first use, constructing a calculator does not execute the codes of the types */
+    pub(crate) fn dynamic_type_units(&self) -> &BTreeMap<String, BTreeMap<usize, UnitPart>> {
+        self.dynamic_type_units.get_or_init(|| import_dynamic_types(self))
+    }
+
+    /// Types, units or conversions changed, the units of the types are imported again on next use.
+    pub(crate) fn reset_dynamic_type_units(&mut self) {
+        self.dynamic_type_units = LazyCell::default();
+    }
+
+    pub(crate) fn get_rules(&self, language: &str) -> Option<&RuleItemList> {
+        let language_rules = self.rule.get(language)?;
+        Some(language_rules.rules.get_or_init(|| language_rules.sources.iter().map(|(function_name, function, patterns)| RuleType::Internal {
+            function_name: function_name.to_string(),
+            function: *function,
+            tokens_list: patterns.iter().map(|pattern| self.pattern(language, pattern)).collect()
+        }).collect()))
+    }
+
+    pub(crate) fn get_rules_mut(&mut self, language: &str) -> Option<&mut RuleItemList> {
+        self.get_rules(language)?;
+        self.rule.get_mut(language)?.rules.get_mut()
+    }
+
+    pub(crate) fn pattern(&self, language: &str, pattern: &str) -> Vec<PatternToken> {
+        let mut session = Session::new();
+        session.set_language(language.to_string());
+        session.set_text(pattern.to_string());
+        Tokinizer::pattern(self, &session)
+    }
+
     pub fn load_from_json(json_data: &str) -> Self {
+        match from_str(json_data) {
+            Ok(data) => SmartCalcConfig::load(data, &mut Vec::new()),
+            Err(error) => panic!("JSON parse error: {}", error)
+        }
+    }
+
+    /// Check a configuration without using it. Problems that `load_from_json` would only log,
+    /// like invalid regexes or unknown currencies, are returned with the JSON path of the entry.
+    /// A valid configuration gives an empty list.
+    pub fn validate_json(json_data: &str) -> Vec<ConfigDiagnostic> {
+        let mut diagnostics = Vec::new();
+        match from_str(json_data) {
+            Ok(data) => { SmartCalcConfig::load(data, &mut diagnostics); },
+            Err(error) => diagnostics.push(ConfigDiagnostic { path: "$".to_string(), severity: Severity::Error, message: error.to_string() })
+        };
+        diagnostics
+    }
+
+    fn load(json_data: JsonConstant, diagnostics: &mut Vec<ConfigDiagnostic>) -> Self {
         let mut config = SmartCalcConfig {
-            json_data: match from_str(json_data) {
-                Ok(data) => data,
-                Err(error) => panic!("JSON parse error: {}", error)
-            },
+            json_data,
             format: LanguageData::new(),
             currency: LanguageData::new(),
             currency_alias: LanguageData::new(),
             timezones: BTreeMap::new(),
             currency_rate: CurrencyData::new(),
+            currency_rate_history: CurrencyData::new(),
+            rate_provider: None,
+            rate_cache: SharedCell::new(CurrencyData::new()),
+
             token_parse_regex: LanguageData::new(),
             word_group: LanguageData::new(),
             constant_pair: LanguageData::new(),
             language_alias_regex: LanguageData::new(),
             rule: LanguageData::new(),
+            rule_samples: LanguageData::new(),
+            functions: BTreeMap::new(),
             types: BTreeMap::new(),
             type_conversion: Vec::new(),
+            units: BTreeMap::new(),
+            unit_names: BTreeMap::new(),
+            unit_prefixes: Vec::new(),
+            dynamic_type_units: LazyCell::default(),
             month_regex: LanguageData::new(),
             alias_regex: Vec::new(),
             decimal_seperator: ",".to_string(),
             thousand_separator: ".".to_string(),
             timezone: "UTC".to_string(),
             timezone_offset: 0,
+            division_by_zero_as_zero: false,
             money_config: MoneyConfig {
                 remove_fract_if_zero: false,
                 use_fract_rounding: true
@@ -164,231 +337,383 @@ impl SmartCalcConfig {
             },
         };
         
-        for (name, currency) in config.json_data.currencies.iter() {
+        let json_data = config.json_data.clone();
+        for (name, currency) in json_data.currencies.iter() {
             config.currency.insert(name.to_lowercase(), currency.clone());
         }
 
-        for (timezone, offset) in config.json_data.timezones.iter() {
+        for (name, function) in BUILTIN_FUNCTIONS.iter() {
+            config.functions.insert(name.to_string(), FunctionType::Internal {
+                function_name: name.to_string(),
+                function: *function
+            });
+        }
+
+        for (timezone, offset) in json_data.timezones.iter() {
             config.timezones.insert(timezone.clone(), *offset);
         }
 
-        for (from, to) in config.json_data.alias.iter() {
+        for (from, to) in json_data.alias.iter() {
             match Regex::new(&format!(r"\b{}\b", from)) {
                 Ok(re) => config.alias_regex.push((re, to.to_string())),
-                Err(error) => log::error!("Alias parser error ({}) {}", from, error)
+                Err(error) => report(diagnostics, Severity::Error, json_path("$.alias", from), format!("Alias parser error ({}) {}", from, error))
             }
         }
 
-        for (language, language_object) in config.json_data.languages.iter() {
-            let mut language_clone = language_object.format.clone();
-            language_clone.language = language.to_string();
-            config.format.insert(language.to_string(), language_clone);
-        }
-
-        for (key, value) in config.json_data.currency_alias.iter() {
+        for (key, value) in json_data.currency_alias.iter() {
             match config.get_currency(value) {
                 Some(currency) => { config.currency_alias.insert(key.to_string(), currency.clone()); },
-                None => log::warn!("'{}' currency not found at alias", value)
+                None => report(diagnostics, Severity::Warning, json_path("$.currency_alias", key), format!("'{}' currency not found at alias", value))
             };
         }
 
-        for (key, value) in config.json_data.currency_rates.iter() {
+        for (key, value) in json_data.currency_rates.iter() {
             match config.get_currency(key) {
                 Some(currency) => { config.currency_rate.insert(currency.clone(), *value); },
-                None => log::warn!("'{}' currency not found at rate", key)
+                None => report(diagnostics, Severity::Warning, json_path("$.currency_rates", key), format!("'{}' currency not found at rate", key))
             };
         }
 
-        for (language, language_constant) in config.json_data.languages.iter() {
-            let mut language_aliases = Vec::new();
-            for (alias, target_name) in language_constant.alias.iter() {
-                
-                match Regex::new(&format!(r"\b{}\b", alias)) {
-                    Ok(re) => language_aliases.push((re, target_name.to_string())),
-                    Err(error) => log::error!("Alias parser error ({}) {}", alias, error)
-                }
-
-            }
-
-            config.language_alias_regex.insert(language.to_string(), language_aliases);
-        }
-        
-        for (parse_type, items) in &config.json_data.parse {
+        for (parse_type, items) in &json_data.parse {
             let mut patterns = Vec::new();
-            for pattern in items {
+            for (index, pattern) in items.iter().enumerate() {
                 match Regex::new(pattern) {
                     Ok(re) => patterns.push(re),
-                    Err(error) => log::error!("Token parse regex error ({}) {}", pattern, error)
+                    Err(error) => report(diagnostics, Severity::Error, format!("{}[{}]", json_path("$.parse", parse_type), index), format!("Token parse regex error ({}) {}", pattern, error))
                 }
             }
 
             config.token_parse_regex.insert(parse_type.to_string(), patterns);
         }
 
-        for (language, language_constant) in config.json_data.languages.iter() {
-            let mut language_group = Vec::new();
-            let mut month_list = Vec::with_capacity(12);
-            for i in 0..12 {
-                month_list.push(MonthInfo {
-                    short: String::new(),
-                    long: String::new(),
-                    month: i + 1
-                });
+        for (language, language_constant) in json_data.languages.iter() {
+            config.load_language(language, language_constant, diagnostics);
+        }
+
+        for (type_index, dynamic_type) in json_data.types.iter().enumerate() {
+            config.types.entry(dynamic_type.name.to_string()).or_default();
+            for (item_index, type_item) in dynamic_type.items.iter().enumerate() {
+                if let Err(error) = config.load_dynamic_type_item(&dynamic_type.name, type_item) {
+                    report(diagnostics, Severity::Warning, format!("$.types[{}].items[{}]", type_index, item_index), error);
+                }
             }
+        }
+        
+        for prefix in json_data.unit_prefixes.iter() {
+            config.unit_prefixes.push(UnitPrefix {
+                symbol: prefix.symbol.to_string(),
+                name: prefix.name.to_lowercase(),
+                factor: prefix.factor,
+                binary: prefix.binary
+            });
+        }
 
-            for (month_name, month_number) in &language_constant.long_months {
-                match month_list.get_mut((*month_number - 1) as usize) {
-                    Some(month_object) => month_object.long = month_name.to_string(),
-                    None => log::warn!("Month not fetched. {}", month_number)
-                };
+        for (index, unit) in json_data.units.iter().enumerate() {
+            if let Err(error) = config.load_unit(unit) {
+                report(diagnostics, Severity::Warning, format!("$.units[{}]", index), error);
             }
+        }
 
-            for (month_name, month_number) in &language_constant.short_months {
-                match month_list.get_mut((*month_number - 1) as usize) {
-                    Some(month_object) => month_object.short = month_name.to_string(),
-                    None => log::warn!("Month not fetched. {}", month_number)
-                };
+        for (index, type_conversion) in json_data.type_conversion.iter().enumerate() {
+            if let Err((field, error)) = config.load_type_conversion(type_conversion) {
+                report(diagnostics, Severity::Warning, format!("$.type_conversion[{}].{}", index, field), error);
             }
+        }
 
-            for month in month_list.iter() {
-                let pattern = &format!(r"\b{}\b|\b{}\b", month.long, month.short);
-                match Regex::new(pattern) {
-                    Ok(re) => language_group.push((re, month.clone())),
-                    Err(error) => log::error!("Month parser error ({}) {}", month.long, error)
+        config
+    }
+
+    /// Layer a configuration pack onto the loaded configuration, see [`crate::SmartCalc::merge_json`].
+    pub(crate) fn merge_json(&mut self, json_data: &str) -> Result<MergeReport, ConfigError> {
+        let value: serde_json::Value = from_str(json_data).map_err(|error| ConfigError::InvalidJson { message: error.to_string() })?;
+        match value.as_object() {
+            Some(sections) => if let Some(name) = sections.keys().find(|name| !JSON_PACK_SECTIONS.contains(&&name[..])) {
+                return Err(ConfigError::UnsupportedSection { name: name.to_string() });
+            },
+            None => return Err(ConfigError::InvalidJson { message: "configuration pack should be an object".to_string() })
+        };
+
+        let pack: JsonConfigPack = serde_json::from_value(value).map_err(|error| ConfigError::InvalidJson { message: error.to_string() })?;
+        let mut report = MergeReport::default();
+
+        for (name, currency) in pack.currencies.iter() {
+            let result = match self.currency.contains_key(&name.to_lowercase()) {
+                true => Err("currency already defined".to_string()),
+                false => {
+                    self.currency.insert(name.to_lowercase(), currency.clone());
+                    Ok(())
                 }
-            }
+            };
+            report.apply("currencies", name, result);
+        }
 
-            config.month_regex.insert(language.to_string(), language_group);
+        for (name, rate) in pack.currency_rates.iter() {
+            let result = match self.get_currency(name.to_lowercase()) {
+                Some(currency) if self.currency_rate.contains_key(&currency) => Err("currency already has a rate".to_string()),
+                Some(currency) => {
+                    self.currency_rate.insert(currency, *rate);
+                    Ok(())
+                },
+                None => Err("currency not found".to_string())
+            };
+            report.apply("currency_rates", name, result);
         }
 
-        for (language, language_constant) in config.json_data.languages.iter() {
-            let mut word_groups = BTreeMap::new();
-            for (word_group_name, word_group_items) in language_constant.word_group.iter() {
-                let mut patterns = Vec::new();
+        for (alias, name) in pack.currency_alias.iter() {
+            let result = match (self.currency_alias.contains_key(&alias.to_lowercase()), self.get_currency(name.to_lowercase())) {
+                (true, _) => Err("alias already defined".to_string()),
+                (false, None) => Err(format!("'{}' currency not found", name)),
+                (false, Some(currency)) => {
+                    self.currency_alias.insert(alias.to_lowercase(), currency);
+                    Ok(())
+                }
+            };
+            report.apply("currency_alias", alias, result);
+        }
 
-                for pattern in word_group_items {
-                    patterns.push(pattern.to_string());
+        for (timezone, offset) in pack.timezones.iter() {
+            let result = match self.timezones.contains_key(timezone) {
+                true => Err("timezone already defined".to_string()),
+                false => {
+                    self.timezones.insert(timezone.to_string(), *offset);
+                    Ok(())
                 }
+            };
+            report.apply("timezones", timezone, result);
+        }
 
-                word_groups.insert(word_group_name.to_string(), patterns);
+        for (language, language_constant) in pack.languages.iter() {
+            let invalid_month = language_constant.long_months.iter().chain(language_constant.short_months.iter()).find(|(_, month)| !(1..=12).contains(*month));
+            let result = match (self.format.contains_key(language), invalid_month) {
+                (true, _) => Err("language already defined".to_string()),
+                (false, Some((name, month))) => Err(format!("'{}' has invalid month number ({})", name, month)),
+                (false, None) => {
+                    self.load_language(language, language_constant, &mut Vec::new());
+                    Ok(())
+                }
+            };
+            report.apply("languages", language, result);
+        }
+
+        for dynamic_type in pack.types.iter() {
+            for type_item in dynamic_type.items.iter() {
+                let result = match self.types.get(&dynamic_type.name).is_some_and(|group| group.contains_key(&type_item.index)) {
+                    true => Err("dynamic type already defined".to_string()),
+                    false => self.load_dynamic_type_item(&dynamic_type.name, type_item)
+                };
+                report.apply("types", format!("{}:{}", dynamic_type.name, type_item.index), result);
             }
+        }
 
-            config.word_group.insert(language.to_string(), word_groups);
+        for unit in pack.units.iter() {
+            let result = match (self.units.contains_key(&unit.symbol), unit.names.iter().find(|name| self.unit_names.contains_key(&name.to_lowercase()))) {
+                (true, _) => Err("unit already defined".to_string()),
+                (false, Some(name)) => Err(format!("'{}' is already used by another unit", name)),
+                (false, None) => self.load_unit(unit)
+            };
+            report.apply("units", &unit.symbol, result);
         }
 
-        for (language, language_constant) in config.json_data.languages.iter() {
-            let mut constants = BTreeMap::new();
-            for (alias_name, constant_type) in language_constant.constant_pair.iter() {
+        for type_conversion in pack.type_conversion.iter() {
+            let (source, target) = (&type_conversion.source, &type_conversion.target);
+            let is_defined = self.type_conversion.iter().any(|item| item.source.name == source.name && item.source.index == source.index && item.target.name == target.name && item.target.index == target.index);
+            let result = match is_defined {
+                true => Err("conversion already defined".to_string()),
+                false => self.load_type_conversion(type_conversion).map_err(|(_, error)| error)
+            };
+            report.apply("type_conversion", format!("{}:{} to {}:{}", source.name, source.index, target.name, target.index), result);
+        }
 
-                match ConstantType::from_u8(*constant_type) {
-                    Some(const_type) => {
-                        constants.insert(alias_name.to_string(), const_type);
-                    },
-                    _ => log::error!("Constant type not parsed. {}", constant_type)
-                };
+        if report.added.iter().any(|entry| matches!(&entry.section[..], "types" | "units" | "type_conversion")) {
+            self.reset_dynamic_type_units();
+        }
+
+        Ok(report)
+    }
+
+    fn load_language(&mut self, language: &str, language_constant: &JsonLanguageConstant, diagnostics: &mut Vec<ConfigDiagnostic>) {
+        let path = json_path("$.languages", language);
+        let mut format = language_constant.format.clone();
+        format.language = language.to_string();
+        self.format.insert(language.to_string(), format);
+
+        let mut language_aliases = Vec::new();
+        for (alias, target_name) in language_constant.alias.iter() {
+            match Regex::new(&format!(r"\b{}\b", alias)) {
+                Ok(re) => language_aliases.push((re, target_name.to_string())),
+                Err(error) => report(diagnostics, Severity::Error, json_path(&format!("{}.alias", path), alias), format!("Alias parser error ({}) {}", alias, error))
             }
+        }
+
+        self.language_alias_regex.insert(language.to_string(), language_aliases);
 
-            config.constant_pair.insert(language.to_string(), constants);
+        let mut language_group = Vec::new();
+        let mut month_list = Vec::with_capacity(12);
+        for i in 0..12 {
+            month_list.push(MonthInfo {
+                short: String::new(),
+                long: String::new(),
+                month: i + 1
+            });
         }
-        
-        for (language, language_constant) in config.json_data.languages.iter() {
-            let mut language_rules = Vec::new();
-            for (rule_name, rule) in language_constant.rules.iter() {
-                if let Some(function_ref) = RULE_FUNCTIONS.get(rule_name) {
-                    let mut function_items = Vec::new();
-
-                    for rule_item in &rule.rules {
-                        let mut session = Session::new();
-                        session.set_language(language.to_string());
-                        session.set_text(rule_item.to_string());
-                        function_items.push(Tokinizer::token_infos(&config, &session));
-                    }
-
-                    language_rules.push(RuleType::Internal {
-                        function_name: rule_name.to_string(),
-                        function: *function_ref,
-                        tokens_list: function_items
-                    });
-                }
-                else {
-                    log::warn!("Function not found : {}", rule_name);
-                }
-            }
 
-            config.rule.insert(language.to_string(), language_rules);
+        for (month_name, month_number) in &language_constant.long_months {
+            match month_list.get_mut((*month_number as usize).wrapping_sub(1)) {
+                Some(month_object) => month_object.long = month_name.to_string(),
+                None => report(diagnostics, Severity::Warning, json_path(&format!("{}.long_months", path), month_name), format!("Month not fetched. {}", month_number))
+            };
         }
-        
-        for dynamic_type in config.json_data.types.iter() {
-            let mut dynamic_type_holder = BTreeMap::new();
-            
-            for type_item in dynamic_type.items.iter() {
-                
-                if type_item.upgrade_code.is_none() || type_item.downgrade_code.is_none() {
-                    log::warn!("Dynamic type {}:{} has missing calculation code. Please check upgrade_code and downgrade_code fields", dynamic_type.name, type_item.index);
-                    continue;
-                }
-                
-                let upgrade_code = type_item.upgrade_code.as_ref().map_or(String::new(), |item| item.to_string());
-                let downgrade_code = type_item.downgrade_code.as_ref().map_or(String::new(), |item| item.to_string());
-
-                let mut token_info = DynamicType {
-                    group_name: dynamic_type.name.to_string(),
-                    index: type_item.index,
-                    format: type_item.format.to_string(),
-                    parse: Vec::new(),
-                    upgrade_code,
-                    downgrade_code,
-                    names: type_item.names.clone(),
-                    decimal_digits: type_item.decimal_digits,
-                    use_fract_rounding: type_item.use_fract_rounding,
-                    remove_fract_if_zero: type_item.remove_fract_if_zero
-                };
 
-                for type_parse_item in type_item.parse.iter() {
-                    let mut session = Session::new();
-                    session.set_language("en".to_string());
-                    session.set_text(type_parse_item.to_string());
-                    
-                    let tokens = Tokinizer::token_infos(&config, &session);
-                    token_info.parse.push(tokens);
-                }
-                
-                dynamic_type_holder.insert(token_info.index, Rc::new(token_info));
+        for (month_name, month_number) in &language_constant.short_months {
+            match month_list.get_mut((*month_number as usize).wrapping_sub(1)) {
+                Some(month_object) => month_object.short = month_name.to_string(),
+                None => report(diagnostics, Severity::Warning, json_path(&format!("{}.short_months", path), month_name), format!("Month not fetched. {}", month_number))
+            };
+        }
+
+        for month in month_list.iter() {
+            let pattern = &format!(r"\b{}\b|\b{}\b", month.long, month.short);
+            match Regex::new(pattern) {
+                Ok(re) => language_group.push((re, month.clone())),
+                Err(error) => report(diagnostics, Severity::Error, json_path(&format!("{}.long_months", path), &month.long), format!("Month parser error ({}) {}", month.long, error))
             }
-            
-            config.types.insert(dynamic_type.name.to_string(), dynamic_type_holder);
         }
-        
-        for type_conversion in config.json_data.type_conversion.iter() {
-            let source = config.types.get(&type_conversion.source.name);
-            let target = config.types.get(&type_conversion.target.name);
 
-            let mut source_found = false;
-            let mut target_found = false;
+        self.month_regex.insert(language.to_string(), language_group);
 
-            if let Some(source) = source {
-                source_found = source.contains_key(&type_conversion.source.index)
+        let mut word_groups = BTreeMap::new();
+        for (word_group_name, word_group_items) in language_constant.word_group.iter() {
+            word_groups.insert(word_group_name.to_string(), word_group_items.clone());
+        }
 
-            }
+        self.word_group.insert(language.to_string(), word_groups);
 
-            if let Some(target) = target {
-                target_found = target.contains_key(&type_conversion.target.index)
+        let mut constants = BTreeMap::new();
+        for (alias_name, constant_type) in language_constant.constant_pair.iter() {
+            match ConstantType::from_u8(*constant_type) {
+                Some(const_type) => {
+                    constants.insert(alias_name.to_string(), const_type);
+                },
+                _ => report(diagnostics, Severity::Error, json_path(&format!("{}.constant_pair", path), alias_name), format!("Constant type not parsed. {}", constant_type))
+            };
+        }
 
-            }
+        self.constant_pair.insert(language.to_string(), constants);
 
-            if !source_found {
-                log::warn!("{} type not defined", type_conversion.source.name);
-            }
+        let mut language_rules = LanguageRules::default();
+        let mut rule_samples = Vec::new();
+        let mut rules = language_constant.rules.iter().collect::<Vec<_>>();
+        rules.sort_by_key(|(_, rule)| core::cmp::Reverse(rule.priority));
 
-            if !target_found {
-                log::warn!("{} type not defined", type_conversion.target.name);
+        for (rule_name, rule) in rules {
+            for sample in rule.samples.iter() {
+                rule_samples.push((rule_name.to_string(), sample.clone()));
             }
-            
-            if source_found && target_found {
-                config.type_conversion.push(type_conversion.clone());
+
+            match RULE_FUNCTIONS.get(rule_name) {
+                Some(function_ref) => language_rules.sources.push((rule_name.to_string(), *function_ref, rule.rules.clone())),
+                None => report(diagnostics, Severity::Warning, json_path(&format!("{}.rules", path), rule_name), format!("Function not found : {}", rule_name))
+            };
+        }
+
+        self.rule.insert(language.to_string(), language_rules);
+        self.rule_samples.insert(language.to_string(), rule_samples);
+    }
+
+    fn load_dynamic_type_item(&mut self, name: &str, type_item: &JsonDynamicTypeItem) -> Result<(), String> {
+        let (upgrade_code, downgrade_code) = match (&type_item.upgrade_code, &type_item.downgrade_code) {
+            (Some(upgrade_code), Some(downgrade_code)) => (upgrade_code.to_string(), downgrade_code.to_string()),
+            _ => return Err(format!("Dynamic type {}:{} has missing calculation code. Please check upgrade_code and downgrade_code fields", name, type_item.index))
+        };
+
+        let mut token_info = DynamicType {
+            group_name: name.to_string(),
+            index: type_item.index,
+            format: type_item.format.to_string(),
+            parse: Vec::new(),
+            upgrade_code,
+            downgrade_code,
+            names: type_item.names.clone(),
+            decimal_digits: type_item.decimal_digits,
+            use_fract_rounding: type_item.use_fract_rounding,
+            remove_fract_if_zero: type_item.remove_fract_if_zero
+        };
+
+        for type_parse_item in type_item.parse.iter() {
+            let mut session = Session::new();
+            session.set_language("en".to_string());
+            session.set_text(type_parse_item.to_string());
+            token_info.parse.push(Tokinizer::pattern(self, &session));
+        }
+
+        self.types.entry(name.to_string()).or_default().insert(token_info.index, Shared::new(token_info));
+        Ok(())
+    }
+
+    fn load_unit(&mut self, unit: &JsonUnit) -> Result<(), String> {
+        let (factor, dimension) = match (&unit.dimension, &unit.definition) {
+            (Some(dimension), _) => (unit.factor, Dimension::base(dimension)),
+            (None, Some(definition)) => match Unit::parse(self, definition) {
+                Some(definition) => (unit.factor * definition.factor(), definition.dimension()),
+                None => return Err(format!("Unit {} has invalid definition ({})", unit.symbol, definition))
+            },
+            (None, None) => return Err(format!("Unit {} has missing dimension. Please check dimension and definition fields", unit.symbol))
+        };
+
+        let definition = Shared::new(UnitDefinition {
+            symbol: unit.symbol.to_string(),
+            factor,
+            offset: unit.offset,
+            dimension,
+            prefix: unit.prefix,
+            binary_prefix: unit.binary_prefix
+        });
+
+        for name in unit.names.iter() {
+            self.unit_names.insert(name.to_lowercase(), definition.clone());
+        }
+        self.units.insert(unit.symbol.to_string(), definition);
+        Ok(())
+    }
+
+    fn load_type_conversion(&mut self, type_conversion: &JsonTypeConversion) -> Result<(), (&'static str, String)> {
+        for (field, item) in [("source", &type_conversion.source), ("target", &type_conversion.target)].iter() {
+            if !self.types.get(&item.name).is_some_and(|group| group.contains_key(&item.index)) {
+                return Err((field, format!("{}:{} type not defined", item.name, item.index)));
             }
         }
 
-        config
+        self.type_conversion.push(type_conversion.clone());
+        Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+#[test]
+fn validate_json_test() {
+    use alloc::vec;
+
+    assert_eq!(SmartCalcConfig::validate_json(JSON_DATA), Vec::new());
+
+    let mut json: serde_json::Value = from_str(JSON_DATA).unwrap();
+    json["parse"]["text"][0] = serde_json::Value::from("(?P<TEXT>[a-z");
+    json["currency_alias"]["₺"] = serde_json::Value::from("xyz");
+    json["types"][0]["items"][1].as_object_mut().unwrap().remove("upgrade_code");
+    json["type_conversion"][0]["target"]["index"] = serde_json::Value::from(99);
+    json["languages"]["en"]["rules"]["unknown_rule"] = json["languages"]["en"]["rules"]["percent_calculator"].clone();
+
+    let diagnostics = SmartCalcConfig::validate_json(&json.to_string());
+    let paths = diagnostics.iter().map(|diagnostic| (diagnostic.path.as_str(), diagnostic.severity)).collect::<Vec<_>>();
+    assert_eq!(paths, vec![
+        ("$.currency_alias[\"₺\"]", Severity::Warning),
+        ("$.parse.text[0]", Severity::Error),
+        ("$.languages.en.rules.unknown_rule", Severity::Warning),
+        ("$.types[0].items[1]", Severity::Warning),
+        ("$.type_conversion[0].target", Severity::Warning)
+    ]);
+
+    let diagnostics = SmartCalcConfig::validate_json("{\"parse\": 1}");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].path, "$".to_string());
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+}
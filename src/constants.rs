@@ -5,7 +5,7 @@
  */
 
 use crate::types::CurrencyInfo;
-use alloc::rc::Rc;
+use crate::shared::Shared;
 use alloc::{collections::btree_map::BTreeMap};
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -61,6 +61,10 @@ pub enum ConstantType {
     Tomorrow = 9,
     Yesterday = 10,
     Now = 11,
+    Previous = 12,
+    Sum = 13,
+    Average = 14,
+    Line = 15,
 }
 
 #[derive(Clone, Debug, Serialize_repr, Deserialize_repr)]
@@ -90,6 +94,10 @@ impl ConstantType {
             9 => Some(ConstantType::Tomorrow),
             10 => Some(ConstantType::Yesterday),
             11 => Some(ConstantType::Now),
+            12 => Some(ConstantType::Previous),
+            13 => Some(ConstantType::Sum),
+            14 => Some(ConstantType::Average),
+            15 => Some(ConstantType::Line),
             _ => None,
         }
     }
@@ -105,6 +113,10 @@ pub struct Sample {
 pub struct LanguageRule {
     pub rules: Vec<String>,
     pub samples: Vec<Sample>,
+
+    /// Rules with a higher priority are tried first, the others in name order.
+    #[serde(default)]
+    pub priority: i32,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -174,6 +186,52 @@ pub struct JsonDynamicTypeItem {
     pub remove_fract_if_zero: Option<bool>
 }
 
+#[derive(Default)]
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct JsonUnitPrefix {
+    pub symbol: String,
+    pub name: String,
+    pub factor: f64,
+
+    #[serde(default)]
+    pub binary: bool
+}
+
+fn default_unit_factor() -> f64 {
+    1.0
+}
+
+#[derive(Default)]
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct JsonUnit {
+    pub symbol: String,
+
+    #[serde(default)]
+    pub names: Vec<String>,
+
+    #[serde(default)]
+    pub dimension: Option<String>,
+
+    #[serde(default)]
+    pub definition: Option<String>,
+
+    #[serde(default = "default_unit_factor")]
+    pub factor: f64,
+
+    #[serde(default)]
+    pub offset: f64,
+
+    #[serde(default)]
+    pub prefix: bool,
+
+    #[serde(default)]
+    pub binary_prefix: bool
+}
+
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct JsonConstant {
     pub default_language: String,
@@ -181,14 +239,36 @@ pub struct JsonConstant {
     pub alias: BTreeMap<String, String>,
     pub currency_alias: BTreeMap<String, String>,
     pub currency_rates: BTreeMap<String, f64>,
-    pub currencies: BTreeMap<String, Rc<CurrencyInfo>>,
+    pub currencies: BTreeMap<String, Shared<CurrencyInfo>>,
     pub languages: BTreeMap<String, JsonLanguageConstant>,
     pub type_group: BTreeMap<String, Vec<String>>,
     pub timezones: BTreeMap<String, i32>,
     pub type_conversion: Vec<JsonTypeConversion>,
-    pub types: Vec<JsonDynamicType>
+    pub types: Vec<JsonDynamicType>,
+
+    #[serde(default)]
+    pub unit_prefixes: Vec<JsonUnitPrefix>,
+
+    #[serde(default)]
+    pub units: Vec<JsonUnit>
 }
 
+/// Sections of a configuration that can be layered onto a loaded configuration.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct JsonConfigPack {
+    pub types: Vec<JsonDynamicType>,
+    pub type_conversion: Vec<JsonTypeConversion>,
+    pub currencies: BTreeMap<String, Shared<CurrencyInfo>>,
+    pub currency_alias: BTreeMap<String, String>,
+    pub currency_rates: BTreeMap<String, f64>,
+    pub timezones: BTreeMap<String, i32>,
+    pub languages: BTreeMap<String, JsonLanguageConstant>,
+    pub units: Vec<JsonUnit>
+}
+
+pub const JSON_PACK_SECTIONS: [&str; 8] = ["types", "type_conversion", "currencies", "currency_alias", "currency_rates", "timezones", "languages", "units"];
+
 pub type MonthItemList = Vec<(Regex, MonthInfo)>;
 
 pub const JSON_DATA: &str = include_str!("./json/config.json");
@@ -0,0 +1,133 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use core::fmt;
+use alloc::string::String;
+use alloc::string::ToString;
+use serde_derive::Serialize;
+
+/// Character based location of an error in the calculated line.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    pub fn merge(&self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmartCalcError {
+    SyntaxError { message: String, span: Span },
+    UnknownOperator { operator: char, span: Span },
+    IncompatibleTypes { left: String, right: String, span: Span },
+    UnknownCurrency { currency: String, span: Span },
+    DivisionByZero { span: Span },
+    UnknownUnit { unit: String, span: Span },
+    UnknownFunction { name: String, span: Span },
+    UnknownLineReference { reference: String, span: Span },
+    InvalidArgument { message: String, span: Span }
+}
+
+impl SmartCalcError {
+    pub(crate) fn syntax_error<T: ToString>(message: T, span: Span) -> Self {
+        SmartCalcError::SyntaxError { message: message.to_string(), span }
+    }
+
+    pub(crate) fn invalid_argument<T: ToString>(message: T) -> Self {
+        SmartCalcError::InvalidArgument { message: message.to_string(), span: Span::default() }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            SmartCalcError::SyntaxError { span, .. } => *span,
+            SmartCalcError::UnknownOperator { span, .. } => *span,
+            SmartCalcError::IncompatibleTypes { span, .. } => *span,
+            SmartCalcError::UnknownCurrency { span, .. } => *span,
+            SmartCalcError::DivisionByZero { span } => *span,
+            SmartCalcError::UnknownUnit { span, .. } => *span,
+            SmartCalcError::UnknownFunction { span, .. } => *span,
+            SmartCalcError::UnknownLineReference { span, .. } => *span,
+            SmartCalcError::InvalidArgument { span, .. } => *span
+        }
+    }
+
+    pub(crate) fn with_span(mut self, new_span: Span) -> Self {
+        match &mut self {
+            SmartCalcError::SyntaxError { span, .. } => *span = new_span,
+            SmartCalcError::UnknownOperator { span, .. } => *span = new_span,
+            SmartCalcError::IncompatibleTypes { span, .. } => *span = new_span,
+            SmartCalcError::UnknownCurrency { span, .. } => *span = new_span,
+            SmartCalcError::DivisionByZero { span } => *span = new_span,
+            SmartCalcError::UnknownUnit { span, .. } => *span = new_span,
+            SmartCalcError::UnknownFunction { span, .. } => *span = new_span,
+            SmartCalcError::UnknownLineReference { span, .. } => *span = new_span,
+            SmartCalcError::InvalidArgument { span, .. } => *span = new_span
+        };
+        self
+    }
+}
+
+impl fmt::Display for SmartCalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmartCalcError::SyntaxError { message, .. } => write!(f, "{}", message),
+            SmartCalcError::UnknownOperator { operator, .. } => write!(f, "Unknown operator. ({})", operator),
+            SmartCalcError::IncompatibleTypes { left, right, .. } => write!(f, "{} and {} can not be calculated", left, right),
+            SmartCalcError::UnknownCurrency { currency, .. } => write!(f, "Unknown currency. ({})", currency),
+            SmartCalcError::DivisionByZero { .. } => write!(f, "Division by zero"),
+            SmartCalcError::UnknownUnit { unit, .. } => write!(f, "Unknown unit. ({})", unit),
+            SmartCalcError::UnknownFunction { name, .. } => write!(f, "Unknown function. ({})", name),
+            SmartCalcError::UnknownLineReference { reference, .. } => write!(f, "Unknown line reference. ({})", reference),
+            SmartCalcError::InvalidArgument { message, .. } => write!(f, "{}", message)
+        }
+    }
+}
+
+/// Error of a configuration that could not be read at all. Problems with single entries are
+/// reported without failing, see [`crate::MergeReport`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    InvalidJson { message: String },
+    UnsupportedSection { name: String }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidJson { message } => write!(f, "Invalid configuration. ({})", message),
+            ConfigError::UnsupportedSection { name } => write!(f, "'{}' can not be merged into a loaded configuration", name)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The entry can not be used, like an invalid regex.
+    Error,
+
+    /// The entry is skipped or partly used, like an alias of an unknown currency.
+    Warning
+}
+
+/// Problem found by [`crate::SmartCalcConfig::validate_json`]. `path` points at the entry,
+/// like `$.languages.en.rules.time_for_location`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDiagnostic {
+    pub path: String,
+    pub severity: Severity,
+    pub message: String
+}
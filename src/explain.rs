@@ -0,0 +1,37 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// One step of the derivation of a line result.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExplainStep {
+    /// A word of the line was replaced by an alias, like `x` by `*`.
+    Alias { text: String, replacement: String },
+
+    /// Tokens of the line were replaced by the value of a session variable.
+    Variable { name: String, value: String },
+
+    /// A rule matched the line and replaced the matched tokens with its result.
+    Rule { name: String, fields: BTreeMap<String, String>, result: String },
+
+    /// Lines added by a word like `sum` or `average`, by line number starting at 1 with their values.
+    Aggregate { text: String, lines: Vec<(usize, String)>, result: String },
+
+    /// Exchange rate of a currency against USD.
+    Rate { currency: String, rate: f64 },
+
+    /// Operation of the interpreter. `steps` hold the operations used to compute the operands.
+    Binary { operator: char, left: String, right: String, result: String, steps: Vec<ExplainStep> }
+}
+
+/// Steps of a line in the order they happened, recorded when `Session::set_explain` is enabled.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Explanation {
+    pub steps: Vec<ExplainStep>
+}
@@ -12,8 +12,9 @@ use crate::tools::do_divition;
 use core::ops::Deref;
 
 use crate::config::SmartCalcConfig;
-use crate::types::{SmartCalcAstType};
+use crate::types::{Number, SmartCalcAstType, TokenType};
 use crate::constants::MonthInfo;
+use crate::locale::Locale;
 
 pub const MINUTE: i64 = 60;
 pub const HOUR: i64 = MINUTE * 60;
@@ -42,7 +43,7 @@ pub fn left_padding(number: i64, size: usize) -> String {
     format!("{:0width$}", &number, width = size)
 }
 
-pub fn format_number(number: f64, thousands_separator: String, decimal_separator: String, decimal_digits: u8, remove_fract_if_zero: bool, use_fract_rounding: bool) -> String {
+pub fn format_number(number: f64, locale: &Locale, decimal_digits: u8, remove_fract_if_zero: bool, use_fract_rounding: bool) -> String {
     let divider      = 10_u32.pow(decimal_digits.into());
     let fract_number = do_divition((number * divider as f64).round(), divider as f64);
     let trunc_part   = fract_number.trunc().abs().to_string();
@@ -52,26 +53,54 @@ pub fn format_number(number: f64, thousands_separator: String, decimal_separator
         false => format!("{}", &number.abs())
     };
 
-    let fract_part = fract_information(fract_number.fract());
+    let has_fract = fract_information(fract_number.fract()) > 0;
+    group_number(number < 0.0, &trunc_part, &formated_number, has_fract, locale, remove_fract_if_zero)
+}
+
+#[cfg(feature = "decimal")]
+pub fn format_decimal(number: Number, locale: &Locale, decimal_digits: u8, remove_fract_if_zero: bool, use_fract_rounding: bool) -> String {
+    use rust_decimal::RoundingStrategy;
+
+    let fract_number = number.round_dp_with_strategy(decimal_digits.into(), RoundingStrategy::MidpointAwayFromZero);
+    let trunc_part   = fract_number.trunc().abs().to_string();
+
+    let formated_number = match use_fract_rounding {
+        true => format!("{:.width$}", &fract_number.abs(), width = decimal_digits.into()),
+        false => format!("{}", &number.abs().normalize())
+    };
+
+    group_number(number.is_sign_negative() && !number.is_zero(), &trunc_part, &formated_number, !fract_number.fract().is_zero(), locale, remove_fract_if_zero)
+}
+
+/// Formats a `Number` with the active numeric backend.
+#[cfg(not(feature = "decimal"))]
+pub fn format_amount(number: Number, locale: &Locale, decimal_digits: u8, remove_fract_if_zero: bool, use_fract_rounding: bool) -> String {
+    format_number(number, locale, decimal_digits, remove_fract_if_zero, use_fract_rounding)
+}
+
+/// Formats a `Number` with the active numeric backend.
+#[cfg(feature = "decimal")]
+pub fn format_amount(number: Number, locale: &Locale, decimal_digits: u8, remove_fract_if_zero: bool, use_fract_rounding: bool) -> String {
+    format_decimal(number, locale, decimal_digits, remove_fract_if_zero, use_fract_rounding)
+}
+
+fn group_number(is_negative: bool, trunc_part: &str, formated_number: &str, has_fract: bool, locale: &Locale, remove_fract_if_zero: bool) -> String {
     let trunc_size = trunc_part.len();
-    let mut trunc_dot_index = 3 - (trunc_part.len() % 3);
     let mut trunc_formated = String::new();
 
-
-    if number < 0.0 {
+    if is_negative {
         trunc_formated.push('-');
     }
 
     for index in 0..trunc_size {
         trunc_formated.push(formated_number.chars().nth(index).unwrap());
-        trunc_dot_index += 1;
-        if trunc_size != (index + 1) && trunc_dot_index % 3 == 0 {
-            trunc_formated.push_str(&thousands_separator);
+        if locale.grouping.has_separator(trunc_size - index - 1) {
+            trunc_formated.push_str(&locale.thousands_separator);
         }
     }
     
-    if (fract_part > 0 || !remove_fract_if_zero) && trunc_size != formated_number.len() {
-        trunc_formated.push_str(&decimal_separator);
+    if (has_fract || !remove_fract_if_zero) && trunc_size != formated_number.len() {
+        trunc_formated.push_str(&locale.decimal_separator);
 
         for index in (trunc_size+1)..formated_number.len() {
             trunc_formated.push(formated_number.chars().nth(index).unwrap());
@@ -103,6 +132,17 @@ pub fn format_result(config: &SmartCalcConfig, session: &Session, result: alloc:
     }
 }
 
+/* Values print like results, variables print their value and other tokens their text */
+pub fn format_token(config: &SmartCalcConfig, session: &Session, token: &TokenType) -> String {
+    match token {
+        TokenType::Variable(variable) => format_result(config, session, variable.data.borrow().clone()),
+        _ => match token.as_item() {
+            Some(item) => item.print(config, session),
+            None => token.to_string()
+        }
+    }
+}
+
 #[cfg(test)]
 #[test]
 fn get_frac_test() {
@@ -113,24 +153,30 @@ fn get_frac_test() {
 #[cfg(test)]
 #[test]
 fn format_number_test() {
-    assert_eq!(format_number(123.0, ",".to_string(), ".".to_string(), 2, false, true), "123.00".to_string());
-    assert_eq!(format_number(123.1, ",".to_string(), ".".to_string(), 2, false, true), "123.10".to_string());
-    assert_eq!(format_number(123.01, ",".to_string(), ".".to_string(), 2, false, true), "123.01".to_string());
-    assert_eq!(format_number(1234.01, ",".to_string(), ".".to_string(), 2, false, true), "1,234.01".to_string());
-    assert_eq!(format_number(123456.01, ",".to_string(), ".".to_string(), 2, false, true), "123,456.01".to_string());
-    assert_eq!(format_number(123456.123456789, ",".to_string(), ".".to_string(), 2, false, true), "123,456.12".to_string());
-    assert_eq!(format_number(123456.1, ",".to_string(), ".".to_string(), 2, false, true), "123,456.10".to_string());
-    assert_eq!(format_number(-123456.1, ",".to_string(), ".".to_string(), 2, false, true), "-123,456.10".to_string());
-
-    assert_eq!(format_number(123.0, ",".to_string(), ".".to_string(), 2, true, false), "123".to_string());
-    assert_eq!(format_number(123.0000, ",".to_string(), ".".to_string(), 2, true, false), "123".to_string());
-    assert_eq!(format_number(123.1, ",".to_string(), ".".to_string(), 2, false, false), "123.1".to_string());
-    assert_eq!(format_number(123.01, ",".to_string(), ".".to_string(), 2, false, false), "123.01".to_string());
-    assert_eq!(format_number(1234.01, ",".to_string(), ".".to_string(), 2, false, false), "1,234.01".to_string());
-    assert_eq!(format_number(123456.01, ",".to_string(), ".".to_string(), 2, false, false), "123,456.01".to_string());
-    assert_eq!(format_number(123456.123456789, ",".to_string(), ".".to_string(), 2, false, false), "123,456.123456789".to_string());
-    assert_eq!(format_number(123456.1, ",".to_string(), ".".to_string(), 2, false, false), "123,456.1".to_string());
-    assert_eq!(format_number(-123456.1, ",".to_string(), ".".to_string(), 2, false, false), "-123,456.1".to_string());
+    assert_eq!(format_number(123.0, &Locale::new(".", ","), 2, false, true), "123.00".to_string());
+    assert_eq!(format_number(123.1, &Locale::new(".", ","), 2, false, true), "123.10".to_string());
+    assert_eq!(format_number(123.01, &Locale::new(".", ","), 2, false, true), "123.01".to_string());
+    assert_eq!(format_number(1234.01, &Locale::new(".", ","), 2, false, true), "1,234.01".to_string());
+    assert_eq!(format_number(123456.01, &Locale::new(".", ","), 2, false, true), "123,456.01".to_string());
+    assert_eq!(format_number(123456.123456789, &Locale::new(".", ","), 2, false, true), "123,456.12".to_string());
+    assert_eq!(format_number(123456.1, &Locale::new(".", ","), 2, false, true), "123,456.10".to_string());
+    assert_eq!(format_number(-123456.1, &Locale::new(".", ","), 2, false, true), "-123,456.10".to_string());
+
+    assert_eq!(format_number(123.0, &Locale::new(".", ","), 2, true, false), "123".to_string());
+    assert_eq!(format_number(123.0000, &Locale::new(".", ","), 2, true, false), "123".to_string());
+    assert_eq!(format_number(123.1, &Locale::new(".", ","), 2, false, false), "123.1".to_string());
+    assert_eq!(format_number(123.01, &Locale::new(".", ","), 2, false, false), "123.01".to_string());
+    assert_eq!(format_number(1234.01, &Locale::new(".", ","), 2, false, false), "1,234.01".to_string());
+    assert_eq!(format_number(123456.01, &Locale::new(".", ","), 2, false, false), "123,456.01".to_string());
+    assert_eq!(format_number(123456.123456789, &Locale::new(".", ","), 2, false, false), "123,456.123456789".to_string());
+    assert_eq!(format_number(123456.1, &Locale::new(".", ","), 2, false, false), "123,456.1".to_string());
+    assert_eq!(format_number(-123456.1, &Locale::new(".", ","), 2, false, false), "-123,456.1".to_string());
+
+    assert_eq!(format_number(12345678.5, &Locale::indian(), 2, false, true), "1,23,45,678.50".to_string());
+    assert_eq!(format_number(-100000.0, &Locale::indian(), 2, true, false), "-1,00,000".to_string());
+    assert_eq!(format_number(999.0, &Locale::indian(), 2, true, false), "999".to_string());
+    assert_eq!(format_number(1234567.5, &Locale::swiss(), 2, false, true), "1'234'567.50".to_string());
+    assert_eq!(format_number(1234.5, &Locale::non_breaking_space(), 2, false, true), "1\u{a0}234,50".to_string());
 }
 
 #[cfg(test)]
@@ -146,9 +192,9 @@ fn format_result_test() {
 
     let mut session = Session::default();
     session.set_language("en".to_string());
-    assert_eq!(NumberItem(123456.123456789, NumberType::Decimal).print(&config, &session), "123.456,12".to_string());
-    assert_eq!(NumberItem(1.123456789, NumberType::Decimal).print(&config, &session), "1,12".to_string());
-    assert_eq!(NumberItem(2.0, NumberType::Hexadecimal).print(&config, &session), "0x2".to_string());
+    assert_eq!(NumberItem(number!(123456.123456789), NumberType::Decimal).print(&config, &session), "123.456,12".to_string());
+    assert_eq!(NumberItem(number!(1.123456789), NumberType::Decimal).print(&config, &session), "1,12".to_string());
+    assert_eq!(NumberItem(number!(2.0), NumberType::Hexadecimal).print(&config, &session), "0x2".to_string());
             
     assert_eq!(format_result(&config, &session, Rc::new(SmartCalcAstType::Item(Rc::new(TimeItem(chrono::Utc::today().and_hms(11, 30, 0).naive_utc(), config.get_time_offset()))))), "11:30:00 UTC".to_string());
     assert_eq!(format_result(&config, &session, Rc::new(SmartCalcAstType::Item(Rc::new(TimeItem(chrono::Utc::today().and_hms(0, 0, 0).naive_utc(), config.get_time_offset()))))), "00:00:00 UTC".to_string());
@@ -6,6 +6,8 @@
 
 #![no_std]
 extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
 extern crate lazy_static;
 extern crate log;
 
@@ -14,6 +16,13 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 #[cfg(all(not(target_arch = "wasm32"), not(test)))]
 extern crate libc_print;
 
+/* Number literal of the enabled number backend, for tests */
+#[cfg(test)]
+macro_rules! number {
+    (-$value:literal) => { -crate::tools::number_from_literal(stringify!($value)) };
+    ($value:literal) => { crate::tools::number_from_literal(stringify!($value)) };
+}
+
 pub(crate) mod types;
 pub(crate) mod tokinizer;
 pub(crate) mod syntax;
@@ -27,19 +36,46 @@ pub(crate) mod config;
 pub(crate) mod smartcalc;
 pub(crate) mod variable;
 pub(crate) mod session;
+pub(crate) mod snapshot;
+pub(crate) mod rate_provider;
+pub(crate) mod error;
+pub(crate) mod unit;
+pub(crate) mod locale;
+pub(crate) mod shared;
+pub(crate) mod value;
+pub(crate) mod explain;
+pub(crate) mod completion;
 
 #[cfg(test)]
 mod tests;
 
 pub use smartcalc::SmartCalc;
+pub use smartcalc::SampleFailure;
 pub use session::Session;
-pub use config::SmartCalcConfig;
+pub use config::{SmartCalcConfig, MergeReport, MergeEntry, MergeConflict};
 pub use types::SmartCalcAstType;
 pub use types::FieldType;
 pub use compiler::DataItem;
 pub use token::ui_token::UiToken;
 pub use token::ui_token::UiTokenType;
 pub use smartcalc::RuleTrait;
+pub use smartcalc::FunctionTrait;
 pub use types::TokenType;
 pub use types::NumberType;
-pub use types::TimeOffset;
\ No newline at end of file
+pub use types::TimeOffset;
+pub use types::Number;
+pub use error::{SmartCalcError, ConfigError, ConfigDiagnostic, Severity};
+pub use error::Span;
+pub use variable::VariableValue;
+pub use variable::SessionVariable;
+pub use rate_provider::{CurrencyRate, RateProvider, JsonRateProvider};
+pub use shared::{Shared, MaybeSync};
+pub use value::Value;
+pub use unit::Dimension;
+pub use snapshot::ItemSnapshot;
+pub use explain::{ExplainStep, Explanation};
+pub use completion::{Completion, CompletionKind};
+pub use locale::{Locale, DigitGrouping, CurrencyPlacement, DateOrder, NON_BREAKING_SPACE};
+
+#[cfg(any(feature = "std", test))]
+pub use rate_provider::FileRateProvider;
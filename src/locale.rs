@@ -0,0 +1,187 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::config::SmartCalcConfig;
+
+pub const NON_BREAKING_SPACE: &str = "\u{a0}";
+
+/// Where the thousands separators are placed in the integer part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigitGrouping {
+    /// Groups of three digits, like `1,234,567`.
+    Thousands,
+
+    /// Lakh and crore grouping, like `12,34,567`.
+    Indian
+}
+
+impl DigitGrouping {
+    /* "remaining" is the number of digits after the current one */
+    pub(crate) fn has_separator(&self, remaining: usize) -> bool {
+        match self {
+            DigitGrouping::Thousands => remaining > 0 && remaining.is_multiple_of(3),
+            DigitGrouping::Indian => remaining == 3 || (remaining > 3 && (remaining - 3).is_multiple_of(2))
+        }
+    }
+}
+
+/// Position of the currency symbol, overriding the placement of the currency itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurrencyPlacement {
+    Left,
+    LeftWithSpace,
+    Right,
+    RightWithSpace
+}
+
+/// Order of the day, month and year fields.
+///
+/// It is used to read numeric dates like `3/12/2021` and to print dates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateOrder {
+    DayMonthYear,
+    MonthDayYear,
+    YearMonthDay
+}
+
+/// Regional number, currency and date conventions of a session.
+///
+/// Sessions without a locale use the separators of the `SmartCalc` instance,
+/// so one instance can serve users from different regions at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locale {
+    pub decimal_separator: String,
+    pub thousands_separator: String,
+    pub grouping: DigitGrouping,
+    pub currency_placement: Option<CurrencyPlacement>,
+    pub date_order: Option<DateOrder>
+}
+
+impl Locale {
+    pub fn new(decimal_separator: &str, thousands_separator: &str) -> Self {
+        Locale {
+            decimal_separator: decimal_separator.to_string(),
+            thousands_separator: thousands_separator.to_string(),
+            grouping: DigitGrouping::Thousands,
+            currency_placement: None,
+            date_order: None
+        }
+    }
+
+    /// `1,23,45,678.50`
+    pub fn indian() -> Self {
+        Locale::new(".", ",").with_grouping(DigitGrouping::Indian)
+    }
+
+    /// `1'234'567.50`
+    pub fn swiss() -> Self {
+        Locale::new(".", "'")
+    }
+
+    /// `1 234 567,50` with non-breaking spaces.
+    pub fn non_breaking_space() -> Self {
+        Locale::new(",", NON_BREAKING_SPACE)
+    }
+
+    pub fn with_grouping(mut self, grouping: DigitGrouping) -> Self {
+        self.grouping = grouping;
+        self
+    }
+
+    pub fn with_currency_placement(mut self, currency_placement: CurrencyPlacement) -> Self {
+        self.currency_placement = Some(currency_placement);
+        self
+    }
+
+    pub fn with_date_order(mut self, date_order: DateOrder) -> Self {
+        self.date_order = Some(date_order);
+        self
+    }
+
+    pub(crate) fn from_config(config: &SmartCalcConfig) -> Self {
+        Locale::new(&config.decimal_seperator, &config.thousand_separator)
+    }
+
+    /// Number text with the separators of the locale, converted to `1234.5` form.
+    pub(crate) fn normalize_number(&self, text: &str) -> String {
+        let text = match self.thousands_separator.is_empty() {
+            true => text.to_string(),
+            false => text.replace(&self.thousands_separator[..], "")
+        };
+        text.replace(&self.decimal_separator[..], ".")
+    }
+
+    /// Copy of the text where separators between digits are replaced with commas of the same byte length.
+    ///
+    /// Number patterns only accept dots and commas, so apostrophes and non-breaking spaces are masked before
+    /// matching. The positions stay the same, the values are read from the original text.
+    pub(crate) fn mask_separators(&self, data: &str) -> String {
+        let mut ranges = Vec::new();
+        for separator in [&self.thousands_separator, &self.decimal_separator] {
+            if separator.is_empty() || separator == "." || separator == "," {
+                continue;
+            }
+
+            for (start, _) in data.match_indices(&separator[..]) {
+                let end = start + separator.len();
+                let after_digit = data[..start].chars().next_back().is_some_and(|letter| letter.is_ascii_digit());
+                let before_digit = data[end..].chars().next().is_some_and(|letter| letter.is_ascii_digit());
+                if after_digit && before_digit {
+                    ranges.push(start..end);
+                }
+            }
+        }
+
+        if ranges.is_empty() {
+            return data.to_string();
+        }
+
+        let mut bytes = data.as_bytes().to_vec();
+        for range in ranges {
+            bytes[range].fill(b',');
+        }
+        String::from_utf8(bytes).unwrap_or_else(|_| data.to_string())
+    }
+
+    /// Date template used instead of the language format, if the locale has a date order.
+    pub(crate) fn date_format(&self, name: &str) -> Option<String> {
+        let date = match (self.date_order?, name) {
+            (DateOrder::DayMonthYear, "full_date" | "full_date_time") => "{day} {month_short} {year}",
+            (DateOrder::DayMonthYear, _) => "{day} {month_long}",
+            (DateOrder::MonthDayYear, "full_date" | "full_date_time") => "{month_short} {day}, {year}",
+            (DateOrder::MonthDayYear, _) => "{month_long} {day}",
+            (DateOrder::YearMonthDay, _) => "{year}-{month_pad}-{day_pad}"
+        };
+
+        Some(match name.ends_with("_time") {
+            true => alloc::format!("{} {{hour_pad}}:{{minute_pad}}:{{second_pad}} {{timezone}}", date),
+            false => date.to_string()
+        })
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn locale_test() {
+    let locale = Locale::swiss();
+    assert_eq!(locale.normalize_number("1'234'567.50"), "1234567.50");
+    assert_eq!(locale.mask_separators("1'234.5 + 'a'"), "1,234.5 + 'a'");
+
+    let locale = Locale::non_breaking_space();
+    assert_eq!(locale.normalize_number("1\u{a0}234,5"), "1234.5");
+    assert_eq!(locale.mask_separators("1\u{a0}234,5 km"), "1,,234,5 km");
+
+    assert!(DigitGrouping::Indian.has_separator(3));
+    assert!(DigitGrouping::Indian.has_separator(5));
+    assert!(!DigitGrouping::Indian.has_separator(6));
+    assert!(DigitGrouping::Thousands.has_separator(6));
+
+    assert_eq!(Locale::indian().date_format("full_date"), None);
+    assert_eq!(Locale::indian().with_date_order(DateOrder::MonthDayYear).date_format("full_date_time").unwrap(), "{month_short} {day}, {year} {hour_pad}:{minute_pad}:{second_pad} {timezone}");
+}
@@ -0,0 +1,107 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use chrono::NaiveDateTime;
+use serde_derive::Deserialize;
+
+use crate::error::SmartCalcError;
+use crate::shared::MaybeSync;
+
+/// USD based rate of a currency and the time it was published.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurrencyRate {
+    pub rate: f64,
+    pub as_of: NaiveDateTime
+}
+
+/// Source of exchange rates.
+///
+/// `SmartCalc` asks for a currency only when a conversion needs it and caches the
+/// answer, including `None`, until `SmartCalc::clear_rate_cache` is called.
+pub trait RateProvider: MaybeSync {
+    fn get_rate(&self, currency: &str) -> Option<CurrencyRate>;
+}
+
+#[derive(Deserialize)]
+struct JsonRateSnapshot {
+    as_of: String,
+    rates: BTreeMap<String, f64>
+}
+
+/// Rate provider for a JSON snapshot like
+/// `{"as_of": "2022-03-01T12:00:00", "rates": {"EUR": 0.89, "TRY": 14.2}}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonRateProvider {
+    as_of: NaiveDateTime,
+    rates: BTreeMap<String, f64>
+}
+
+impl JsonRateProvider {
+    pub fn from_json(json: &str) -> Result<Self, SmartCalcError> {
+        let snapshot: JsonRateSnapshot = serde_json::from_str(json).map_err(|error| SmartCalcError::invalid_argument(error.to_string()))?;
+        let as_of = NaiveDateTime::parse_from_str(&snapshot.as_of, "%Y-%m-%dT%H:%M:%S")
+            .map_err(|_| SmartCalcError::invalid_argument(alloc::format!("'{}' is not a valid date time", snapshot.as_of)))?;
+
+        Ok(JsonRateProvider {
+            as_of,
+            rates: snapshot.rates.into_iter().map(|(currency, rate)| (currency.to_lowercase(), rate)).collect()
+        })
+    }
+}
+
+impl RateProvider for JsonRateProvider {
+    fn get_rate(&self, currency: &str) -> Option<CurrencyRate> {
+        self.rates.get(&currency.to_lowercase()).map(|rate| CurrencyRate {
+            rate: *rate,
+            as_of: self.as_of
+        })
+    }
+}
+
+/// Rate provider reading a `JsonRateProvider` snapshot from a local file on first use.
+#[cfg(any(feature = "std", test))]
+pub struct FileRateProvider {
+    path: std::path::PathBuf,
+    snapshot: crate::shared::SharedCell<Option<Option<JsonRateProvider>>>
+}
+
+#[cfg(any(feature = "std", test))]
+impl FileRateProvider {
+    pub fn new<T: Into<std::path::PathBuf>>(path: T) -> Self {
+        FileRateProvider {
+            path: path.into(),
+            snapshot: crate::shared::SharedCell::new(None)
+        }
+    }
+
+    fn load(&self) -> Option<JsonRateProvider> {
+        let json = match std::fs::read_to_string(&self.path) {
+            Ok(json) => json,
+            Err(error) => {
+                log::error!("Rate snapshot could not be read ({:?}) {}", self.path, error);
+                return None;
+            }
+        };
+
+        match JsonRateProvider::from_json(&json) {
+            Ok(provider) => Some(provider),
+            Err(error) => {
+                log::error!("Rate snapshot could not be parsed ({:?}) {}", self.path, error);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "std", test))]
+impl RateProvider for FileRateProvider {
+    fn get_rate(&self, currency: &str) -> Option<CurrencyRate> {
+        self.snapshot.with(|snapshot| snapshot.get_or_insert_with(|| self.load()).as_ref()?.get_rate(currency))
+    }
+}
+
@@ -5,20 +5,59 @@
  */
 
 use core::cell::{Cell, RefCell};
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::string::{String, ToString};
+use chrono::NaiveDateTime;
 
 use alloc::{rc::Rc, vec::Vec};
 use regex::Regex;
+use serde::{Serialize, Serializer};
 
-use crate::variable::VariableInfo;
+use crate::compiler::DataItem;
+use crate::compiler::date::DateItem;
+use crate::compiler::duration::DurationItem;
+use crate::compiler::money::MoneyItem;
+use crate::compiler::number::NumberItem;
+use crate::config::SmartCalcConfig;
+use crate::error::SmartCalcError;
+use crate::explain::{ExplainStep, Explanation};
+use crate::locale::Locale;
+use crate::smartcalc::{ExecutionLine, SmartCalc};
+use crate::snapshot::{ItemSnapshot, NameTokenSnapshot, SessionSnapshot, VariableSnapshot};
+use crate::tools::number_from_f64;
+use crate::types::{NumberType, SmartCalcAstType, TokenType};
+use crate::variable::{SessionVariable, VariableInfo, VariableValue};
+
+/// What a calculated line defines and reads, used to find the lines that must be recalculated after an edit.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct LineDependency {
+    pub defines: Option<String>,
+    pub uses: BTreeSet<String>,
+    pub references: BTreeSet<usize>,
+    pub aggregate: bool
+}
+
+/// Exchange rates used by the executing line and the publish time of the oldest one.
+#[derive(Debug, Default)]
+pub(crate) struct LineRates {
+    pub as_of: Option<NaiveDateTime>,
+    pub rates: Vec<(String, f64)>
+}
 
 #[derive(Default)]
 pub struct Session {
     text: String,
     text_parts: Vec<String>,
     language: String,
+    locale: Option<Locale>,
+    explain: bool,
+    trace: RefCell<Option<Vec<ExplainStep>>>,
+    line_rates: RefCell<LineRates>,
     position: Cell<usize>,
+    line_results: RefCell<Vec<Option<Rc<dyn DataItem>>>>,
+    line_cache: RefCell<Vec<Option<(ExecutionLine, LineDependency)>>>,
+    dirty_lines: RefCell<BTreeSet<usize>>,
+    host_variables: BTreeMap<String, Rc<SmartCalcAstType>>,
 
     pub(crate) variables: RefCell<BTreeMap<String, Rc<VariableInfo>>>
 }
@@ -32,8 +71,16 @@ impl Session {
             text: String::new(),
             text_parts: Vec::new(),
             language: String::new(),
+            locale: None,
+            explain: false,
+            trace: RefCell::new(None),
+            line_rates: RefCell::new(LineRates::default()),
             variables: RefCell::new(BTreeMap::new()),
-            position: Cell::default()
+            position: Cell::default(),
+            line_results: RefCell::new(Vec::new()),
+            line_cache: RefCell::new(Vec::new()),
+            dirty_lines: RefCell::new(BTreeSet::new()),
+            host_variables: BTreeMap::new()
         }
     }
 
@@ -45,13 +92,73 @@ impl Session {
             Ok(re) => re.split(&self.text).map(|item| item.to_string()).collect::<Vec<_>>(),
             _ => self.text.lines().map(|item| item.to_string()).collect::<Vec<_>>()
         };
+
+        self.line_cache.borrow_mut().clear();
+        self.dirty_lines.borrow_mut().clear();
+    }
+
+    /// Replace the text of a single line.
+    ///
+    /// Passing the line count as index appends a new line. The line is
+    /// recalculated, together with the lines depending on it, by the next
+    /// `SmartCalc::execute_incremental` call.
+    pub fn update_line(&mut self, index: usize, text: String) -> bool {
+        if index > self.text_parts.len() {
+            return false;
+        }
+
+        match index == self.text_parts.len() {
+            true => self.text_parts.push(text),
+            false => self.text_parts[index] = text
+        };
+
+        self.text = self.text_parts.join("\n");
+        self.dirty_lines.borrow_mut().insert(index);
+        true
     }
 
     /// Set the language used to interpret input.
     pub fn set_language(&mut self, language: String) {
         self.language = language;
     }
+
+    /// Set the number, currency and date conventions used to read input and print results.
+    ///
+    /// Without a locale, the separators of the `SmartCalc` instance are used.
+    pub fn set_locale(&mut self, locale: Locale) {
+        self.locale = Some(locale);
+        self.line_cache.borrow_mut().clear();
+        self.dirty_lines.borrow_mut().clear();
+    }
+
+    pub fn clear_locale(&mut self) {
+        self.locale = None;
+        self.line_cache.borrow_mut().clear();
+        self.dirty_lines.borrow_mut().clear();
+    }
+
+    pub fn get_locale(&self) -> Option<&Locale> {
+        self.locale.as_ref()
+    }
+
+    /// Record how every line is calculated, returned as `ExecuteLine::explanation`.
+    pub fn set_explain(&mut self, explain: bool) {
+        self.explain = explain;
+        self.line_cache.borrow_mut().clear();
+        self.dirty_lines.borrow_mut().clear();
+    }
+
+    pub(crate) fn locale(&self, config: &SmartCalcConfig) -> Locale {
+        match &self.locale {
+            Some(locale) => locale.clone(),
+            None => Locale::from_config(config)
+        }
+    }
     
+    pub(crate) fn get_line(&self, index: usize) -> Option<&'_ String> {
+        self.text_parts.get(index)
+    }
+
     pub(crate) fn current_line(&self) -> &'_ String { 
         &self.text_parts[self.position.get()]
     }
@@ -75,12 +182,298 @@ impl Session {
         }
     }
     
+    pub(crate) fn current_line_index(&self) -> usize {
+        self.position.get()
+    }
+
+    pub(crate) fn set_current_line_index(&self, index: usize) {
+        self.position.set(index);
+    }
+
+    pub(crate) fn has_line_cache(&self) -> bool {
+        !self.line_cache.borrow().is_empty()
+    }
+
+    pub(crate) fn set_line_cache(&self, index: usize, line: ExecutionLine, dependency: LineDependency) {
+        let mut line_cache = self.line_cache.borrow_mut();
+        if line_cache.len() <= index {
+            line_cache.resize(index + 1, None);
+        }
+        line_cache[index] = Some((line, dependency));
+    }
+
+    pub(crate) fn get_line_cache(&self, index: usize) -> Option<(ExecutionLine, LineDependency)> {
+        match self.line_cache.borrow().get(index) {
+            Some(cache) => cache.clone(),
+            None => None
+        }
+    }
+
+    /// Whether the line is a sum or an average of the lines above it.
+    pub(crate) fn is_aggregate_line(&self, index: usize) -> bool {
+        matches!(self.line_cache.borrow().get(index), Some(Some((_, dependency))) if dependency.aggregate)
+    }
+
+    pub(crate) fn get_line_definition(&self, index: usize) -> Option<String> {
+        match self.line_cache.borrow().get(index) {
+            Some(Some((_, dependency))) => dependency.defines.clone(),
+            _ => None
+        }
+    }
+
+    pub(crate) fn take_dirty_lines(&self) -> BTreeSet<usize> {
+        core::mem::take(&mut *self.dirty_lines.borrow_mut())
+    }
+
+    pub(crate) fn set_line_result(&self, index: usize, result: Option<Rc<dyn DataItem>>) {
+        let mut line_results = self.line_results.borrow_mut();
+        if line_results.len() <= index {
+            line_results.resize(index + 1, None);
+        }
+        line_results[index] = result;
+    }
+
+    pub(crate) fn get_line_result(&self, index: usize) -> Option<Rc<dyn DataItem>> {
+        match self.line_results.borrow().get(index) {
+            Some(result) => result.clone(),
+            None => None
+        }
+    }
+
+    pub(crate) fn start_trace(&self) {
+        *self.trace.borrow_mut() = match self.explain {
+            true => Some(Vec::new()),
+            false => None
+        };
+    }
+
+    pub(crate) fn take_trace(&self) -> Option<Explanation> {
+        self.trace.borrow_mut().take().map(|steps| Explanation { steps })
+    }
+
+    pub(crate) fn use_rate(&self, currency: &str, rate: f64, as_of: Option<NaiveDateTime>) {
+        let mut line_rates = self.line_rates.borrow_mut();
+
+        /* Keep the oldest rate used by the line */
+        if let Some(as_of) = as_of {
+            if line_rates.as_of.map(|current| as_of < current).unwrap_or(true) {
+                line_rates.as_of = Some(as_of);
+            }
+        }
+
+        if !line_rates.rates.iter().any(|(code, _)| code == currency) {
+            line_rates.rates.push((currency.to_string(), rate));
+        }
+    }
+
+    /// Exchange rates used since the last call, which is made at the start of every line.
+    pub(crate) fn take_line_rates(&self) -> LineRates {
+        self.line_rates.take()
+    }
+
+    /* The step is only built if the line is traced */
+    pub(crate) fn trace(&self, step: impl FnOnce() -> ExplainStep) {
+        if self.trace.borrow().is_none() {
+            return;
+        }
+
+        let step = step();
+        if let Some(steps) = self.trace.borrow_mut().as_mut() {
+            steps.push(step);
+        }
+    }
+
+    /* Remove the last recorded step matching the predicate */
+    pub(crate) fn retract_trace(&self, predicate: impl Fn(&ExplainStep) -> bool) {
+        if let Some(steps) = self.trace.borrow_mut().as_mut() {
+            if let Some(index) = steps.iter().rposition(predicate) {
+                steps.remove(index);
+            }
+        }
+    }
+
+    /* Run the function and return the steps it recorded, instead of adding them to the line */
+    pub(crate) fn trace_nested<R>(&self, func: impl FnOnce() -> R) -> (R, Vec<ExplainStep>) {
+        let parent = self.trace.borrow_mut().as_mut().map(core::mem::take);
+        let result = func();
+
+        let steps = match (self.trace.borrow_mut().as_mut(), parent) {
+            (Some(steps), Some(parent)) => core::mem::replace(steps, parent),
+            _ => Vec::new()
+        };
+        (result, steps)
+    }
+
     pub(crate) fn add_variable(&self, variable_info: Rc<VariableInfo>) {
         self.variables.borrow_mut().insert(variable_info.to_string(), variable_info);
     }
+
+    /* Lines using the variable must be tokenized again on the next incremental execution */
+    fn invalidate_variable(&self, name: &str) {
+        let line_cache = self.line_cache.borrow();
+        let mut dirty_lines = self.dirty_lines.borrow_mut();
+        for (index, cache) in line_cache.iter().enumerate() {
+            if let Some((_, dependency)) = cache {
+                if dependency.uses.contains(name) || dependency.defines.as_deref() == Some(name) {
+                    dirty_lines.insert(index);
+                }
+            }
+        }
+    }
+
+    /* Value given by the host, kept until a line of the text defines the variable */
+    pub(crate) fn get_host_variable(&self, name: &str) -> Option<Rc<SmartCalcAstType>> {
+        self.host_variables.get(name).cloned()
+    }
+
+    fn variable_key(name: &str) -> String {
+        name.split_whitespace().map(|word| word.to_lowercase()).collect::<String>()
+    }
+
+    /// List the variables of the session with their type and formatted value.
+    pub fn variables(&self, smartcalc: &SmartCalc) -> Vec<SessionVariable> {
+        self.variables.borrow().values().map(|variable| {
+            let data = variable.data.borrow().clone();
+            SessionVariable {
+                name: variable.tokens.iter().map(|token| token.to_string()).collect::<Vec<_>>().join(" "),
+                type_name: data.type_name(),
+                value: smartcalc.format_result(self, data)
+            }
+        }).collect()
+    }
+
+    /// Create or update a variable, so it can be used by the session text.
+    ///
+    /// The name may contain multiple words, each made of letters only.
+    pub fn set_variable(&mut self, smartcalc: &SmartCalc, name: &str, value: VariableValue) -> Result<(), SmartCalcError> {
+        if name.split_whitespace().next().is_none() || !name.split_whitespace().all(|word| word.chars().all(char::is_alphabetic)) {
+            return Err(SmartCalcError::invalid_argument(alloc::format!("'{}' is not a valid variable name", name)));
+        }
+
+        let config = smartcalc.get_config();
+        let item: Rc<dyn DataItem> = match value {
+            VariableValue::Number(number) => Rc::new(NumberItem(number_from_f64(number)?, NumberType::Decimal)),
+            VariableValue::Money(price, currency) => match config.get_currency(currency.to_lowercase()) {
+                Some(currency) => Rc::new(MoneyItem(number_from_f64(price)?, currency)),
+                None => return Err(SmartCalcError::UnknownCurrency { currency, span: Default::default() })
+            },
+            VariableValue::Date(date) => Rc::new(DateItem(date, config.get_time_offset())),
+            VariableValue::Duration(duration) => Rc::new(DurationItem(duration))
+        };
+
+        let key = Session::variable_key(name);
+        let data = Rc::new(SmartCalcAstType::Item(item));
+        self.host_variables.insert(key.clone(), data.clone());
+        let variable = self.variables.borrow().get(&key).cloned();
+        match variable {
+            Some(variable) => {
+                *variable.data.borrow_mut() = data;
+                self.invalidate_variable(&key);
+            },
+            None => {
+                self.add_variable(Rc::new(VariableInfo {
+                    tokens: name.split_whitespace().map(|word| Rc::new(TokenType::Text(word.to_string()))).collect(),
+                    data: RefCell::new(data)
+                }));
+                self.line_cache.borrow_mut().clear();
+            }
+        };
+        Ok(())
+    }
+
+    /// Remove a variable from the session. Returns `false` if it does not exist.
+    pub fn remove_variable(&mut self, name: &str) -> bool {
+        let key = Session::variable_key(name);
+        self.host_variables.remove(&key);
+        let removed = self.variables.borrow_mut().remove(&key).is_some();
+        if removed {
+            self.invalidate_variable(&key);
+        }
+        removed
+    }
+
+    /// Remove all variables from the session.
+    pub fn clear_variables(&mut self) {
+        self.variables.borrow_mut().clear();
+        self.host_variables.clear();
+        self.line_cache.borrow_mut().clear();
+    }
     
     /// Returns the language configured for this session.
     pub fn get_language(&self) -> String {
         self.language.to_string()
     }
+
+    fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot {
+            language: self.language.to_string(),
+            text: self.text.to_string(),
+            variables: self.variables.borrow().values().map(|variable| VariableSnapshot {
+                name: variable.tokens.iter().map(|token| NameTokenSnapshot::from_token(token)).collect(),
+                value: match variable.data.borrow().as_ref() {
+                    SmartCalcAstType::Item(item) => ItemSnapshot::from_item(item.as_ref()),
+                    _ => None
+                },
+                host: self.host_variables.contains_key(&variable.to_string())
+            }).collect(),
+            line_results: self.line_results.borrow().iter().map(|result| match result {
+                Some(item) => ItemSnapshot::from_item(item.as_ref()),
+                None => None
+            }).collect()
+        }
+    }
+
+    /// Save the text, language, variables and line results of the session as JSON.
+    pub fn to_json(&self) -> Result<String, SmartCalcError> {
+        serde_json::to_string(&self.snapshot()).map_err(|error| SmartCalcError::invalid_argument(error.to_string()))
+    }
+
+    /// Restore a session saved with `Session::to_json`.
+    ///
+    /// Variables and line results are available without executing the text again,
+    /// currencies and dynamic types are resolved with the configuration of `smartcalc`.
+    pub fn from_json(smartcalc: &SmartCalc, json: &str) -> Result<Session, SmartCalcError> {
+        let snapshot: SessionSnapshot = serde_json::from_str(json).map_err(|error| SmartCalcError::invalid_argument(error.to_string()))?;
+        let config = smartcalc.get_config();
+
+        let mut session = Session::new();
+        session.set_language(snapshot.language);
+        session.set_text(snapshot.text);
+
+        for variable in snapshot.variables.iter() {
+            let data = match &variable.value {
+                Some(value) => SmartCalcAstType::Item(value.to_item(config)?),
+                None => SmartCalcAstType::None
+            };
+
+            let mut tokens = Vec::new();
+            for token in variable.name.iter() {
+                tokens.push(Rc::new(token.to_token(config)?));
+            }
+
+            let variable_info = Rc::new(VariableInfo {
+                tokens,
+                data: RefCell::new(Rc::new(data))
+            });
+
+            if variable.host {
+                session.host_variables.insert(variable_info.to_string(), variable_info.data.borrow().clone());
+            }
+            session.add_variable(variable_info);
+        }
+
+        for (index, result) in snapshot.line_results.iter().enumerate() {
+            if let Some(result) = result {
+                session.set_line_result(index, Some(result.to_item(config)?));
+            }
+        }
+
+        Ok(session)
+    }
+}
+
+impl Serialize for Session {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.snapshot().serialize(serializer)
+    }
 }
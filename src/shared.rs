@@ -0,0 +1,83 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+/// Pointer used by the compiled configuration.
+///
+/// With the `sync` feature it is `Arc` and `SmartCalcConfig` is `Send + Sync`, so one
+/// `SmartCalc` can be shared by many threads, each of them executing its own `Session`.
+#[cfg(not(feature = "sync"))]
+pub use alloc::rc::Rc as Shared;
+
+#[cfg(feature = "sync")]
+pub use alloc::sync::Arc as Shared;
+
+/// `Send + Sync` with the `sync` feature, implemented by every type otherwise.
+///
+/// Rules, functions and rate providers added to the configuration must implement it.
+#[cfg(feature = "sync")]
+pub trait MaybeSync: Send + Sync {}
+
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> MaybeSync for T {}
+
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSync {}
+
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSync for T {}
+
+/// Interior mutable value of the configuration, like the exchange rate cache.
+#[derive(Default)]
+pub(crate) struct SharedCell<T> {
+    #[cfg(not(feature = "sync"))]
+    value: core::cell::RefCell<T>,
+
+    #[cfg(feature = "sync")]
+    value: std::sync::Mutex<T>
+}
+
+impl<T> SharedCell<T> {
+    pub fn new(value: T) -> Self {
+        SharedCell { value: value.into() }
+    }
+
+    #[cfg(not(feature = "sync"))]
+    pub fn with<R>(&self, func: impl FnOnce(&mut T) -> R) -> R {
+        func(&mut self.value.borrow_mut())
+    }
+
+    #[cfg(feature = "sync")]
+    pub fn with<R>(&self, func: impl FnOnce(&mut T) -> R) -> R {
+        /* The cache stays usable even if a thread panicked while holding it */
+        let mut value = self.value.lock().unwrap_or_else(|error| error.into_inner());
+        func(&mut value)
+    }
+}
+
+/// Value of the configuration calculated on first use, like the compiled rules of a language.
+pub(crate) struct LazyCell<T> {
+    #[cfg(not(feature = "sync"))]
+    value: core::cell::OnceCell<T>,
+
+    #[cfg(feature = "sync")]
+    value: std::sync::OnceLock<T>
+}
+
+impl<T> Default for LazyCell<T> {
+    fn default() -> Self {
+        LazyCell { value: Default::default() }
+    }
+}
+
+impl<T> LazyCell<T> {
+    pub fn get_or_init(&self, func: impl FnOnce() -> T) -> &T {
+        self.value.get_or_init(func)
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.value.get_mut()
+    }
+}
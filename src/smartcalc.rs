@@ -6,67 +6,110 @@
 
 use core::borrow::Borrow;
 use core::ops::Deref;
-use alloc::collections::BTreeMap;
-use alloc::vec;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::vec::Vec;
 use alloc::rc::Rc;
 use alloc::string::{String, ToString};
+use alloc::format;
+use chrono::{NaiveDate, NaiveDateTime, Utc};
 use anyhow::anyhow;
 use crate::{Session, TimeOffset};
+use crate::session::LineDependency;
+use crate::variable::VariableInfo;
 use crate::tokinizer::{read_currency, RuleType, small_date};
 
 use crate::compiler::Interpreter;
+use crate::compiler::function::FunctionType;
 use crate::logger::{LOGGER, initialize_logger};
 use crate::syntax::SyntaxParser;
 use crate::token::ui_token::UiToken;
 use crate::tokinizer::TokenInfo;
 use crate::tokinizer::Tokinizer;
-use crate::tools::parse_timezone;
-use crate::types::{TokenType, ExpressionFunc};
+use crate::tools::{current_timezone_offset, find_timezone, parse_timezone};
+use crate::types::{CurrencyInfo, TokenType, ExpressionFunc};
 use crate::types::SmartCalcAstType;
 use crate::formatter::format_result;
-use crate::config::{SmartCalcConfig, DynamicType};
+use crate::config::{SmartCalcConfig, DynamicType, MergeReport};
+use crate::error::{ConfigError, SmartCalcError};
+use crate::rate_provider::{CurrencyRate, RateProvider};
+use crate::shared::{Shared, MaybeSync};
+use crate::value::Value;
+use crate::locale::Locale;
+use crate::explain::{ExplainStep, Explanation};
+use crate::completion::{complete, Completion};
 
 pub type ExecutionLine = Option<ExecuteLine>;
 
-pub trait RuleTrait {
+pub trait RuleTrait: MaybeSync {
     fn name(&self) -> String;
     fn call(&self, smartcalc: &SmartCalcConfig, fields: &BTreeMap<String, TokenType>) -> Option<TokenType>;
 }
 
+/// Function added with `SmartCalc::add_function`. Arguments and result are plain values,
+/// so a function can build new values, like a money amount from a stock symbol.
+///
+/// The returned error is reported for the line with the span of the call.
+pub trait FunctionTrait: MaybeSync {
+    fn name(&self) -> String;
+    fn call(&self, smartcalc: &SmartCalcConfig, arguments: &[Value]) -> Result<Value, SmartCalcError>;
+}
+
 #[derive(Debug)]
 #[derive(Default)]
 pub struct ExecuteResult {
     pub status: bool,
-    pub lines: Vec<ExecutionLine>
+    pub lines: Vec<ExecutionLine>,
+    pub changed_lines: Vec<usize>
 }
 
 #[derive(Debug, Clone)]
 pub struct ExecuteLineResult {
     pub output: String,
-    pub ast: Rc<SmartCalcAstType>
+    pub ast: Rc<SmartCalcAstType>,
+
+    /// Publish time of the oldest exchange rate used by the line.
+    pub rates_as_of: Option<NaiveDateTime>,
+
+    value: Option<Value>
 }
 
 impl ExecuteLineResult {
     pub fn new(output: String, ast: Rc<SmartCalcAstType>) -> Self {
-        ExecuteLineResult { output, ast }
+        ExecuteLineResult { output, ast, rates_as_of: None, value: None }
+    }
+
+    /// Typed value of the line, `None` when the result is not a value like a number, money or date.
+    pub fn value(&self) -> Option<Value> {
+        self.value.clone()
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ExecuteLine {
-    pub result: Result<ExecuteLineResult, String>,
+    pub result: Result<ExecuteLineResult, SmartCalcError>,
     pub raw_tokens: Vec<Rc<TokenType>>,
     pub ui_tokens: Vec<UiToken>,
-    pub calculated_tokens: Vec<Rc<TokenInfo>>
+    pub calculated_tokens: Vec<Rc<TokenInfo>>,
+
+    /// How the line was calculated, if the session was executed with `Session::set_explain`.
+    pub explanation: Option<Explanation>
 }
 
 impl ExecuteLine {
-    pub fn new(result: Result<ExecuteLineResult, String>, ui_tokens: Vec<UiToken>, raw_tokens: Vec<Rc<TokenType>>, calculated_tokens: Vec<Rc<TokenInfo>>) -> Self {
-        ExecuteLine { result, ui_tokens, raw_tokens, calculated_tokens }
+    pub fn new(result: Result<ExecuteLineResult, SmartCalcError>, ui_tokens: Vec<UiToken>, raw_tokens: Vec<Rc<TokenType>>, calculated_tokens: Vec<Rc<TokenInfo>>) -> Self {
+        ExecuteLine { result, ui_tokens, raw_tokens, calculated_tokens, explanation: None }
     }
 }
 
+/// A rule sample from the configuration whose query did not give the expected output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleFailure {
+    pub rule: String,
+    pub query: String,
+    pub expected: String,
+    pub actual: Result<String, SmartCalcError>
+}
+
 pub struct SmartCalc {
     config: SmartCalcConfig
 }
@@ -74,22 +117,9 @@ pub struct SmartCalc {
 impl Default for SmartCalc {
     fn default() -> Self {
         initialize_logger();
-        let mut smartcalc = SmartCalc {
+        SmartCalc {
             config: SmartCalcConfig::default()
-        };
-        smartcalc.set_date_rule("en", vec![
-            "{MONTH:month} {NUMBER:day}, {NUMBER:year}".to_string(),
-            "{MONTH:month} {NUMBER:day} {NUMBER:year}".to_string(),
-            "{NUMBER:day}/{NUMBER:month}/{NUMBER:year}".to_string(),
-            "{NUMBER:day} {MONTH:month} {NUMBER:year}".to_string(),
-            "{NUMBER:day} {MONTH:month}".to_string()
-        ]);
-        smartcalc.set_date_rule("tr", vec![
-            "{NUMBER:day}/{NUMBER:month}/{NUMBER:year}".to_string(),
-            "{NUMBER:day} {MONTH:month} {NUMBER:year}".to_string(),
-            "{NUMBER:day} {MONTH:month}".to_string()
-        ]);
-        smartcalc
+        }
     }
 }
 
@@ -120,13 +150,15 @@ impl SmartCalc {
             session.set_language("en".to_string());
             session.set_text(type_parse_item.borrow().to_string());
             
-            let tokens = Tokinizer::token_infos(&self.config, &session);
+            let tokens = Tokinizer::pattern(&self.config, &session);
             parse_tokens.push(tokens);
         }
         
         if let Some(dynamic_type) = self.config.types.get_mut(name.borrow()) {            
-            dynamic_type.insert(index, Rc::new(DynamicType::new(name.borrow().to_string(), index, format.borrow().to_string(), parse_tokens, upgrade_code.borrow().to_string(), downgrade_code.borrow().to_string(), names, decimal_digits, use_fract_rounding, remove_fract_if_zero)));
+            dynamic_type.insert(index, Shared::new(DynamicType::new(name.borrow().to_string(), index, format.borrow().to_string(), parse_tokens, upgrade_code.borrow().to_string(), downgrade_code.borrow().to_string(), names, decimal_digits, use_fract_rounding, remove_fract_if_zero)));
         }
+
+        self.config.reset_dynamic_type_units();
         true
     }
     
@@ -147,6 +179,10 @@ impl SmartCalc {
         self.config.percentage_config.use_fract_rounding = use_fract_rounding;
     }
 
+    pub fn set_division_by_zero_as_zero(&mut self, division_by_zero_as_zero: bool) {
+        self.config.division_by_zero_as_zero = division_by_zero_as_zero;
+    }
+
     pub fn set_decimal_seperator(&mut self, decimal_seperator: String) {
         self.config.decimal_seperator = decimal_seperator;
     }
@@ -155,56 +191,43 @@ impl SmartCalc {
         self.config.thousand_separator = thousand_separator;
     }
     
-    pub fn set_date_rule(&mut self, language: &str, rules: Vec<String>) {                
-        let mut function_items = Vec::new();
-        for rule_item in rules {
-            let mut session = Session::new();
-            session.set_language(language.to_string());
-            session.set_text(rule_item.to_string());
-            function_items.push(Tokinizer::token_infos(&self.config, &session));
-        }
+    /// Replace the patterns of the `small_date` rule of the language. The rule keeps its priority.
+    pub fn set_date_rule(&mut self, language: &str, rules: Vec<String>) {
+        let function_items = rules.iter().map(|rule_item| self.config.pattern(language, rule_item)).collect();
         
-        let current_rules = match self.config.rule.get_mut(language) {
+        let current_rules = match self.config.get_rules_mut(language) {
             Some(current_rules) => current_rules,
             None => return
         };
 
-        /* Remove small_date rule */
-        current_rules.retain(|rule| {
-            let is_small_date = if let RuleType::Internal { function_name, .. } = rule {
-                function_name == "small_date"
-            } else {
-                false
-            };
-            
-            !is_small_date
-        });
-        
-        current_rules.push(RuleType::Internal {
-            function_name: "small_date".to_string(),
-            function: small_date as ExpressionFunc,
-            tokens_list: function_items
-        });
+        let date_rule = current_rules.iter_mut().find(|rule| matches!(rule, RuleType::Internal { function_name, .. } if function_name == "small_date"));
+        match date_rule {
+            Some(RuleType::Internal { tokens_list, .. }) => *tokens_list = function_items,
+            _ => current_rules.push(RuleType::Internal {
+                function_name: "small_date".to_string(),
+                function: small_date as ExpressionFunc,
+                tokens_list: function_items
+            })
+        };
     }
     
+    /// Set the default timezone, as an abbreviation ("EST"), an offset ("GMT+3"), an IANA name ("Europe/Berlin") or a city ("Berlin").
     pub fn set_timezone(&mut self, timezone: String) -> Result<(), String> {
-        let timezone = match self.config.token_parse_regex.get("timezone") {
-            Some(regexes) => {
-                let capture = match regexes[0].captures(&timezone) {
-                    Some(capture) => capture,
-                    None => return Err("Timezone information not found".to_string())
-                };
-                match capture.name("timezone") {
-                    Some(_) => parse_timezone(&self.config, &capture),
+        let abbreviation = match self.config.token_parse_regex.get("timezone") {
+            Some(regexes) => match regexes[0].captures(&timezone) {
+                Some(capture) => match capture.name("timezone") {
+                    Some(_) => parse_timezone(&self.config, &capture).map(|(timezone, offset)| (timezone.to_uppercase(), offset)),
                     None => None
-                }
+                },
+                None => None
             },
             _ => None
         };
-        
+
+        let timezone = abbreviation.or_else(|| find_timezone(&timezone).map(|timezone| (timezone.name().to_string(), current_timezone_offset(&timezone))));
         match timezone {
             Some((timezone, offset)) => {
-                self.config.timezone = timezone.to_uppercase();
+                self.config.timezone = timezone;
                 self.config.timezone_offset = offset;
                 Ok(())
             },
@@ -222,9 +245,21 @@ impl SmartCalc {
         }
     }
 
+    /// Layer a configuration pack onto this instance. Packs can have `types`, `type_conversion`,
+    /// `currencies`, `currency_alias`, `currency_rates`, `timezones`, `languages` and `units`
+    /// sections in the format of the full configuration.
+    ///
+    /// Only the regexes and rules of the new entries are compiled. Entries that are already
+    /// loaded are kept and reported as conflicts.
+    pub fn merge_json(&mut self, json_data: &str) -> Result<MergeReport, ConfigError> {
+        self.config.merge_json(json_data)
+    }
+
+    /// Set the current USD based rate of a currency, it is used instead of the rate provider.
     pub fn update_currency(&mut self, currency: &str, rate: f64) -> bool {
         match read_currency(&self.config, currency) {
             Some(real_currency) => {
+                self.config.rate_cache.with(|cache| cache.insert(real_currency.clone(), Some(CurrencyRate { rate, as_of: Utc::now().naive_utc() })));
                 self.config.currency_rate.insert(real_currency, rate);
                 true
             },
@@ -232,8 +267,90 @@ impl SmartCalc {
         }
     }
     
+    /// Use a rate provider for the current exchange rates, rates already read are forgotten.
+    pub fn set_rate_provider(&mut self, provider: Shared<dyn RateProvider>) {
+        self.config.rate_provider = Some(provider);
+        self.clear_rate_cache();
+    }
+
+    /// Forget the rates read from the rate provider, they are asked again on the next conversion.
+    pub fn clear_rate_cache(&mut self) {
+        self.config.rate_cache.with(|cache| cache.clear());
+    }
+
+    /// Add the USD based rate of a currency for a specific day.
+    ///
+    /// Conversions with a date ("100 eur to usd on 12 march 2021") use the closest rate on or before that day.
+    /// Unknown currencies and rates that are not positive are not added.
+    pub fn update_currency_at(&mut self, currency: &str, date: NaiveDate, rate: f64) -> bool {
+        match read_currency(&self.config, currency) {
+            Some(real_currency) if rate.is_finite() && rate > 0.0 => {
+                self.config.currency_rate_history.entry(real_currency).or_default().insert(date, rate);
+                true
+            },
+             _ => false
+        }
+    }
+
+    /// Load dated rates from CSV, one `date,currency,rate` line per rate (for example `2021-03-12,EUR,0.8367`).
+    ///
+    /// A header line is allowed. Nothing is loaded if a line is invalid, otherwise the number of loaded rates is returned.
+    pub fn load_currency_rates_csv(&mut self, data: &str) -> Result<usize, SmartCalcError> {
+        let mut rates = Vec::new();
+        for (index, line) in data.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let columns = line.split(',').map(|column| column.trim()).collect::<Vec<_>>();
+            if index == 0 && columns.first().map(|column| NaiveDate::parse_from_str(column, "%Y-%m-%d").is_err()).unwrap_or(false) {
+                continue;
+            }
+
+            match columns[..] {
+                [date, currency, rate] => rates.push(self.parse_currency_rate(date, currency, rate.parse::<f64>().ok(), index + 1)?),
+                _ => return Err(SmartCalcError::invalid_argument(format!("Line {}: expected date, currency and rate", index + 1)))
+            };
+        }
+
+        Ok(self.add_currency_rates(rates))
+    }
+
+    /// Load dated rates from JSON, a map of days to currency rates (for example `{"2021-03-12": {"EUR": 0.8367}}`).
+    ///
+    /// Nothing is loaded if a rate is invalid, otherwise the number of loaded rates is returned.
+    pub fn load_currency_rates_json(&mut self, data: &str) -> Result<usize, SmartCalcError> {
+        let table: BTreeMap<String, BTreeMap<String, f64>> = serde_json::from_str(data).map_err(|error| SmartCalcError::invalid_argument(error.to_string()))?;
+        let mut rates = Vec::new();
+        for (date, currencies) in table.iter() {
+            for (currency, rate) in currencies.iter() {
+                rates.push(self.parse_currency_rate(date, currency, Some(*rate), 0)?);
+            }
+        }
+
+        Ok(self.add_currency_rates(rates))
+    }
+
+    fn parse_currency_rate(&self, date: &str, currency: &str, rate: Option<f64>, line: usize) -> Result<(Shared<CurrencyInfo>, NaiveDate, f64), SmartCalcError> {
+        let location = match line {
+            0 => String::new(),
+            line => format!("Line {}: ", line)
+        };
+
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| SmartCalcError::invalid_argument(format!("{}'{}' is not a valid date", location, date)))?;
+        let currency = read_currency(&self.config, currency).ok_or_else(|| SmartCalcError::UnknownCurrency { currency: currency.to_string(), span: Default::default() })?;
+        match rate {
+            Some(rate) if rate.is_finite() && rate > 0.0 => Ok((currency, date, rate)),
+            _ => Err(SmartCalcError::invalid_argument(format!("{}{} rate is not valid", location, currency.code)))
+        }
+    }
+
+    fn add_currency_rates(&mut self, rates: Vec<(Shared<CurrencyInfo>, NaiveDate, f64)>) -> usize {
+        let total = rates.len();
+        for (currency, date, rate) in rates {
+            self.config.currency_rate_history.entry(currency).or_default().insert(date, rate);
+        }
+        total
+    }
+    
     pub fn delete_rule(&mut self, language: String, rule_name: String) -> bool {
-        match self.config.rule.get_mut(&language) {
+        match self.config.get_rules_mut(&language) {
             Some(language_collection) => {
                 let position = language_collection.iter().position(|item| match item {
                     RuleType::API { tokens_list: _, rule: rule_item } => rule_name == rule_item.name(),
@@ -252,18 +369,10 @@ impl SmartCalc {
         }
     }
     
-    pub fn add_rule(&mut self, language: String, rules: Vec<String>, rule: Rc<dyn RuleTrait>) -> bool {
-        let mut rule_tokens = Vec::new();
+    pub fn add_rule(&mut self, language: String, rules: Vec<String>, rule: Shared<dyn RuleTrait>) -> bool {
+        let rule_tokens = rules.iter().map(|rule_item| self.config.pattern(&language, rule_item)).collect();
         
-        for rule_item in rules.iter() {
-            let mut session = Session::new();
-            session.set_language(language.to_string());
-            session.set_text(rule_item.to_string());
-            let tokens = Tokinizer::token_infos(&self.config, &session);
-            rule_tokens.push(tokens);
-        }
-        
-        let language_data = match self.config.rule.get_mut(&language) {
+        let language_data = match self.config.get_rules_mut(&language) {
             Some(language) => language,
             None => return false
         };
@@ -275,6 +384,28 @@ impl SmartCalc {
         true
     }
     
+    pub fn add_function(&mut self, name: String, arity: usize, function: Shared<dyn FunctionTrait>) -> bool {
+        /* Function names are parsed as text tokens, so only letters are allowed */
+        if name.is_empty() || !name.chars().all(char::is_alphabetic) {
+            return false;
+        }
+
+        /* Built-in and already added functions are kept */
+        if self.config.functions.contains_key(&name.to_lowercase()) {
+            return false;
+        }
+
+        self.config.functions.insert(name.to_lowercase(), FunctionType::API {
+            arity,
+            function
+        });
+        true
+    }
+
+    pub(crate) fn get_config(&self) -> &SmartCalcConfig {
+        &self.config
+    }
+
     pub fn format_result(&self, session: &Session, result: Rc<SmartCalcAstType>) -> String {
         format_result(&self.config, session, result)
     }
@@ -290,40 +421,84 @@ impl SmartCalc {
     }
 
 
-    pub(crate) fn execute_text(&self, session: &Session) -> ExecutionLine {
+    pub(crate) fn execute_text(&self, session: &Session, dependency: &mut LineDependency) -> ExecutionLine {
         log::debug!("> {}", session.current_line());
         if session.current_line().is_empty() {
             return None;
         }
 
+        session.take_line_rates();
+        session.start_trace();
         let mut tokinizer = Tokinizer::new(&self.config, session);
-        if !tokinizer.tokinize() {
+        let has_tokens = tokinizer.tokinize();
+
+        dependency.references = core::mem::take(&mut tokinizer.line_references);
+        dependency.aggregate = tokinizer.aggregate_line;
+        for token in tokinizer.tokens.iter() {
+            if let TokenType::Variable(variable) = token.deref() {
+                dependency.uses.insert(variable.to_string());
+            }
+        }
+
+        if !has_tokens {
             return None;
         }
 
+        if let Some(error) = tokinizer.token_error.take() {
+            return Some(ExecuteLine {
+                explanation: session.take_trace(),
+                ..ExecuteLine::new(Err(error), tokinizer.ui_tokens.get_tokens(), tokinizer.tokens, tokinizer.token_infos.clone())
+            });
+        }
+
+        let rule_error = tokinizer.rule_error.take();
         let mut syntax = SyntaxParser::new(session, &tokinizer);
         log::debug!(" > parse starting");
 
         let execution_result = match syntax.parse() {
             Ok(ast) => {
                 log::debug!(" > parse Ok {:?}", ast);
+                if let SmartCalcAstType::Assignment { variable, .. } = &ast {
+                    dependency.defines = Some(variable.to_string());
+                }
+
                 let ast_rc = Rc::new(ast);
 
-                match Interpreter::execute(&self.config, ast_rc, session) {
-                    Ok(ast) => Ok(ExecuteLineResult::new(self.format_result(session, ast.clone()), ast)),
-                    Err(error) => Err(error)
+                match Interpreter::execute(&self.config, ast_rc, session, tokinizer.line_span()) {
+                    Ok(ast) => {
+                        let line_rates = session.take_line_rates();
+                        for (currency, rate) in line_rates.rates {
+                            session.trace(|| ExplainStep::Rate { currency, rate });
+                        }
+
+                        let value = match ast.deref() {
+                            SmartCalcAstType::Item(item) => Value::from_item(&self.config, item.deref()),
+                            _ => None
+                        };
+
+                        Ok(ExecuteLineResult {
+                            rates_as_of: line_rates.as_of,
+                            value,
+                            ..ExecuteLineResult::new(self.format_result(session, ast.clone()), ast)
+                        })
+                    },
+                    Err(error) => Err(rule_error.unwrap_or(error))
                 }
             },
-            Err((error, _, _)) => {
+            Err(error) => {
                 log::debug!(" > parse Err");
                 log::info!("Syntax parse error, {}", error);
-                Err(error.to_string())
+                Err(rule_error.unwrap_or(error))
             }
         };
         
-        Some(ExecuteLine::new(execution_result, tokinizer.ui_tokens.get_tokens(), tokinizer.tokens, tokinizer.token_infos.clone()))
+        Some(ExecuteLine {
+            explanation: session.take_trace(),
+            ..ExecuteLine::new(execution_result, tokinizer.ui_tokens.get_tokens(), tokinizer.tokens, tokinizer.token_infos.clone())
+        })
     }
 
+    /// Calculate the lines of the text in a new session. Lines have no `explanation`, see `execute_with_explain`.
     pub fn execute<Tlan: Borrow<str>, Tdata: Borrow<str>>(&self, language: Tlan, data: Tdata) -> ExecuteResult {
         let mut session = Session::new();
 
@@ -332,8 +507,66 @@ impl SmartCalc {
         self.execute_session(&session)
     }
 
+    /// Same as `execute`, every line also gets the `explanation` of how it was calculated.
+    pub fn execute_with_explain<Tlan: Borrow<str>, Tdata: Borrow<str>>(&self, language: Tlan, data: Tdata) -> ExecuteResult {
+        let mut session = Session::new();
+
+        session.set_text(data.borrow().to_string());
+        session.set_language(language.borrow().to_string());
+        session.set_explain(true);
+        self.execute_session(&session)
+    }
+
+    /// Suggest variables, currencies, units, timezones, months and keywords for the word before the cursor.
+    ///
+    /// `line` is the line index in the session and `cursor` the character position in that line. Words the rules
+    /// expect after the text before the word come first, variables come first where a value is expected. Without a
+    /// word, only those are returned. Multi word variables and IANA timezones ("Europe/Berlin") match the words before
+    /// the cursor, cities only where a timezone is expected.
+    pub fn complete(&self, session: &Session, line: usize, cursor: usize) -> Vec<Completion> {
+        complete(&self.config, session, line, cursor)
+    }
+
+    /// Run the samples of every rule in the language and return the ones that do not match.
+    /// Multi line queries are compared with the output of the last line. A language that is not
+    /// configured is an error, so it is not mistaken for one whose samples all pass.
+    pub fn verify_samples<T: Borrow<str>>(&self, language: T) -> Result<Vec<SampleFailure>, SmartCalcError> {
+        let samples = match self.config.rule_samples.get(language.borrow()) {
+            Some(samples) => samples,
+            None => return Err(SmartCalcError::invalid_argument(format!("Language '{}' is not configured", language.borrow())))
+        };
+
+        let mut failures = Vec::new();
+        for (rule, sample) in samples.iter() {
+            let result = self.execute(language.borrow(), &sample.query[..]);
+            let actual = match result.lines.last() {
+                Some(Some(line)) => line.result.clone().map(|result| result.output),
+                _ => Err(SmartCalcError::syntax_error("Sample has no result", Default::default()))
+            };
+
+            if actual.as_ref() != Ok(&sample.result) {
+                failures.push(SampleFailure {
+                    rule: rule.to_string(),
+                    query: sample.query.to_string(),
+                    expected: sample.result.to_string(),
+                    actual
+                });
+            }
+        }
+
+        Ok(failures)
+    }
+
     pub fn basic_execute<T: Borrow<str>>(data: T, config: &SmartCalcConfig) -> anyhow::Result<f64> {
+        SmartCalc::basic_execute_with_locale(data, config, None)
+    }
+
+    /* Calculations of the configuration are written with "." decimals, whatever the default locale is */
+    pub(crate) fn basic_execute_with_locale<T: Borrow<str>>(data: T, config: &SmartCalcConfig, locale: Option<Locale>) -> anyhow::Result<f64> {
         let mut session = Session::new();
+        if let Some(locale) = locale {
+            session.set_locale(locale);
+        }
 
         session.set_text(data.borrow().to_string());
         session.set_language("en".borrow().to_string());
@@ -364,32 +597,50 @@ impl SmartCalc {
                 log::debug!(" > parse Ok {:?}", ast);
                 let ast_rc = Rc::new(ast);
 
-                match Interpreter::execute(config, ast_rc, &session) {
+                match Interpreter::execute(config, ast_rc, &session, tokinizer.line_span()) {
                     Ok(ast) => {
                         match ast.deref() {
                             SmartCalcAstType::Item(item) => Ok(item.get_underlying_number()),
                             _ => Err(anyhow!("Number not found"))
                         }
                     },
-                    Err(error) => Err(anyhow!(error))
+                    Err(error) => Err(anyhow!(error.to_string()))
                 }
             },
-            Err((error, _, _)) => {
+            Err(error) => {
                 log::debug!(" > parse Err");
                 log::info!("Syntax parse error, {}", error);
-                Err(anyhow!(error))
+                Err(anyhow!(error.to_string()))
             }
         }
     }
 
+    fn execute_line(&self, session: &Session) -> ExecutionLine {
+        let mut dependency = LineDependency::default();
+        let line_result = self.execute_text(session, &mut dependency);
+        let line_item = match &line_result {
+            Some(ExecuteLine { result: Ok(result), .. }) => match result.ast.deref() {
+                SmartCalcAstType::Item(item) => Some(item.clone()),
+                _ => None
+            },
+            _ => None
+        };
+
+        session.set_line_result(session.current_line_index(), line_item);
+        session.set_line_cache(session.current_line_index(), line_result.clone(), dependency);
+        line_result
+    }
+
     pub fn execute_session(&self, session: &Session) -> ExecuteResult {
         let mut results = ExecuteResult::default();
+        session.set_current_line_index(0);
+        session.take_dirty_lines();
 
         if session.has_value() {
             results.status = true;
             loop {
-                let line_result = self.execute_text(session);
-                results.lines.push(line_result);
+                results.changed_lines.push(session.current_line_index());
+                results.lines.push(self.execute_line(session));
                 if session.next_line().is_none() {
                     break;
                 }
@@ -398,14 +649,104 @@ impl SmartCalc {
 
         results
     }
+
+    /// Recalculate only the lines changed with `Session::update_line` and
+    /// the lines depending on them.
+    ///
+    /// Lines that are not recalculated are returned from the previous
+    /// execution. `ExecuteResult::changed_lines` lists the recalculated
+    /// lines. A session that was never executed is fully calculated.
+    pub fn execute_incremental(&self, session: &Session) -> ExecuteResult {
+        if !session.has_line_cache() {
+            return self.execute_session(session);
+        }
+
+        let mut results = ExecuteResult::default();
+        let dirty_lines = session.take_dirty_lines();
+        let mut all_variables = session.variables.borrow().clone();
+        let mut definitions: Vec<Option<String>> = Vec::new();
+        let mut changed_variables = BTreeSet::new();
+        let mut recalculated_lines = BTreeSet::new();
+        let mut recalculate_rest = false;
+
+        results.status = session.line_count() > 0;
+        for index in 0..session.line_count() {
+            let cache = session.get_line_cache(index);
+            let recalculate = match &cache {
+                Some((_, dependency)) => recalculate_rest || dirty_lines.contains(&index) ||
+                    dependency.uses.iter().any(|name| changed_variables.contains(name)) ||
+                    dependency.references.iter().any(|line| recalculated_lines.contains(line)),
+                None => true
+            };
+
+            match (recalculate, cache) {
+                (false, Some((line, dependency))) => {
+                    definitions.push(dependency.defines);
+                    results.lines.push(line);
+                },
+                (_, cache) => {
+                    Self::restore_variables(session, &all_variables, &definitions);
+                    session.set_current_line_index(index);
+                    results.lines.push(self.execute_line(session));
+
+                    let old_definition = cache.and_then(|(_, dependency)| dependency.defines);
+                    let new_definition = session.get_line_definition(index);
+
+                    /* A new or removed variable may change how the following lines are tokenized */
+                    if old_definition != new_definition {
+                        recalculate_rest = true;
+                    }
+
+                    changed_variables.extend(old_definition);
+                    changed_variables.extend(new_definition.clone());
+                    all_variables.extend(session.variables.borrow().iter().map(|(name, variable)| (name.clone(), variable.clone())));
+                    definitions.push(new_definition);
+                    recalculated_lines.insert(index);
+                    results.changed_lines.push(index);
+                }
+            };
+        }
+
+        Self::restore_variables(session, &all_variables, &definitions);
+        session.set_current_line_index(session.line_count().saturating_sub(1));
+        results
+    }
+
+    /* Bring the session variables back to the state they had after the given lines were calculated.
+       Variables that are neither defined by these lines nor set by the host do not exist yet. */
+    fn restore_variables(session: &Session, all_variables: &BTreeMap<String, Rc<VariableInfo>>, definitions: &[Option<String>]) {
+        let mut variables = session.variables.borrow_mut();
+        variables.clear();
+
+        for (name, variable) in all_variables.iter() {
+            let defined_before = definitions.iter().any(|definition| definition.as_ref() == Some(name));
+            let data = match defined_before {
+                true => definitions.iter().enumerate().rev()
+                    .filter(|(_, definition)| definition.as_ref() == Some(name))
+                    .find_map(|(index, _)| session.get_line_result(index))
+                    .map(|item| Rc::new(SmartCalcAstType::Item(item))),
+                false => match session.get_host_variable(name) {
+                    Some(data) => Some(data),
+                    None => continue
+                }
+            };
+
+            if let Some(data) = data {
+                *variable.data.borrow_mut() = data;
+            }
+
+            variables.insert(name.clone(), variable.clone());
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use core::ops::Deref;
-    use alloc::{collections::BTreeMap, string::{String, ToString}, vec, rc::Rc};
+    use alloc::{collections::BTreeMap, string::{String, ToString}, vec};
+    use crate::shared::Shared;
 
-    use crate::{SmartCalc, types::{TokenType, NumberType}, RuleTrait, SmartCalcConfig};
+    use crate::{SmartCalc, types::{TokenType, NumberType}, RuleTrait, FunctionTrait, SmartCalcConfig, SmartCalcError, Span, Value};
 
     #[derive(Default)]
     pub struct Test1;
@@ -417,7 +758,7 @@ mod test {
             match fields.get("surname") {
                 Some(TokenType::Text(surname)) => {
                     assert_eq!(surname, &"baris".to_string());
-                    Some(TokenType::Number(2022.0, NumberType::Decimal))
+                    Some(TokenType::Number(number!(2022.0), NumberType::Decimal))
                 },
                 _ => None
             }
@@ -470,13 +811,13 @@ mod test {
     #[test]
     fn add_rule_1() ->  Result<(), ()> {
         let mut calculater = SmartCalc::default();
-        let test1 = Rc::new(Test1::default());
+        let test1 = Shared::new(Test1::default());
         calculater.add_rule("en".to_string(), vec!["erhan {TEXT:surname}".to_string(), "{TEXT:surname} erhan".to_string()], test1.clone());
         let result = calculater.execute("en".to_string(), "erhan baris");
-        check_basic_rule_output!(result, TokenType::Number(2022.0, NumberType::Decimal));
+        check_basic_rule_output!(result, TokenType::Number(number!(2022.0), NumberType::Decimal));
 
         let result = calculater.execute("en".to_string(), "baris erhan");
-        check_basic_rule_output!(result, TokenType::Number(2022.0, NumberType::Decimal));
+        check_basic_rule_output!(result, TokenType::Number(number!(2022.0), NumberType::Decimal));
 
         Ok(())
     }
@@ -484,13 +825,13 @@ mod test {
     #[test]
     fn add_rule_2() ->  Result<(), ()> {
         let mut calculater = SmartCalc::default();
-        let test1 = Rc::new(Test1::default());
+        let test1 = Shared::new(Test1::default());
         calculater.add_rule("en".to_string(), vec!["erhan {TEXT:surname:baris}".to_string(), "{TEXT:surname:baris} erhan".to_string()], test1.clone());
         let result = calculater.execute("en".to_string(), "erhan baris");
-        check_basic_rule_output!(result, TokenType::Number(2022.0, NumberType::Decimal));
+        check_basic_rule_output!(result, TokenType::Number(number!(2022.0), NumberType::Decimal));
 
         let result = calculater.execute("en".to_string(), "baris erhan");
-        check_basic_rule_output!(result, TokenType::Number(2022.0, NumberType::Decimal));
+        check_basic_rule_output!(result, TokenType::Number(number!(2022.0), NumberType::Decimal));
 
         Ok(())
     }
@@ -498,7 +839,7 @@ mod test {
     #[test]
     fn delete_rule_2() ->  Result<(), ()> {
         let mut calculater = SmartCalc::default();
-        let test1 = Rc::new(Test1::default());
+        let test1 = Shared::new(Test1::default());
         assert!(calculater.add_rule("en".to_string(), vec!["erhan {TEXT:surname:baris}".to_string(), "{TEXT:surname:baris} erhan".to_string()], test1.clone()));
         assert!(calculater.delete_rule("en".to_string(), test1.name().clone()));
 
@@ -508,7 +849,7 @@ mod test {
     #[test]
     fn delete_rule_3() ->  Result<(), ()> {
         let mut calculater = SmartCalc::default();
-        let test1 = Rc::new(Test1::default());
+        let test1 = Shared::new(Test1::default());
         assert!(!calculater.delete_rule("en".to_string(), test1.name().clone()));
 
         Ok(())
@@ -628,8 +969,8 @@ mod test {
             };
             
             let price = match &coin[..] {
-                "btc" => 1000.0 * count,
-                "eth" => 800.0 * count,
+                "btc" => number!(1000.0) * count,
+                "eth" => number!(800.0) * count,
                 _ => return None
             };
             
@@ -640,30 +981,279 @@ mod test {
     #[test]
     fn add_rule_3() ->  Result<(), ()> {
         let mut calculater = SmartCalc::default();
-        let test1 = Rc::new(Coin::default());
+        let test1 = Shared::new(Coin::default());
         calculater.add_rule("en".to_string(), vec!["{NUMBER:count} {TEXT:coin}".to_string()], test1.clone());
         let result = calculater.execute("en".to_string(), "10 btc to usd");
-        check_basic_rule_output!(result, TokenType::Money(10000.0, calculater.config.get_currency("usd".to_string()).unwrap()));
+        check_basic_rule_output!(result, TokenType::Money(number!(10000.0), calculater.config.get_currency("usd".to_string()).unwrap()));
         Ok(())
     }
     
     #[test]
     fn add_rule_4() ->  Result<(), ()> {
         let mut calculater = SmartCalc::default();
-        let test1 = Rc::new(Coin::default());
+        let test1 = Shared::new(Coin::default());
         calculater.add_rule("en".to_string(), vec!["{NUMBER:count} {TEXT:coin}".to_string()], test1.clone());
         let result = calculater.execute("en".to_string(), "10 eth to usd");
-        check_basic_rule_output!(result, TokenType::Money(8000.0, calculater.config.get_currency("usd".to_string()).unwrap()));
+        check_basic_rule_output!(result, TokenType::Money(number!(8000.0), calculater.config.get_currency("usd".to_string()).unwrap()));
         Ok(())
     }
     
     #[test]
     fn add_rule_5() ->  Result<(), ()> {
         let mut calculater = SmartCalc::default();
-        let test1 = Rc::new(Coin::default());
+        let test1 = Shared::new(Coin::default());
         calculater.add_rule("en".to_string(), vec!["{NUMBER:count} {TEXT:coin}".to_string()], test1.clone());
         let result = calculater.execute("en".to_string(), "10 eth to dkk");
-        check_basic_rule_output!(result, TokenType::Money(49644.9970792, calculater.config.get_currency("dkk".to_string()).unwrap()));
+        check_basic_rule_output!(result, TokenType::Money(number!(49644.9970792), calculater.config.get_currency("dkk".to_string()).unwrap()));
+        Ok(())
+    }
+
+    #[derive(Default)]
+    pub struct Tax;
+
+    impl FunctionTrait for Tax {
+        fn name(&self) -> String {
+            "Tax".to_string()
+        }
+
+        fn call(&self, _: &SmartCalcConfig, arguments: &[Value]) -> Result<Value, SmartCalcError> {
+            match &arguments[0] {
+                Value::Number(number) => Ok(Value::Number(*number * number!(1.18))),
+                Value::Money { amount, currency } => Ok(Value::Money { amount: *amount * number!(1.18), currency: currency.to_string() }),
+                _ => Err(SmartCalcError::InvalidArgument { message: "Tax needs a number or money".to_string(), span: Span::default() })
+            }
+        }
+    }
+
+    macro_rules! check_output {
+        ($result:ident, $index:literal, $expected:literal) => {
+            assert!($result.status);
+            match $result.lines.get($index) {
+                Some(Some(line)) => assert_eq!(line.result.as_ref().unwrap().output, $expected.to_string()),
+                _ => assert!(false, "Result line not found")
+            };
+        };
+    }
+
+    #[test]
+    fn add_function_1() ->  Result<(), ()> {
+        let mut calculater = SmartCalc::default();
+        assert!(calculater.add_function("tax".to_string(), 1, Shared::new(Tax)));
+        let result = calculater.execute("en".to_string(), "tax($100) + $2");
+        check_output!(result, 0, "$120,00");
+        Ok(())
+    }
+
+    #[test]
+    fn add_function_2() ->  Result<(), ()> {
+        let mut calculater = SmartCalc::default();
+        assert!(calculater.add_function("Tax".to_string(), 1, Shared::new(Tax)));
+        let result = calculater.execute("en".to_string(), "price = 50\ntax(price)");
+        check_output!(result, 1, "59");
+        Ok(())
+    }
+
+    #[test]
+    fn add_function_3() ->  Result<(), ()> {
+        let mut calculater = SmartCalc::default();
+        assert!(!calculater.add_function("tax2".to_string(), 1, Shared::new(Tax)));
+        assert!(calculater.add_function("tax".to_string(), 1, Shared::new(Tax)));
+        let result = calculater.execute("en".to_string(), "tax(1, 2)");
+        assert!(result.lines[0].as_ref().unwrap().result.is_err());
+
+        /* Names of built-in or added functions are not replaced */
+        assert!(!calculater.add_function("Tax".to_string(), 1, Shared::new(Tax)));
+        assert!(!calculater.add_function("abs".to_string(), 1, Shared::new(Tax)));
+        let result = calculater.execute("en".to_string(), "abs(-2)");
+        check_output!(result, 0, "2");
+
+        /* Errors of the function are reported with the span of the call */
+        let result = calculater.execute("en".to_string(), "1 + tax(2 days)");
+        assert_eq!(result.lines[0].as_ref().unwrap().result.as_ref().err(), Some(&SmartCalcError::InvalidArgument { message: "Tax needs a number or money".to_string(), span: Span::new(4, 15) }));
+        Ok(())
+    }
+
+    #[derive(Default)]
+    pub struct Stock;
+
+    impl FunctionTrait for Stock {
+        fn name(&self) -> String {
+            "Stock".to_string()
+        }
+
+        fn call(&self, _: &SmartCalcConfig, _: &[Value]) -> Result<Value, SmartCalcError> {
+            Ok(Value::Money { amount: number!(150.5), currency: "USD".to_string() })
+        }
+    }
+
+    #[test]
+    fn add_function_4() ->  Result<(), ()> {
+        let mut calculater = SmartCalc::default();
+        assert!(calculater.add_function("stock".to_string(), 0, Shared::new(Stock)));
+        let result = calculater.execute("en".to_string(), "stock() * 2");
+        check_output!(result, 0, "$301,00");
+        Ok(())
+    }
+
+    #[test]
+    fn merge_json_1() ->  Result<(), ()> {
+        use crate::{ConfigError, MergeConflict};
+
+        let mut calculater = SmartCalc::default();
+        let report = calculater.merge_json(r#"{
+            "currencies": {
+                "XYZ": { "code": "XYZ", "symbol": "X", "thousandsSeparator": ",", "decimalSeparator": ".", "symbolOnLeft": false, "spaceBetweenAmountAndSymbol": true, "decimalDigits": 2 },
+                "USD": { "code": "USD", "symbol": "$", "thousandsSeparator": ",", "decimalSeparator": ".", "symbolOnLeft": true, "spaceBetweenAmountAndSymbol": false, "decimalDigits": 2 }
+            },
+            "currency_rates": { "xyz": 2.0 },
+            "currency_alias": { "zed": "xyz", "bucks": "abc" },
+            "timezones": { "EST": -300, "XST": 120 },
+            "types": [{
+                "name": "kitchen",
+                "items": [{
+                    "index": 1,
+                    "format": "{value} tsp",
+                    "parse": ["{NUMBER:value} {TEXT:type:tsp}"],
+                    "upgrade_code": "{value} / 3",
+                    "downgrade_code": "{value}",
+                    "names": ["tsp"]
+                }, {
+                    "index": 2,
+                    "format": "{value} tbsp",
+                    "parse": ["{NUMBER:value} {TEXT:type:tbsp}"],
+                    "upgrade_code": "{value}",
+                    "downgrade_code": "{value} * 3",
+                    "names": ["tbsp"]
+                }]
+            }, {
+                "name": "memory",
+                "items": [{ "index": 1, "format": "{value}b", "parse": [], "upgrade_code": "{value}", "downgrade_code": "{value}", "names": [] }]
+            }],
+            "type_conversion": [{
+                "source": { "name": "kitchen", "index": 1 },
+                "target": { "name": "unknown", "index": 1 },
+                "to_source_calculation": "{value}",
+                "to_target_calculation": "{value}"
+            }]
+        }"#).unwrap();
+
+        assert_eq!(report.added.len(), 6);
+        assert_eq!(report.conflicts, vec![
+            MergeConflict { section: "currencies".to_string(), name: "USD".to_string(), reason: "currency already defined".to_string() },
+            MergeConflict { section: "currency_alias".to_string(), name: "bucks".to_string(), reason: "'abc' currency not found".to_string() },
+            MergeConflict { section: "timezones".to_string(), name: "EST".to_string(), reason: "timezone already defined".to_string() },
+            MergeConflict { section: "types".to_string(), name: "memory:1".to_string(), reason: "dynamic type already defined".to_string() },
+            MergeConflict { section: "type_conversion".to_string(), name: "kitchen:1 to unknown:1".to_string(), reason: "unknown:1 type not defined".to_string() }
+        ]);
+
+        let result = calculater.execute("en".to_string(), "10 zed to usd\n6 tsp to tbsp\n1 GB to mb");
+        check_output!(result, 0, "$5,00");
+        check_output!(result, 1, "2 tbsp");
+        check_output!(result, 2, "1.000 MB");
+
+        let json: serde_json::Value = serde_json::from_str(crate::constants::JSON_DATA).unwrap();
+        let pack = alloc::format!(r#"{{"languages": {{"xx": {}}}}}"#, json["languages"]["en"]);
+        let report = calculater.merge_json(&pack).unwrap();
+        assert_eq!(report.added.len(), 1);
+        let result = calculater.execute("xx".to_string(), "10 zed to usd");
+        check_output!(result, 0, "$5,00");
+        assert_eq!(calculater.merge_json(&pack).unwrap().conflicts[0].reason, "language already defined".to_string());
+
+        assert_eq!(calculater.merge_json(r#"{"parse": {}}"#), Err(ConfigError::UnsupportedSection { name: "parse".to_string() }));
+        assert!(matches!(calculater.merge_json("{"), Err(ConfigError::InvalidJson { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn merge_json_decimal_codes() ->  Result<(), ()> {
+        let mut calculater = SmartCalc::default();
+        let report = calculater.merge_json(r#"{
+            "types": [{
+                "name": "stride",
+                "items": [{
+                    "index": 1,
+                    "format": "{value} pace",
+                    "parse": ["{NUMBER:value} {TEXT:type:pace}"],
+                    "upgrade_code": "{value} / 2.5",
+                    "downgrade_code": "{value}",
+                    "names": ["pace"]
+                }, {
+                    "index": 2,
+                    "format": "{value} stride",
+                    "parse": ["{NUMBER:value} {TEXT:type:stride}"],
+                    "upgrade_code": "{value}",
+                    "downgrade_code": "{value} * 2.5",
+                    "names": ["stride"]
+                }]
+            }]
+        }"#).unwrap();
+        assert_eq!(report.added.len(), 2);
+
+        /* "2.5" is two and a half even though the default locale uses "," for decimals */
+        let result = calculater.execute("en".to_string(), "5 pace to stride
+2 stride to pace");
+        check_output!(result, 0, "2 stride");
+        check_output!(result, 1, "5 pace");
+        Ok(())
+    }
+
+    #[test]
+    fn date_rule_priority() -> Result<(), ()> {
+        let is_date_rule = |rule: &crate::tokinizer::RuleType| matches!(rule, crate::tokinizer::RuleType::Internal { function_name, .. } if function_name == "small_date");
+        let mut calculater = SmartCalc::default();
+        for language in ["en", "tr", "de", "fr", "es", "bg"] {
+            assert!(is_date_rule(&calculater.config.get_rules(language).unwrap()[0]));
+        }
+
+        calculater.set_date_rule("en", vec!["{MONTH:month} {NUMBER:day} {NUMBER:year}".to_string()]);
+        let rules = calculater.config.get_rules("en").unwrap();
+        assert!(is_date_rule(&rules[0]));
+        assert_eq!(rules.iter().filter(|rule| is_date_rule(rule)).count(), 1);
+
+        let result = calculater.execute("en", "march 12 2021");
+        check_output!(result, 0, "12 Mar 2021");
+        Ok(())
+    }
+
+    #[test]
+    fn french_today() -> Result<(), ()> {
+        let calculater = SmartCalc::default();
+        let result = calculater.execute("fr", "aujourdhui\naujourd'hui\nAujourd’hui");
+        let outputs = result.lines.iter().map(|line| line.as_ref().unwrap().result.as_ref().unwrap().output.to_string()).collect::<alloc::vec::Vec<_>>();
+        assert_eq!(outputs[1], outputs[0]);
+        assert_eq!(outputs[2], outputs[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_samples_1() -> Result<(), ()> {
+        use alloc::vec::Vec;
+        use crate::{SampleFailure, SmartCalcError};
+        let mut calculater = SmartCalc::default();
+        for language in ["en", "tr", "de", "fr", "es", "bg"] {
+            assert_eq!(calculater.verify_samples(language), Ok(Vec::new()));
+        }
+        assert_eq!(calculater.verify_samples("xx"), Err(SmartCalcError::invalid_argument("Language 'xx' is not configured")));
+
+        let mut json: serde_json::Value = serde_json::from_str(crate::constants::JSON_DATA).unwrap();
+        let mut language = json["languages"]["en"].take();
+        language["rules"] = serde_json::json!({
+            "number_of": {
+                "rules": ["{PERCENT:p} of {NUMBER_OR_MONEY:number}"],
+                "samples": [
+                    { "query": "%20 of 200", "result": "40" },
+                    { "query": "price = 200\n%10 of price", "result": "25" },
+                    { "query": "", "result": "0" }
+                ]
+            }
+        });
+        let pack = alloc::format!(r#"{{"languages": {{"xx": {}}}}}"#, language);
+        calculater.merge_json(&pack).unwrap();
+
+        assert_eq!(calculater.verify_samples("xx"), Ok(vec![
+            SampleFailure { rule: "number_of".to_string(), query: "price = 200\n%10 of price".to_string(), expected: "25".to_string(), actual: Ok("20".to_string()) },
+            SampleFailure { rule: "number_of".to_string(), query: "".to_string(), expected: "0".to_string(), actual: Err(SmartCalcError::syntax_error("Sample has no result", Default::default())) }
+        ]));
         Ok(())
     }
 }
@@ -0,0 +1,165 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::compiler::DataItem;
+use crate::compiler::date::DateItem;
+use crate::compiler::date_time::DateTimeItem;
+use crate::compiler::duration::DurationItem;
+use crate::compiler::dynamic_type::DynamicTypeItem;
+use crate::compiler::money::MoneyItem;
+use crate::compiler::number::NumberItem;
+use crate::compiler::percent::PercentItem;
+use crate::compiler::quantity::QuantityItem;
+use crate::compiler::time::TimeItem;
+use crate::config::SmartCalcConfig;
+use crate::error::SmartCalcError;
+use crate::types::{Number, NumberType, TimeOffset, TokenType};
+use crate::unit::Unit;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Serializable form of a calculated value.
+///
+/// Numbers are kept as text so decimal values survive the round trip without
+/// precision loss. Currencies are referenced by code and dynamic types by
+/// group name and index, units by their symbol. They are resolved against the
+/// configuration on restore.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ItemSnapshot {
+    Number { value: String, number_type: NumberType },
+    Percent { value: f64 },
+    Money { price: String, currency: String },
+    Date { date: String, timezone: TimeOffset },
+    Time { time: String, timezone: TimeOffset },
+    DateTime { date_time: String, timezone: TimeOffset },
+    Duration { seconds: i64, nanoseconds: i32 },
+    DynamicType { value: f64, group: String, index: usize },
+    Quantity { value: f64, unit: String }
+}
+
+/* Variable names are matched token by token, so each token keeps its kind */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub(crate) enum NameTokenSnapshot {
+    Text { text: String },
+    Operator { operator: char },
+    Month { month: u32 },
+    Timezone { name: String, offset: i32 },
+    Function { name: String },
+    Value { value: ItemSnapshot }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct VariableSnapshot {
+    pub name: Vec<NameTokenSnapshot>,
+    pub value: Option<ItemSnapshot>,
+
+    /// Set with `Session::set_variable` instead of a line of the text.
+    #[serde(default)]
+    pub host: bool
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SessionSnapshot {
+    pub language: String,
+    pub text: String,
+    pub variables: Vec<VariableSnapshot>,
+    pub line_results: Vec<Option<ItemSnapshot>>
+}
+
+fn parse_number(value: &str) -> Result<Number, SmartCalcError> {
+    value.parse::<Number>().map_err(|_| SmartCalcError::invalid_argument(format!("'{}' is not a valid number", value)))
+}
+
+fn parse_date_time(value: &str) -> Result<NaiveDateTime, SmartCalcError> {
+    NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT).map_err(|_| SmartCalcError::invalid_argument(format!("'{}' is not a valid date time", value)))
+}
+
+impl ItemSnapshot {
+    pub(crate) fn from_token(token: &TokenType) -> Option<ItemSnapshot> {
+        Some(match token {
+            TokenType::Number(number, number_type) => ItemSnapshot::Number { value: number.to_string(), number_type: *number_type },
+            TokenType::Percent(percent) => ItemSnapshot::Percent { value: *percent },
+            TokenType::Money(price, currency) => ItemSnapshot::Money { price: price.to_string(), currency: currency.code.to_string() },
+            TokenType::Date(date, timezone) => ItemSnapshot::Date { date: date.format(DATE_FORMAT).to_string(), timezone: timezone.clone() },
+            TokenType::Time(time, timezone) => ItemSnapshot::Time { time: time.format(DATE_TIME_FORMAT).to_string(), timezone: timezone.clone() },
+            TokenType::DateTime(date_time, timezone) => ItemSnapshot::DateTime { date_time: date_time.format(DATE_TIME_FORMAT).to_string(), timezone: timezone.clone() },
+            TokenType::Duration(duration) => ItemSnapshot::Duration { seconds: duration.num_seconds(), nanoseconds: duration.subsec_nanos() },
+            TokenType::DynamicType(value, dynamic_type) => ItemSnapshot::DynamicType {
+                value: *value,
+                group: dynamic_type.group_name.to_string(),
+                index: dynamic_type.index
+            },
+            TokenType::Quantity(value, unit) => ItemSnapshot::Quantity { value: *value, unit: unit.to_string() },
+            _ => return None
+        })
+    }
+
+    pub(crate) fn from_item(item: &dyn DataItem) -> Option<ItemSnapshot> {
+        ItemSnapshot::from_token(&item.as_token_type())
+    }
+
+    pub(crate) fn to_item(&self, config: &SmartCalcConfig) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        Ok(match self {
+            ItemSnapshot::Number { value, number_type } => Rc::new(NumberItem(parse_number(value)?, *number_type)),
+            ItemSnapshot::Percent { value } => Rc::new(PercentItem(*value)),
+            ItemSnapshot::Money { price, currency } => match config.get_currency(currency.to_lowercase()) {
+                Some(currency_info) => Rc::new(MoneyItem(parse_number(price)?, currency_info)),
+                None => return Err(SmartCalcError::UnknownCurrency { currency: currency.to_string(), span: Default::default() })
+            },
+            ItemSnapshot::Date { date, timezone } => match NaiveDate::parse_from_str(date, DATE_FORMAT) {
+                Ok(date) => Rc::new(DateItem(date, timezone.clone())),
+                Err(_) => return Err(SmartCalcError::invalid_argument(format!("'{}' is not a valid date", date)))
+            },
+            ItemSnapshot::Time { time, timezone } => Rc::new(TimeItem(parse_date_time(time)?, timezone.clone())),
+            ItemSnapshot::DateTime { date_time, timezone } => Rc::new(DateTimeItem(parse_date_time(date_time)?, timezone.clone())),
+            ItemSnapshot::Duration { seconds, nanoseconds } => Rc::new(DurationItem(Duration::seconds(*seconds) + Duration::nanoseconds(*nanoseconds as i64))),
+            ItemSnapshot::DynamicType { value, group, index } => match config.types.get(group).and_then(|group| group.get(index)) {
+                Some(dynamic_type) => Rc::new(DynamicTypeItem(*value, dynamic_type.clone())),
+                None => return Err(SmartCalcError::UnknownUnit { unit: format!("{}:{}", group, index), span: Default::default() })
+            },
+            ItemSnapshot::Quantity { value, unit } => match Unit::parse(config, unit) {
+                Some(unit) => Rc::new(QuantityItem(*value, Rc::new(unit))),
+                None => return Err(SmartCalcError::UnknownUnit { unit: unit.to_string(), span: Default::default() })
+            }
+        })
+    }
+}
+
+impl NameTokenSnapshot {
+    pub(crate) fn from_token(token: &TokenType) -> NameTokenSnapshot {
+        match token {
+            TokenType::Operator(operator) => NameTokenSnapshot::Operator { operator: *operator },
+            TokenType::Month(month) => NameTokenSnapshot::Month { month: *month },
+            TokenType::Timezone(name, offset) => NameTokenSnapshot::Timezone { name: name.to_string(), offset: *offset },
+            TokenType::Function(name) => NameTokenSnapshot::Function { name: name.to_string() },
+            _ => match ItemSnapshot::from_token(token) {
+                Some(value) => NameTokenSnapshot::Value { value },
+                None => NameTokenSnapshot::Text { text: token.to_string() }
+            }
+        }
+    }
+
+    pub(crate) fn to_token(&self, config: &SmartCalcConfig) -> Result<TokenType, SmartCalcError> {
+        Ok(match self {
+            NameTokenSnapshot::Text { text } => TokenType::Text(text.to_string()),
+            NameTokenSnapshot::Operator { operator } => TokenType::Operator(*operator),
+            NameTokenSnapshot::Month { month } => TokenType::Month(*month),
+            NameTokenSnapshot::Timezone { name, offset } => TokenType::Timezone(name.to_string(), *offset),
+            NameTokenSnapshot::Function { name } => TokenType::Function(name.to_string()),
+            NameTokenSnapshot::Value { value } => value.to_item(config)?.as_token_type()
+        })
+    }
+}
@@ -4,34 +4,80 @@
  * Licensed under the GNU General Public License v2.0.
  */
 
+use crate::compiler::number::NumberItem;
 use crate::types::*;
 use crate::syntax::{SyntaxParser, SyntaxParserTrait};
+use crate::syntax::primative::PrimativeParser;
 use crate::syntax::unary::UnaryParser;
+use core::ops::Deref;
 use alloc::rc::Rc;
 
-pub struct ModuloParser;
 pub struct MultiplyDivideParser;
+pub struct PowerParser;
 pub struct AddSubtractParser;
 
-impl SyntaxParserTrait for ModuloParser {
+impl SyntaxParserTrait for MultiplyDivideParser {
     fn parse(parser: &mut SyntaxParser) -> AstResult {
-        parse_binary::<MultiplyDivideParser>(parser, &['%'])
+        parse_binary::<UnaryParser>(parser, &['*', '/', '\\', '%'])
     }
 }
 
-impl SyntaxParserTrait for MultiplyDivideParser {
+impl SyntaxParserTrait for PowerParser {
     fn parse(parser: &mut SyntaxParser) -> AstResult {
-        parse_binary::<UnaryParser>(parser, &['*', '/'])
+        let start_index = parser.get_index();
+        let left_expr = PrimativeParser::parse(parser)?;
+        
+        if let SmartCalcAstType::None = left_expr {
+            return Ok(left_expr)
+        }
+
+        let index_backup = parser.get_index();
+
+        /* Power is right associative and the right side can have a sign, like "2 ^ -1" */
+        if let Some(operator) = parser.match_operator(&['^']) {
+            match UnaryParser::parse(parser)? {
+                SmartCalcAstType::None => parser.set_index(index_backup),
+                right_expr => {
+                    let (sign, left_expr) = match index_backup == start_index + 1 {
+                        true => split_literal_sign(parser, start_index, left_expr),
+                        false => (None, left_expr)
+                    };
+
+                    let power = SmartCalcAstType::Binary {
+                        left: Rc::new(left_expr),
+                        operator,
+                        right: Rc::new(right_expr),
+                        span: parser.span_from(start_index)
+                    };
+
+                    return Ok(match sign {
+                        Some(sign) => SmartCalcAstType::PrefixUnary(sign, Rc::new(power)),
+                        None => power
+                    });
+                }
+            };
+        }
+
+        Ok(left_expr)
+    }
+}
+
+/* The tokinizer keeps the sign in number literals, but "-2 ^ 2" is "-(2 ^ 2)" */
+fn split_literal_sign(parser: &SyntaxParser, index: usize, ast: SmartCalcAstType) -> (Option<char>, SmartCalcAstType) {
+    match parser.tokinizer.tokens.get(index).map(|token| token.deref()) {
+        Some(TokenType::Number(number, number_type)) if *number < Number::default() => (Some('-'), SmartCalcAstType::Item(Rc::new(NumberItem(-*number, *number_type)))),
+        _ => (None, ast)
     }
 }
 
 impl SyntaxParserTrait for AddSubtractParser {
     fn parse(parser: &mut SyntaxParser) -> AstResult {
-        parse_binary::<ModuloParser>(parser, &['+', '-'])
+        parse_binary::<MultiplyDivideParser>(parser, &['+', '-'])
     }
 }
 
 pub fn parse_binary<T: SyntaxParserTrait>(parser: &mut SyntaxParser, operators: &[char]) -> AstResult {
+    let start_index = parser.get_index();
     let mut left_expr = T::parse(parser)?;
     
     if let SmartCalcAstType::None = left_expr {
@@ -50,7 +96,8 @@ pub fn parse_binary<T: SyntaxParserTrait>(parser: &mut SyntaxParser, operators:
                         left_expr = SmartCalcAstType::Binary {
                             left: Rc::new(left_expr),
                             operator,
-                            right: Rc::new(right_expr.unwrap())
+                            right: Rc::new(right_expr.unwrap()),
+                            span: parser.span_from(start_index)
                         };
                         break;
                     },
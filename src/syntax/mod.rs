@@ -17,6 +17,7 @@ use crate::syntax::util::map_parser;
 
 use crate::tokinizer::Tokinizer;
 use crate::types::*;
+use crate::error::Span;
 use alloc::rc::Rc;
 use crate::session::Session;
 use crate::syntax::assignment::AssignmentParser;
@@ -46,7 +47,21 @@ impl<'a> SyntaxParser<'a> {
 
     pub fn parse(&mut self) -> AstResult {
         let ast = map_parser(self, &[AssignmentParser::parse, AddSubtractParser::parse])?;
-        Ok(ast)
+
+        /* "... in N" converts the whole expression */
+        let (unit, span) = match &self.tokinizer.target_unit {
+            Some((unit, span)) => (unit.clone(), *span),
+            None => return Ok(ast)
+        };
+
+        Ok(match ast {
+            SmartCalcAstType::None => ast,
+            SmartCalcAstType::Assignment { variable, expression } => SmartCalcAstType::Assignment {
+                variable,
+                expression: Rc::new(SmartCalcAstType::Conversion { expression, unit, span })
+            },
+            _ => SmartCalcAstType::Conversion { expression: Rc::new(ast), unit, span }
+        })
     }
 
     pub fn set_index(&self, index: usize) {
@@ -65,6 +80,27 @@ impl<'a> SyntaxParser<'a> {
         }
     }
 
+    pub fn token_span(&self, index: usize) -> Span {
+        match self.tokinizer.token_spans.get(index) {
+            Some(span) => *span,
+            None => match self.tokinizer.token_spans.last() {
+                Some(span) => Span::new(span.end, span.end),
+                None => Span::default()
+            }
+        }
+    }
+
+    pub fn current_span(&self) -> Span {
+        self.token_span(self.index.get())
+    }
+
+    pub fn span_from(&self, start_index: usize) -> Span {
+        match self.index.get() > start_index {
+            true => self.token_span(start_index).merge(self.token_span(self.index.get() - 1)),
+            false => self.token_span(start_index)
+        }
+    }
+
     pub fn consume_token(&self) -> Option<Rc<TokenType>> {
         self.index.set(self.index.get() + 1);
         self.tokinizer.tokens.get(self.index.get()).cloned()
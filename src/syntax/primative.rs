@@ -6,16 +6,10 @@
 
 
 use alloc::rc::Rc;
+use alloc::vec::Vec;
 
-use crate::compiler::date::DateItem;
-use crate::compiler::date_time::DateTimeItem;
-use crate::compiler::duration::DurationItem;
-use crate::compiler::money::MoneyItem;
-use crate::compiler::dynamic_type::DynamicTypeItem;
-use crate::compiler::number::NumberItem;
-use crate::compiler::percent::PercentItem;
-use crate::compiler::time::TimeItem;
 use crate::types::*;
+use crate::error::SmartCalcError;
 use crate::syntax::util::*;
 use crate::syntax::{SyntaxParser, SyntaxParserTrait};
 use crate::syntax::binary::AddSubtractParser;
@@ -29,7 +23,7 @@ impl PrimativeParser {
         let token = parser.peek_token();
 
         if token.is_err() {
-            return Err(("No more token", 0, 0));
+            return Err(SmartCalcError::syntax_error("No more token", parser.current_span()));
         }
 
         let result = match token.unwrap().deref() {
@@ -38,19 +32,15 @@ impl PrimativeParser {
                 parser.consume_token();
                 return Ok(SmartCalcAstType::None);
             },
-            TokenType::DynamicType(number, dynamic_type)     => Ok(SmartCalcAstType::Item(Rc::new(DynamicTypeItem(*number, dynamic_type.clone())))),
-            TokenType::Money(price, currency)     => Ok(SmartCalcAstType::Item(Rc::new(MoneyItem(*price, currency.clone())))),
-            TokenType::Number(double, number_type)     => Ok(SmartCalcAstType::Item(Rc::new(NumberItem(*double, *number_type)))),
             TokenType::Field(field_type)  => Ok(SmartCalcAstType::Field(field_type.clone())),
-            TokenType::Percent(percent)   => Ok(SmartCalcAstType::Item(Rc::new(PercentItem(*percent)))),
-            TokenType::Time(time, tz)         => Ok(SmartCalcAstType::Item(Rc::new(TimeItem(*time, tz.clone())))),
-            TokenType::Date(date, tz)         => Ok(SmartCalcAstType::Item(Rc::new(DateItem(*date, tz.clone())))),
-            TokenType::DateTime(date_time, tz)         => Ok(SmartCalcAstType::Item(Rc::new(DateTimeItem(*date_time, tz.clone())))),
-            TokenType::Duration(duration)         => Ok(SmartCalcAstType::Item(Rc::new(DurationItem(*duration)))),
             TokenType::Variable(variable) => Ok(SmartCalcAstType::Variable(variable.clone())),
-            _ => {
-                parser.consume_token();
-                return Err(("No more token", 0, 0));
+            token => match token.as_item() {
+                Some(item) => Ok(SmartCalcAstType::Item(item)),
+                None => {
+                    let span = parser.current_span();
+                    parser.consume_token();
+                    return Err(SmartCalcError::syntax_error("No more token", span));
+                }
             }
         };
 
@@ -63,8 +53,52 @@ impl PrimativeParser {
                 parser.consume_token();
                 Ok(ast)
             },
-            Err((message, line, column)) => Err((message, line, column))
+            Err(error) => Err(error)
+        }
+    }
+
+    pub fn parse_function_call(parser: &mut SyntaxParser) -> AstResult {
+        let index_backup = parser.get_index();
+        let name = match parser.peek_token() {
+            Ok(token) => match token.deref() {
+                TokenType::Function(name) => name.clone(),
+                _ => return Ok(SmartCalcAstType::None)
+            },
+            _ => return Ok(SmartCalcAstType::None)
+        };
+
+        parser.consume_token();
+        if parser.match_operator(&['(']).is_none() {
+            let span = parser.current_span();
+            parser.set_index(index_backup);
+            return Err(SmartCalcError::syntax_error("Function call not valid", span));
         }
+
+        let mut arguments = Vec::new();
+        if parser.match_operator(&[')']).is_none() {
+            loop {
+                let ast = AddSubtractParser::parse(parser);
+                if is_ast_empty(&ast) {
+                    let error = err_or_message(parser, &ast, "Invalid function argument");
+                    parser.set_index(index_backup);
+                    return error;
+                }
+
+                arguments.push(Rc::new(ast.unwrap()));
+
+                if parser.match_operator(&[')']).is_some() {
+                    break;
+                }
+
+                if parser.match_operator(&[',', ';']).is_none() {
+                    let span = parser.span_from(index_backup);
+                    parser.set_index(index_backup);
+                    return Err(SmartCalcError::syntax_error("Parentheses not closed", span));
+                }
+            }
+        }
+
+        Ok(SmartCalcAstType::Call { name, arguments, span: parser.span_from(index_backup) })
     }
 
     pub fn parse_parenthesis(parser: &mut SyntaxParser) -> AstResult {
@@ -73,13 +107,15 @@ impl PrimativeParser {
             
             let ast = AddSubtractParser::parse(parser);
             if is_ast_empty(&ast) {
+                let error = err_or_message(parser, &ast, "Invalid expression");
                 parser.set_index(index_backup);
-                return err_or_message(&ast, "Invalid expression");
+                return error;
             }
 
             if parser.match_operator(&[')']).is_none() {
+                let span = parser.span_from(index_backup);
                 parser.set_index(index_backup);
-                return Err(("Parentheses not closed", 0, 0));
+                return Err(SmartCalcError::syntax_error("Parentheses not closed", span));
             }
 
             return Ok(ast.unwrap());
@@ -92,6 +128,6 @@ impl PrimativeParser {
 
 impl SyntaxParserTrait for PrimativeParser {
     fn parse(parser: &mut SyntaxParser) -> AstResult {
-        map_parser(parser, &[Self::parse_parenthesis, Self::parse_basic_primatives])
+        map_parser(parser, &[Self::parse_function_call, Self::parse_parenthesis, Self::parse_basic_primatives])
     }
 }
\ No newline at end of file
@@ -5,6 +5,7 @@
  */
 
 use crate::types::*;
+use crate::error::SmartCalcError;
 use crate::syntax::{SyntaxParser};
 use crate::syntax::ParseType;
 
@@ -28,10 +29,10 @@ pub fn is_ast_empty(ast: &AstResult) -> bool {
     }
 }
 
-pub fn err_or_message(ast: &AstResult, message: &'static str) -> AstResult {
+pub fn err_or_message(parser: &SyntaxParser, ast: &AstResult, message: &'static str) -> AstResult {
     match &ast {
-        Ok(SmartCalcAstType::None) => Err((message, 0, 0,)),
+        Ok(SmartCalcAstType::None) => Err(SmartCalcError::syntax_error(message, parser.current_span())),
         Ok(_) => Ok(SmartCalcAstType::None),
-        Err((m, l, c)) => Err((m, *l, *c))
+        Err(error) => Err(error.clone())
     }
 }
@@ -13,6 +13,7 @@ use crate::compiler::time::TimeItem;
 use crate::config::SmartCalcConfig;
 use crate::compiler::money::MoneyItem;
 use crate::types::{SmartCalcAstType, TimeOffset};
+use crate::{SmartCalcError, Span};
 use chrono::{Duration, NaiveDate, Utc};
 use chrono::{Datelike};
 use alloc::string::ToString;
@@ -285,7 +286,7 @@ fn execute_13() {
     match results.lines[0].as_ref().unwrap().result.as_ref().unwrap().ast.deref() {
         SmartCalcAstType::Item(item) => match item.as_any().downcast_ref::<MoneyItem>() {
             Some(item) => {
-                assert_eq!(item.get_price(), 350.0);
+                assert_eq!(item.get_price(), number!(350.0));
                 assert_eq!(item.get_currency(), config.get_currency("usd".to_string()).unwrap());
             },
             _ => assert!(false)
@@ -588,10 +589,10 @@ fn execute_33() {
     assert_eq!(results.lines.len(), 1);
     match results.lines[0].as_ref().unwrap().result.as_ref().unwrap().ast.deref() {
         SmartCalcAstType::Item(item) => {
-            assert_eq!(item.get_underlying_number(), 1048.0);
+            assert_eq!(item.get_underlying_number(), 1048.576);
             match item.as_any().downcast_ref::<DynamicTypeItem>() {
                 Some(memory_item) => {
-                    assert_eq!(memory_item.get_number(), 1048.0);
+                    assert_eq!(memory_item.get_number(), 1048.576);
 
                     let type_detail = memory_item.get_type();
                     assert_eq!(&type_detail.group_name[..], "memory");
@@ -685,6 +686,11 @@ fn execute_38() {
 }
 
 macro_rules! evaluate_line {
+    ($calc:ident, $input:literal => Err($error:expr)) => {
+        let res = $calc.execute("en".to_string(), $input.to_string());
+        assert_eq!(res.lines.len(), 1);
+        assert_eq!(res.lines[0].as_ref().unwrap().result.as_ref().err(), Some(&$error));
+    };
     ($calc:ident, $input:literal => Err) => {
         let res = $calc.execute("en".to_string(), $input.to_string());
         assert_eq!(res.lines.len(), 1);
@@ -751,3 +757,594 @@ fn execute_session() {
     session.set_language("en".to_string());
     evaluate_line!(calc with session, r"foo + bar" => Err);
 }
+
+#[test]
+fn execute_operators() {
+    let calc = SmartCalc::default();
+
+    evaluate_line!(calc, r"10 % 3" => r"1");
+    evaluate_line!(calc, r"2 ^ 3" => r"8");
+    evaluate_line!(calc, r"2 ** 3" => r"8");
+    evaluate_line!(calc, r"2 ^ 3 ^ 2" => r"512");
+    evaluate_line!(calc, r"2 * 3 ^ 2" => r"18");
+    evaluate_line!(calc, r"(1 + 1) ^ 3" => r"8");
+    evaluate_line!(calc, r"7 // 2" => r"3");
+    evaluate_line!(calc, r"-7 // 2" => r"-4");
+    evaluate_line!(calc, r"-7 % 3" => r"2");
+    evaluate_line!(calc, r"7 % -3" => r"-2");
+    evaluate_line!(calc, r"-7 % -3" => r"-1");
+    evaluate_line!(calc, r"7 // -2" => r"-4");
+    evaluate_line!(calc, r"-7 // -2" => r"3");
+    evaluate_line!(calc, r"-7,5 % 2" => r"0,50");
+    evaluate_line!(calc, r"-10 usd % 3" => r"$2,00");
+    evaluate_line!(calc, r"-10 km % 3" => r"2 km");
+    evaluate_line!(calc, r"$10 % 3" => r"$1,00");
+    evaluate_line!(calc, r"$10 // $3" => r"3");
+    evaluate_line!(calc, r"10 km % 3" => r"1 km");
+    evaluate_line!(calc, r"10 % 3 * 2" => r"2");
+    evaluate_line!(calc, r"7 - 10 % 4 * 2" => r"3");
+    evaluate_line!(calc, r"-2 ^ 2" => r"-4");
+    evaluate_line!(calc, r"(-2) ^ 2" => r"4");
+    evaluate_line!(calc, r"2 ^ -1" => r"0,50");
+    evaluate_line!(calc, r"-(2 + 3)" => r"-5");
+    evaluate_line!(calc, r"10 km ^ 2" => r"100 km^2");
+    evaluate_line!(calc, r"(-8) ^ (1 / 3)" => Err(SmartCalcError::InvalidArgument { message: "Power result is not a finite real number".to_string(), span: Span::new(0, 14) }));
+    evaluate_line!(calc, r"10 ^ 400" => Err);
+    evaluate_line!(calc, r"$10 ^ 2" => Err(SmartCalcError::IncompatibleTypes { left: "MONEY".to_string(), right: "NUMBER".to_string(), span: Span::new(0, 7) }));
+}
+
+#[test]
+fn execute_functions() {
+    let calc = SmartCalc::default();
+
+    evaluate_line!(calc, r"sqrt(16)" => r"4");
+    evaluate_line!(calc, r"2 * sqrt(16) + 1" => r"9");
+    evaluate_line!(calc, r"round(12,345, 2)" => r"12,35");
+    evaluate_line!(calc, r"round($12,345)" => r"$12,00");
+    evaluate_line!(calc, r"round($12,345, 2)" => r"$12,35");
+    evaluate_line!(calc, r"max(3 days, 1 week)" => r"1 week");
+    evaluate_line!(calc, r"min(3 days, 1 week)" => r"3 days");
+    evaluate_line!(calc, r"max(3, 7, 5)" => r"7");
+    evaluate_line!(calc, r"abs(-5)" => r"5");
+    evaluate_line!(calc, r"log(100)" => r"2");
+    evaluate_line!(calc, r"log(8, 2)" => r"3");
+    evaluate_line!(calc, r"round(10 km)" => r"10 km");
+    evaluate_line!(calc, r"sqrt(-1)" => Err);
+    evaluate_line!(calc, r"max(1, $5)" => Err);
+    evaluate_line!(calc, r"round()" => Err);
+
+    /* Only rounding, the sign, min and max keep the unit, roots take the root of the unit */
+    evaluate_line!(calc, r"sqrt(16 m^2)" => r"4 m");
+    evaluate_line!(calc, r"cbrt(27 m^3)" => r"3 m");
+    evaluate_line!(calc, r"sqrt(16 m^2/s^2)" => r"4 m/s");
+    evaluate_line!(calc, r"sqrt(1 hectare)" => r"100 m");
+    evaluate_line!(calc, r"sqrt(5 km)" => Err(SmartCalcError::InvalidArgument { message: "DYNAMIC_TYPE is not supported by function".to_string(), span: Span::new(0, 10) }));
+    evaluate_line!(calc, r"sqrt(4 days)" => Err(SmartCalcError::InvalidArgument { message: "DURATION is not supported by function".to_string(), span: Span::new(0, 12) }));
+    evaluate_line!(calc, r"sqrt(20 °C)" => Err);
+    evaluate_line!(calc, r"exp($2)" => Err(SmartCalcError::InvalidArgument { message: "MONEY is not supported by function".to_string(), span: Span::new(0, 7) }));
+    evaluate_line!(calc, r"log(1 gb)" => Err(SmartCalcError::InvalidArgument { message: "DYNAMIC_TYPE is not supported by function".to_string(), span: Span::new(0, 9) }));
+    evaluate_line!(calc, r"sin(90 days)" => Err(SmartCalcError::InvalidArgument { message: "DURATION is not supported by function".to_string(), span: Span::new(0, 12) }));
+    evaluate_line!(calc, r"abs(-5 km)" => r"5 km");
+
+    /* "," is the decimal separator, arguments are separated by ";" or by "," and a space */
+    evaluate_line!(calc, r"max(3;5)" => r"5");
+    evaluate_line!(calc, r"max(3,5)" => r"3,50");
+    evaluate_line!(calc, r"round(12,345; 2)" => r"12,35");
+    evaluate_line!(calc, r"max(abs(-7); 3)" => r"7");
+
+    let mut session = Session::new();
+    session.set_language("en".to_string());
+    session.set_locale(crate::Locale::new(".", ","));
+    session.set_text("max(3;5)\nmax(3.5, 2)".to_string());
+    let results = calc.execute_session(&session);
+    let outputs = results.lines.iter().map(|line| line.as_ref().unwrap().result.as_ref().unwrap().output.to_string()).collect::<alloc::vec::Vec<_>>();
+    assert_eq!(outputs, ["5", "3.50"]);
+}
+
+#[test]
+fn execute_errors() {
+    let calc = SmartCalc::default();
+
+    evaluate_line!(calc, r"1 + 12:00" => Err(SmartCalcError::IncompatibleTypes { left: "NUMBER".to_string(), right: "TIME".to_string(), span: Span::new(0, 9) }));
+    evaluate_line!(calc, r"1 + (2 * 12:00)" => Err(SmartCalcError::IncompatibleTypes { left: "NUMBER".to_string(), right: "TIME".to_string(), span: Span::new(5, 14) }));
+    evaluate_line!(calc, r"10 + (2 * 3" => Err(SmartCalcError::SyntaxError { message: "Parentheses not closed".to_string(), span: Span::new(5, 11) }));
+    evaluate_line!(calc, r"1 + abs(1, 2)" => Err(SmartCalcError::InvalidArgument { message: "Function argument count not valid".to_string(), span: Span::new(4, 13) }));
+}
+
+#[test]
+fn execute_division_by_zero() {
+    let mut calc = SmartCalc::default();
+
+    evaluate_line!(calc, r"10 / 0" => Err(SmartCalcError::DivisionByZero { span: Span::new(0, 6) }));
+    evaluate_line!(calc, r"1 + 10 // 0" => Err(SmartCalcError::DivisionByZero { span: Span::new(4, 11) }));
+    evaluate_line!(calc, r"$5 / 0" => Err(SmartCalcError::DivisionByZero { span: Span::new(0, 6) }));
+    evaluate_line!(calc, r"10% / 0%" => Err(SmartCalcError::DivisionByZero { span: Span::new(0, 8) }));
+    evaluate_line!(calc, r"2 hours / 0" => Err(SmartCalcError::DivisionByZero { span: Span::new(0, 11) }));
+    evaluate_line!(calc, r"10 km % 0" => Err(SmartCalcError::DivisionByZero { span: Span::new(0, 9) }));
+    evaluate_line!(calc, r"2 hours / 2" => r"1 hour");
+    evaluate_line!(calc, r"3 hours / 1 hour" => r"3");
+
+    calc.set_division_by_zero_as_zero(true);
+    evaluate_line!(calc, r"10 / 0" => r"0");
+    evaluate_line!(calc, r"$5 / 0" => r"$0,00");
+}
+
+macro_rules! evaluate_lines {
+    ($calc:ident, $input:literal => [$($output:expr),*]) => {
+        let res = $calc.execute("en".to_string(), $input.to_string());
+        let outputs = res.lines.iter().map(|line| match line {
+            Some(line) => match &line.result {
+                Ok(result) => Some(result.output.to_string()),
+                Err(_) => None
+            },
+            None => None
+        }).collect::<alloc::vec::Vec<_>>();
+        let expected: alloc::vec::Vec<Option<&str>> = alloc::vec![$($output),*];
+        assert_eq!(outputs, expected.iter().map(|item| item.map(|item| item.to_string())).collect::<alloc::vec::Vec<_>>());
+    };
+}
+
+#[test]
+fn execute_line_references() {
+    let calc = SmartCalc::default();
+
+    evaluate_lines!(calc, "10\n20\n30\nsum\nprev * 2" => [Some("10"), Some("20"), Some("30"), Some("60"), Some("120")]);
+    evaluate_lines!(calc, "10\n20\naverage\ntotal" => [Some("10"), Some("20"), Some("15"), Some("30")]);
+    evaluate_lines!(calc, "Rent\n$10\n$20\ntotal\n\n5\nline2 + line 6" => [None, Some("$10,00"), Some("$20,00"), Some("$30,00"), None, Some("5"), Some("$15,00")]);
+    evaluate_lines!(calc, "3 hours\n30 minutes\nsum" => [Some("3 hours"), Some("30 minutes"), Some("3 hours 30 minutes")]);
+    evaluate_lines!(calc, "5\n5 sum 3\nx = sum" => [Some("5"), Some("8"), Some("13")]);
+    evaluate_lines!(calc, "prev\nline 5\n1\nline3\nline5" => [None, None, Some("1"), Some("1"), None]);
+
+    /* References without a value fail the line instead of being read as text */
+    let res = calc.execute("en", "1 / 0\nprev * 2\nline100\n\nsum + 1\n5\n2 * line 9");
+    let errors = res.lines.iter().map(|line| line.as_ref().and_then(|line| line.result.as_ref().err()).cloned()).collect::<alloc::vec::Vec<_>>();
+    assert_eq!(errors[1], Some(SmartCalcError::UnknownLineReference { reference: "prev".to_string(), span: Span::new(0, 4) }));
+    assert_eq!(errors[2], Some(SmartCalcError::UnknownLineReference { reference: "line100".to_string(), span: Span::new(0, 7) }));
+    assert_eq!(errors[4], Some(SmartCalcError::UnknownLineReference { reference: "sum".to_string(), span: Span::new(0, 3) }));
+    assert_eq!(errors[6], Some(SmartCalcError::UnknownLineReference { reference: "line 9".to_string(), span: Span::new(4, 10) }));
+}
+
+#[test]
+fn execute_aggregate_lines() {
+    let calc = SmartCalc::default();
+
+    evaluate_lines!(calc, "$10\n10 EUR\ntotal\naverage" => [Some("$10,00"), Some("10,00 €"), Some("$21,98"), Some("$10,99")]);
+
+    for text in ["$10\n2 hours\nsum", "10\n2 hours\nsum"] {
+        let res = calc.execute("en", text);
+        match res.lines[2].as_ref().unwrap().result.as_ref() {
+            Err(SmartCalcError::IncompatibleTypes { span, .. }) => assert_eq!(*span, Span::new(0, 3)),
+            result => panic!("{} gave {:?}", text, result.map(|result| result.output.to_string()))
+        }
+    }
+}
+
+fn outputs(result: &crate::smartcalc::ExecuteResult) -> alloc::vec::Vec<alloc::string::String> {
+    result.lines.iter().map(|line| match line {
+        Some(line) => match &line.result {
+            Ok(result) => result.output.to_string(),
+            Err(error) => error.to_string()
+        },
+        None => "".to_string()
+    }).collect()
+}
+
+#[test]
+fn execute_incremental() {
+    let calc = SmartCalc::default();
+    let mut session = Session::new();
+    session.set_language("en".to_string());
+    session.set_text("a = 10\nb = a * 2\n5\nb + 1\nsum".to_string());
+
+    let result = calc.execute_incremental(&session);
+    assert_eq!(result.changed_lines, alloc::vec![0, 1, 2, 3, 4]);
+    assert_eq!(outputs(&result), alloc::vec!["10", "20", "5", "21", "56"]);
+
+    assert!(session.update_line(0, "a = 20".to_string()));
+    let result = calc.execute_incremental(&session);
+    assert_eq!(result.changed_lines, alloc::vec![0, 1, 3, 4]);
+    assert_eq!(outputs(&result), alloc::vec!["20", "40", "5", "41", "106"]);
+
+    assert!(session.update_line(2, "7".to_string()));
+    let result = calc.execute_incremental(&session);
+    assert_eq!(result.changed_lines, alloc::vec![2, 4]);
+    assert_eq!(outputs(&result), alloc::vec!["20", "40", "7", "41", "108"]);
+
+    assert!(session.update_line(5, "prev * 2".to_string()));
+    let result = calc.execute_incremental(&session);
+    assert_eq!(result.changed_lines, alloc::vec![5]);
+    assert_eq!(outputs(&result), alloc::vec!["20", "40", "7", "41", "108", "216"]);
+
+    let result = calc.execute_incremental(&session);
+    assert!(result.changed_lines.is_empty());
+    assert_eq!(result.lines.len(), 6);
+    assert!(!session.update_line(10, "1".to_string()));
+}
+
+#[test]
+fn execute_incremental_redefinition() {
+    let calc = SmartCalc::default();
+    let mut session = Session::new();
+    session.set_language("en".to_string());
+    session.set_text("a = 1\nc = a\na = 5\nd = a".to_string());
+
+    let result = calc.execute_incremental(&session);
+    assert_eq!(outputs(&result), alloc::vec!["1", "1", "5", "5"]);
+
+    assert!(session.update_line(1, "c = a + 1".to_string()));
+    let result = calc.execute_incremental(&session);
+    assert_eq!(result.changed_lines, alloc::vec![1]);
+    assert_eq!(outputs(&result), alloc::vec!["1", "2", "5", "5"]);
+
+    assert!(session.update_line(2, "a = 7".to_string()));
+    let result = calc.execute_incremental(&session);
+    assert_eq!(result.changed_lines, alloc::vec![2, 3]);
+    assert_eq!(outputs(&result), alloc::vec!["1", "2", "7", "7"]);
+
+    assert!(session.update_line(3, "a + c".to_string()));
+    let result = calc.execute_incremental(&session);
+    assert_eq!(result.changed_lines, alloc::vec![3]);
+    assert_eq!(outputs(&result), alloc::vec!["1", "2", "7", "9"]);
+}
+
+#[test]
+fn execute_incremental_matches_full_run() {
+    let calc = SmartCalc::default();
+    let mut session = Session::new();
+    session.set_language("en".to_string());
+    let mut lines = alloc::vec!["x = 5", "x + 1", "y = x * 2", "y + tax"];
+    session.set_text(lines.join("\n"));
+    assert!(session.set_variable(&calc, "tax", crate::VariableValue::Number(3.0)).is_ok());
+    calc.execute_incremental(&session);
+
+    /* Defining line deleted, renamed, restored, redefined later and the host variable defined by a line */
+    let edits = [(0, "10"), (0, "z = 5"), (0, "x = 7"), (3, "x = 1"), (1, "x"), (2, "tax = 2"), (2, "10"), (0, "")];
+    for (index, text) in edits.iter() {
+        assert!(session.update_line(*index, text.to_string()));
+        lines[*index] = text;
+        let incremental = outputs(&calc.execute_incremental(&session));
+
+        let mut full_session = Session::new();
+        full_session.set_language("en".to_string());
+        full_session.set_text(lines.join("\n"));
+        assert!(full_session.set_variable(&calc, "tax", crate::VariableValue::Number(3.0)).is_ok());
+        assert_eq!(incremental, outputs(&calc.execute_session(&full_session)), "after '{}' on line {}", text, index);
+    }
+
+}
+
+#[test]
+fn execute_iana_timezones() {
+    let mut calculater = SmartCalc::default();
+    evaluate_line!(calculater, "1625140800 to Europe/Berlin" => "1 Jul 2021 14:00:00 Europe/Berlin");
+    evaluate_line!(calculater, "1609502400 to Europe/Berlin" => "1 Jan 2021 13:00:00 Europe/Berlin");
+    evaluate_line!(calculater, "1 jul 2021 12:00 to new york" => "1 Jul 2021 08:00:00 America/New_York");
+    evaluate_line!(calculater, "12:00 UTC to Asia/Kolkata" => "17:30:00 Asia/Kolkata");
+    evaluate_line!(calculater, "10:00 in tokyo" => "19:00:00 Asia/Tokyo");
+    evaluate_line!(calculater, "9:00 Asia/Tokyo" => "09:00:00 Asia/Tokyo");
+
+    assert!(calculater.set_timezone("Europe/Berlin".to_string()).is_ok());
+    evaluate_line!(calculater, "1625140800 date" => "1 Jul 2021 14:00:00 Europe/Berlin");
+    evaluate_line!(calculater, "1609502400 date" => "1 Jan 2021 13:00:00 Europe/Berlin");
+
+    assert!(calculater.set_timezone("tokyo".to_string()).is_ok());
+    assert_eq!(calculater.get_time_offset().name, "Asia/Tokyo");
+    assert_eq!(calculater.get_time_offset().offset, 540);
+    assert!(calculater.set_timezone("Atlantis".to_string()).is_err());
+}
+
+#[test]
+fn execute_iana_timezone_words() {
+    let calculater = SmartCalc::default();
+    evaluate_line!(calculater, "10 km to north" => "10 km");
+    evaluate_line!(calculater, "10 to Paris" => "10");
+    evaluate_line!(calculater, "1 jul 2021 12:00 Europe/Berlin" => "1 Jul 2021 12:00:00 Europe/Berlin");
+    evaluate_line!(calculater, "1 jul 2021 12:00 Europe/Berlin to UTC" => "1 Jul 2021 10:00:00 UTC");
+    evaluate_line!(calculater, "1 jan 2021 12:00 Europe/Berlin to UTC" => "1 Jan 2021 11:00:00 UTC");
+    evaluate_line!(calculater, "12:00 UTC to US/Pacific" => Err);
+    evaluate_line!(calculater, "3pm istanbul to tokyo" => "21:00:00 Asia/Tokyo");
+    evaluate_line!(calculater, "1 jul 2021 3pm istanbul to tokyo" => "1 Jul 2021 21:00:00 Asia/Tokyo");
+    evaluate_line!(calculater, "1 jul 2021 3pm berlin to new york" => "1 Jul 2021 09:00:00 America/New_York");
+    evaluate_line!(calculater, "1 jul 2021 3pm new york to berlin" => "1 Jul 2021 21:00:00 Europe/Berlin");
+    evaluate_line!(calculater, "28 march 2021 2:30 Europe/Berlin" => Err);
+    evaluate_line!(calculater, "28 march 2021 3:30 Europe/Berlin" => "28 Mar 2021 03:30:00 Europe/Berlin");
+}
+
+#[test]
+fn execute_dated_currency_rates() {
+    let mut calculater = SmartCalc::default();
+    assert!(calculater.update_currency_at("eur", chrono::NaiveDate::from_ymd_opt(2021, 3, 1).unwrap(), 0.8));
+    assert!(!calculater.update_currency_at("xyz", chrono::NaiveDate::from_ymd_opt(2021, 3, 1).unwrap(), 0.8));
+    assert_eq!(calculater.load_currency_rates_csv("date,currency,rate\n2021-03-10,EUR,0.5\n\n2021-03-20, eur, 0.25\n"), Ok(2));
+    assert_eq!(calculater.load_currency_rates_json(r#"{"2021-03-15": {"TRY": 7.5, "EUR": 0.4}}"#), Ok(2));
+
+    evaluate_line!(calculater, "100 eur to usd on 12 march 2021" => "$200,00");
+    evaluate_line!(calculater, "100 eur to usd on 15 march 2021" => "$250,00");
+    evaluate_line!(calculater, "100 eur usd on 1 april 2021" => "$400,00");
+    evaluate_line!(calculater, "100 eur to usd on 5 march 2021" => "$125,00");
+    evaluate_line!(calculater, "10 usd to try on 16 march 2021" => "₺75,00");
+    evaluate_line!(calculater, "100 eur to usd on 1 january 2021" => Err(SmartCalcError::InvalidArgument { message: "No rate for EUR on 2021-01-01".to_string(), span: Span::new(0, 32) }));
+
+    assert!(calculater.load_currency_rates_csv("2021-03-10,EUR,0.5\n2021-03-11,XYZ,1").is_err());
+    assert!(calculater.load_currency_rates_csv("2021-03-10,EUR").is_err());
+    assert!(calculater.load_currency_rates_json(r#"{"12.03.2021": {"EUR": 0.5}}"#).is_err());
+    evaluate_line!(calculater, "100 eur to usd on 10 march 2021" => "$200,00");
+
+    for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        assert!(!calculater.update_currency_at("eur", chrono::NaiveDate::from_ymd_opt(2021, 3, 10).unwrap(), rate));
+    }
+    evaluate_line!(calculater, "100 eur to usd on 10 march 2021" => "$200,00");
+
+    /* Every language has the dated conversion */
+    for (language, text) in [("tr", "100 eur usd 12 mart 2021 tarihinde"), ("de", "100 eur in usd am 12 märz 2021"), ("fr", "100 eur en usd le 12 mars 2021"),
+        ("es", "100 eur en usd el 12 marzo 2021"), ("bg", "100 eur в usd на 12 март 2021")] {
+        let res = calculater.execute(language, text);
+        let result = res.lines[0].as_ref().unwrap().result.as_ref();
+        assert!(result.is_ok_and(|result| result.output.contains("200")), "{} gave {:?}", text, result.map(|result| result.output.to_string()));
+    }
+}
+
+#[test]
+fn execute_rate_provider() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use crate::{CurrencyRate, FileRateProvider, JsonRateProvider, RateProvider, Shared};
+
+    struct CountingProvider(AtomicUsize, JsonRateProvider);
+    impl RateProvider for CountingProvider {
+        fn get_rate(&self, currency: &str) -> Option<CurrencyRate> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            self.1.get_rate(currency)
+        }
+    }
+
+    let as_of = chrono::NaiveDate::from_ymd_opt(2022, 3, 1).unwrap().and_hms_opt(12, 0, 0).unwrap();
+    let snapshot = r#"{"as_of": "2022-03-01T12:00:00", "rates": {"USD": 1.0, "EUR": 0.5}}"#;
+    let provider = Shared::new(CountingProvider(AtomicUsize::new(0), JsonRateProvider::from_json(snapshot).unwrap()));
+
+    let mut calculater = SmartCalc::default();
+    calculater.set_rate_provider(provider.clone());
+    assert_eq!(provider.0.load(Ordering::Relaxed), 0);
+
+    let results = calculater.execute("en", "10 usd to eur\n$10 + 10 eur\n10 eur to try\n1 + 2");
+    let outputs = results.lines.iter().map(|line| line.as_ref().unwrap().result.as_ref().unwrap()).collect::<alloc::vec::Vec<_>>();
+    assert_eq!(outputs[0].output, "5,00 €");
+    assert_eq!(outputs[0].rates_as_of, Some(as_of));
+    assert_eq!(outputs[1].output, "$30,00");
+    assert_eq!(outputs[1].rates_as_of, Some(as_of));
+    assert_eq!(outputs[2].rates_as_of, Some(as_of));
+    assert_eq!(outputs[3].rates_as_of, None);
+
+    /* USD, EUR and TRY are asked once, TRY falls back to the built-in rate */
+    assert_eq!(provider.0.load(Ordering::Relaxed), 3);
+    calculater.clear_rate_cache();
+    evaluate_line!(calculater, "10 usd to eur" => "5,00 €");
+    assert_eq!(provider.0.load(Ordering::Relaxed), 5);
+
+    assert!(calculater.update_currency("eur", 0.25));
+    evaluate_line!(calculater, "10 usd to eur" => "2,50 €");
+
+    let path = std::env::temp_dir().join("smartcalc_rate_provider_test.json");
+    std::fs::write(&path, snapshot).unwrap();
+    calculater.set_rate_provider(Shared::new(FileRateProvider::new(path.clone())));
+    evaluate_line!(calculater, "10 usd to eur" => "5,00 €");
+    std::fs::remove_file(&path).unwrap();
+
+    calculater.set_rate_provider(Shared::new(FileRateProvider::new(path)));
+    let results = calculater.execute("en", "10 usd to usd");
+    assert_eq!(results.lines[0].as_ref().unwrap().result.as_ref().unwrap().rates_as_of, None);
+
+    assert!(JsonRateProvider::from_json(r#"{"as_of": "yesterday", "rates": {}}"#).is_err());
+}
+
+#[test]
+fn execute_units() {
+    let calculater = SmartCalc::default();
+    evaluate_line!(calculater, "60 km/h * 2 h" => "120 km");
+    evaluate_line!(calculater, "5 kg * 9,81 m/s^2 in N" => "49,05 N");
+    evaluate_line!(calculater, "100 km / 2 hours" => "50 km/h");
+    evaluate_line!(calculater, "2 m * 3 m" => "6 m^2");
+    evaluate_line!(calculater, "60 km/h in m/s" => "16,67 m/s");
+    evaluate_line!(calculater, "20 m/s in km/h" => "72 km/h");
+    evaluate_line!(calculater, "500 W * 3 hours in kWh" => "1,50 kWh");
+    evaluate_line!(calculater, "5 kWh in J" => "18.000.000 J");
+    evaluate_line!(calculater, "1 mile in km" => "1,61 km");
+    evaluate_line!(calculater, "1 N in kg" => Err);
+    evaluate_line!(calculater, "1 N + 1 J" => Err);
+
+    /* Symbols in another case, prefixes keep their case */
+    evaluate_line!(calculater, "1 kwh" => "1 kWh");
+    evaluate_line!(calculater, "1 kj to j" => "1.000 J");
+    evaluate_line!(calculater, "2 l to ml" => "2.000 mL");
+    evaluate_line!(calculater, "2 MW in kw" => "2.000 kW");
+    evaluate_line!(calculater, "1 gb to MB" => "1.000 MB");
+    evaluate_line!(calculater, "1 cup to ml" => Err);
+}
+
+#[test]
+fn execute_temperatures() {
+    let calculater = SmartCalc::default();
+    evaluate_line!(calculater, "20 °C to F" => "68 °F");
+    evaluate_line!(calculater, "20 degC in fahrenheit" => "68 °F");
+    evaluate_line!(calculater, "-40 °F in °C" => "-40 °C");
+    evaluate_line!(calculater, "100 celsius in kelvin" => "373,15 K");
+    evaluate_line!(calculater, "20 °C - 15 °C" => "5 ΔK");
+    evaluate_line!(calculater, "68 °F - 50 °F" => "10 ΔK");
+    evaluate_line!(calculater, "(20 °C - 15 °C) to F" => "9 Δ°F");
+    evaluate_line!(calculater, "(20 °C - 15 °C) to °C" => "5 Δ°C");
+    evaluate_line!(calculater, "20 °C + (68 °F - 50 °F)" => "30 °C");
+    evaluate_line!(calculater, "20 °C + 5" => "25 °C");
+    evaluate_line!(calculater, "10 °C - 5 K" => "5 °C");
+    evaluate_line!(calculater, "5 °C/s * 2 s" => "10 ΔK");
+    evaluate_line!(calculater, "20 °C + 5 °C" => "25 °C");
+    evaluate_line!(calculater, "20 degC + 5 degC" => "25 °C");
+    evaluate_line!(calculater, "20 °C + 9 °F" => "25 °C");
+    evaluate_line!(calculater, "20 °C * 2" => Err);
+
+    /* Nothing is colder than 0 K */
+    let below_absolute_zero = |start, end| SmartCalcError::InvalidArgument { message: "Temperature is below absolute zero".to_string(), span: Span::new(start, end) };
+    evaluate_line!(calculater, "-273,15 °C" => "-273,15 °C");
+    evaluate_line!(calculater, "-300 °C" => Err(below_absolute_zero(0, 7)));
+    evaluate_line!(calculater, "x = -500 °F to K" => Err(below_absolute_zero(4, 11)));
+    evaluate_line!(calculater, "-200 °C - 100 K" => Err(below_absolute_zero(0, 15)));
+    evaluate_line!(calculater, "20 °C - 400 °C" => "-380 ΔK");
+}
+
+#[test]
+fn execute_storage_units() {
+    let calculater = SmartCalc::default();
+    evaluate_line!(calculater, "1 gb to mb" => "1.000 MB");
+    evaluate_line!(calculater, "1 GiB in MB" => "1.073,74 MB");
+    evaluate_line!(calculater, "10 GB to GiB" => "9,31 GiB");
+    evaluate_line!(calculater, "1 kibibyte in byte" => "1.024 B");
+    evaluate_line!(calculater, "1 KiB in bit" => "8.192 bit");
+    evaluate_line!(calculater, "100 Mbit/s * 2 hours in GB" => "90 GB");
+    evaluate_line!(calculater, "100 Mbps * 10 min in MB" => "7.500 MB");
+    evaluate_line!(calculater, "1 GB / 2 min" => "0,50 GB/min");
+    evaluate_line!(calculater, "4 GiB / 30 MB/s" => "2 minutes 23 seconds");
+    evaluate_line!(calculater, "500 MB / 25 MB/s" => "20 seconds");
+    evaluate_line!(calculater, "1 KiB in m" => Err);
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn execute_shared_between_threads() {
+    use alloc::sync::Arc;
+    use crate::{Session, SmartCalcConfig};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SmartCalc>();
+    assert_send_sync::<SmartCalcConfig>();
+
+    let calculater = Arc::new(SmartCalc::default());
+    let workers = (0..4).map(|index| {
+        let calculater = calculater.clone();
+        std::thread::spawn(move || {
+            let mut session = Session::new();
+            session.set_language("en".to_string());
+            session.set_text(alloc::format!("price = {} usd\nprice * 2\n10 usd to eur", (index + 1) * 10));
+
+            let results = calculater.execute_session(&session);
+            results.lines.iter().map(|line| line.as_ref().unwrap().result.as_ref().unwrap().output.clone()).collect::<alloc::vec::Vec<_>>()
+        })
+    }).collect::<alloc::vec::Vec<_>>();
+
+    for (index, worker) in workers.into_iter().enumerate() {
+        let outputs = worker.join().unwrap();
+        assert_eq!(outputs[1], alloc::format!("${},00", (index + 1) * 20));
+        assert_eq!(outputs[2], calculater.execute("en", "10 usd to eur").lines[0].as_ref().unwrap().result.as_ref().unwrap().output);
+    }
+}
+
+#[test]
+fn execute_line_value() {
+    use crate::{Dimension, Value};
+
+    let calculater = SmartCalc::default();
+    let results = calculater.execute("en", "12 + 30\n$1.250,50\n%15\n1 january 2020\n12:30 EST\n3 days\n10 km\nnumber\n\n100 km / 2 h");
+    let values = results.lines.iter().map(|line| line.as_ref().and_then(|line| line.result.as_ref().ok()).and_then(|result| result.value())).collect::<alloc::vec::Vec<_>>();
+
+    assert_eq!(values[0], Some(Value::Number(number!(42.0))));
+    assert_eq!(values[1], Some(Value::Money { amount: number!(1250.5), currency: "USD".to_string() }));
+    assert_eq!(values[2], Some(Value::Percent(number!(15.0))));
+    assert_eq!(values[3], Some(Value::Date(NaiveDate::from_ymd_opt(2020, 1, 1).unwrap())));
+    assert_eq!(values[4], Some(Value::Time { time: chrono::NaiveTime::from_hms_opt(12, 30, 0).unwrap(), timezone: TimeOffset { name: "EST".to_string(), offset: -300 } }));
+    assert_eq!(values[5], Some(Value::Duration(Duration::days(3))));
+    assert_eq!(values[6], Some(Value::Quantity { value: number!(10.0), unit: "km".to_string(), dimension: Some(Dimension::base("length")) }));
+    assert_eq!(values[7], None);
+    assert_eq!(values[8], None);
+    assert_eq!(values[9], Some(Value::Quantity { value: number!(50.0), unit: "km/h".to_string(), dimension: Some(Dimension::base("length").combine(&Dimension::base("time"), -1)) }));
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn execute_decimal_values() {
+    use crate::Value;
+
+    let calculater = SmartCalc::default();
+    let results = calculater.execute("en", "0,1 + 0,2\n$0,10 + $0,20\n$0,10 + $0,20 + $0,30 - $0,60\n%10 of $0,30\n$0,30 add %10\n$0,33 off %10");
+    let values = results.lines.iter().map(|line| line.as_ref().and_then(|line| line.result.as_ref().ok()).and_then(|result| result.value())).collect::<alloc::vec::Vec<_>>();
+
+    assert_eq!(values[0], Some(Value::Number(number!(0.3))));
+    assert_eq!(values[1], Some(Value::Money { amount: number!(0.3), currency: "USD".to_string() }));
+    assert_eq!(values[2], Some(Value::Money { amount: number!(0.0), currency: "USD".to_string() }));
+    assert_eq!(values[3], Some(Value::Money { amount: number!(0.03), currency: "USD".to_string() }));
+    assert_eq!(values[4], Some(Value::Money { amount: number!(0.33), currency: "USD".to_string() }));
+    assert_eq!(values[5], Some(Value::Money { amount: number!(0.297), currency: "USD".to_string() }));
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn execute_decimal_overflow() {
+    use crate::Value;
+
+    let calculater = SmartCalc::default();
+    let results = calculater.execute("en", "99999999999999999999 * 99999999999999999999\n79228162514264337593543950335 + 1\n10 ^ 28 * 10\n$99999999999999999999 * 99999999999999999999\n79228162514264337593543950335 / 0,1\nround(79228162514264337593543950335; -28)\nexp(100)\n100 eur to usd");
+    let errors = results.lines.iter().map(|line| line.as_ref().and_then(|line| line.result.as_ref().err())).collect::<alloc::vec::Vec<_>>();
+
+    assert_eq!(errors[0], Some(&SmartCalcError::InvalidArgument { message: "Number is out of range".to_string(), span: Span::new(0, 43) }));
+    assert_eq!(errors[1], Some(&SmartCalcError::InvalidArgument { message: "Number is out of range".to_string(), span: Span::new(0, 33) }));
+    assert_eq!(errors[2], Some(&SmartCalcError::InvalidArgument { message: "Number is out of range".to_string(), span: Span::new(0, 12) }));
+    assert_eq!(errors[3], Some(&SmartCalcError::InvalidArgument { message: "Number is out of range".to_string(), span: Span::new(0, 44) }));
+    assert_eq!(errors[4], Some(&SmartCalcError::InvalidArgument { message: "Number is out of range".to_string(), span: Span::new(0, 35) }));
+    assert_eq!(errors[5], Some(&SmartCalcError::InvalidArgument { message: "Number is out of range".to_string(), span: Span::new(0, 41) }));
+    assert_eq!(errors[6], Some(&SmartCalcError::InvalidArgument { message: "Number is out of range".to_string(), span: Span::new(0, 8) }));
+
+    assert!(crate::tools::number_from_f64(1e30).is_err());
+    assert!(crate::tools::number_from_f64(f64::NAN).is_err());
+
+    /* Converted with the decimal rates, not through f64 */
+    let value = results.lines[7].as_ref().unwrap().result.as_ref().unwrap().value();
+    assert_eq!(value, Some(Value::Money { amount: number!(119.83000000219169070004008602), currency: "USD".to_string() }));
+}
+
+#[test]
+fn execute_explain() {
+    use alloc::vec;
+    use crate::ExplainStep;
+
+    let calculater = SmartCalc::default();
+    let mut session = Session::new();
+    session.set_language("en".to_string());
+    session.set_text("price = $100\n(price + 20) times 2\n10 usd to eur".to_string());
+
+    let results = calculater.execute_session(&session);
+    assert!(results.lines[1].as_ref().unwrap().explanation.is_none());
+
+    session.set_explain(true);
+    let results = calculater.execute_session(&session);
+    let explanations = results.lines.iter().map(|line| line.as_ref().unwrap().explanation.clone().unwrap().steps).collect::<alloc::vec::Vec<_>>();
+
+    assert_eq!(explanations[1], vec![
+        ExplainStep::Alias { text: "times".to_string(), replacement: "*".to_string() },
+        ExplainStep::Variable { name: "price".to_string(), value: "$100,00".to_string() },
+        ExplainStep::Binary { operator: '*', left: "$120,00".to_string(), right: "2".to_string(), result: "$240,00".to_string(), steps: vec![
+            ExplainStep::Binary { operator: '+', left: "$100,00".to_string(), right: "20".to_string(), result: "$120,00".to_string(), steps: vec![] }
+        ] }
+    ]);
+
+    match &explanations[2][0] {
+        ExplainStep::Rule { name, fields, .. } => {
+            assert_eq!(name, "convert_money");
+            assert_eq!(fields["money"], "$10,00");
+            assert_eq!(fields["currency"], "eur");
+        },
+        step => panic!("Unexpected step {:?}", step)
+    };
+    assert_eq!(explanations[2][1], ExplainStep::Rate { currency: "USD".to_string(), rate: 1.0 });
+    assert!(matches!(&explanations[2][2], ExplainStep::Rate { currency, .. } if currency == "EUR"));
+
+    /* Values of rule fields are formatted like results */
+    let results = calculater.execute_with_explain("en", "2 hours 30 minutes to minutes");
+    let fields = results.lines[0].as_ref().unwrap().explanation.as_ref().unwrap().steps.iter().find_map(|step| match step {
+        ExplainStep::Rule { fields, .. } if fields.contains_key("source") => Some(fields.clone()),
+        _ => None
+    });
+    assert_eq!(fields.unwrap()["source"], "2 hours 30 minutes");
+
+    let results = calculater.execute_with_explain("en", "$10\n$20\nsum");
+    assert_eq!(results.lines[2].as_ref().unwrap().explanation.as_ref().unwrap().steps, vec![
+        ExplainStep::Aggregate { text: "sum".to_string(), lines: vec![(1, "$10,00".to_string()), (2, "$20,00".to_string())], result: "$30,00".to_string() }
+    ]);
+}
+
+
@@ -47,19 +47,19 @@ fn execute_1() {
     execute(r#"
 1024                            | 1.024
 200 * 10                        | 2.000
-100mb                           | 100MB
-100 mb                          | 100MB
-100 MegaByte                    | 100MB
-100 MegaBytes                   | 100MB
-22250mb - 250,1mb               | 21.999,90MB
-8 gb * 10                       | 80GB
-1024mb                          | 1.024MB
-1024mb - 24 mb                  | 1.000MB
-1024mb - (1024kb * 24)          | 1.000MB
-1024mb + (1024kb * 24)          | 1.048MB
+100mb                           | 100 MB
+100 mb                          | 100 MB
+100 MegaByte                    | 100 MB
+100 MegaBytes                   | 100 MB
+22250mb - 250,1mb               | 21.999,90 MB
+8 gb * 10                       | 80 GB
+1024mb                          | 1.024 MB
+1024mb - 24 mb                  | 1.000 MB
+1024mb - (1024kb * 24)          | 999,42 MB
+1024mb + (1024kb * 24)          | 1.048,58 MB
 1000mb / 10MB                   | 100
-1 gb to mb                      | 1.024MB
-1 gb to byte                    | 1.073.741.824byte
+1 gb to mb                      | 1.000 MB
+1 gb to byte                    | 1.000.000.000 B
 x = 2                           | 2
 h = 2 * 2                       | 4
 10 $                            | $10,00
@@ -71,19 +71,19 @@ fn execute_2() {
     execute(r#"
 1024                            | 1,024
 200 * 10                        | 2,000
-100mb                           | 100MB
-100 mb                          | 100MB
-100 MegaByte                    | 100MB
-100 MegaBytes                   | 100MB
-22250mb - 250.1mb               | 21,999.90MB
-8 gb * 10                       | 80GB
-1024mb                          | 1,024MB
-1024mb - 24 mb                  | 1,000MB
-1024mb - (1024kb * 24)          | 1,000MB
-1024mb + (1024kb * 24)          | 1,048MB
+100mb                           | 100 MB
+100 mb                          | 100 MB
+100 MegaByte                    | 100 MB
+100 MegaBytes                   | 100 MB
+22250mb - 250.1mb               | 21,999.90 MB
+8 gb * 10                       | 80 GB
+1024mb                          | 1,024 MB
+1024mb - 24 mb                  | 1,000 MB
+1024mb - (1024kb * 24)          | 999.42 MB
+1024mb + (1024kb * 24)          | 1,048.58 MB
 1000mb / 10MB                   | 100
-1 gb to mb                      | 1,024MB
-1 gb to byte                    | 1,073,741,824byte
+1 gb to mb                      | 1,000 MB
+1 gb to byte                    | 1,000,000,000 B
 x = 2                           | 2
 h = 2 * 2                       | 4
 10 $                            | $10.00
@@ -95,19 +95,19 @@ fn execute_3() {
     execute(r#"
 1024                            | 1024
 200 * 10                        | 2000
-100mb                           | 100MB
-100 mb                          | 100MB
-100 MegaByte                    | 100MB
-100 MegaBytes                   | 100MB
-22250mb - 250.1mb               | 21999.90MB
-8 gb * 10                       | 80GB
-1024mb                          | 1024MB
-1024mb - 24 mb                  | 1000MB
-1024mb - (1024kb * 24)          | 1000MB
-1024mb + (1024kb * 24)          | 1048MB
+100mb                           | 100 MB
+100 mb                          | 100 MB
+100 MegaByte                    | 100 MB
+100 MegaBytes                   | 100 MB
+22250mb - 250.1mb               | 21999.90 MB
+8 gb * 10                       | 80 GB
+1024mb                          | 1024 MB
+1024mb - 24 mb                  | 1000 MB
+1024mb - (1024kb * 24)          | 999.42 MB
+1024mb + (1024kb * 24)          | 1048.58 MB
 1000mb / 10MB                   | 100
-1 gb to mb                      | 1024MB
-1 gb to byte                    | 1073741824byte
+1 gb to mb                      | 1000 MB
+1 gb to byte                    | 1000000000 B
 x = 2                           | 2
 h = 2 * 2                       | 4
 10 $                            | $10.00
@@ -185,10 +185,10 @@ total month = down payment / saving                                          |
 #[test]
 fn weight_height_tests() {
     execute(r#"
-1 m to mm                | 1,000 Millimeter
-1 yard to inch           |    36 Inch
-100 yard to mile         |     0.06 Mile
-1 Stone to kg            |     6.35 Kilogram
-10 pound to ounce        |   160 Ounce
+1 m to mm                | 1,000 mm
+1 yard to inch           |    36 in
+100 yard to mile         |     0.06 mi
+1 Stone to kg            |     6.35 kg
+10 pound to ounce        |   160 oz
 "#.to_string(), ".".to_string(), ",".to_string(), "CET".to_string());        
 }
@@ -25,7 +25,7 @@ monthly rent / 4 people".to_string();
     match results.lines[0].as_ref().unwrap().result.as_ref().unwrap().ast.deref() {
         SmartCalcAstType::Item(item) => match item.as_any().downcast_ref::<MoneyItem>() {
             Some(item) => {
-                assert_eq!(item.get_price(), 1900.0);
+                assert_eq!(item.get_price(), number!(1900.0));
                 assert_eq!(item.get_currency(), config.get_currency("usd".to_string()).unwrap());
             },
             _ => assert!(false)
@@ -35,7 +35,7 @@ monthly rent / 4 people".to_string();
     match &*results.lines[1].as_ref().unwrap().result.as_ref().unwrap().ast {
         SmartCalcAstType::Item(item) => match item.as_any().downcast_ref::<MoneyItem>() {
             Some(item) => {
-                assert_eq!(item.get_price(), 2150.0);
+                assert_eq!(item.get_price(), number!(2150.0));
                 assert_eq!(item.get_currency(), config.get_currency("usd".to_string()).unwrap());
             },
             _ => assert!(false)
@@ -45,7 +45,7 @@ monthly rent / 4 people".to_string();
     match &*results.lines[2].as_ref().unwrap().result.as_ref().unwrap().ast {
         SmartCalcAstType::Item(item) => match item.as_any().downcast_ref::<MoneyItem>() {
             Some(item) => {
-                assert_eq!(item.get_price(), 537.5);
+                assert_eq!(item.get_price(), number!(537.5));
                 assert_eq!(item.get_currency(), config.get_currency("usd".to_string()).unwrap());
             },
             _ => assert!(false)
@@ -66,7 +66,7 @@ monthly rent / $4".to_string();
     match results.lines[0].as_ref().unwrap().result.as_ref().unwrap().ast.deref() {
         SmartCalcAstType::Item(item) => match item.as_any().downcast_ref::<MoneyItem>() {
             Some(item) => {
-                assert_eq!(item.get_price(), 1900.0);
+                assert_eq!(item.get_price(), number!(1900.0));
                 assert_eq!(item.get_currency(), config.get_currency("usd".to_string()).unwrap());
             },
             _ => assert!(false)
@@ -76,7 +76,7 @@ monthly rent / $4".to_string();
     match &*results.lines[1].as_ref().unwrap().result.as_ref().unwrap().ast {
         SmartCalcAstType::Item(item) => match item.as_any().downcast_ref::<MoneyItem>() {
             Some(item) => {
-                assert_eq!(item.get_price(), 2150.0);
+                assert_eq!(item.get_price(), number!(2150.0));
                 assert_eq!(item.get_currency(), config.get_currency("usd".to_string()).unwrap());
             },
             _ => assert!(false)
@@ -116,3 +116,183 @@ my age = year - 1985".to_string();
         _ => assert!(false)
     };
 }
+
+#[test]
+fn session_variables() {
+    use crate::{Session, SessionVariable, VariableValue, SmartCalcError};
+    use alloc::vec;
+
+    let calculater = SmartCalc::default();
+    let mut session = Session::new();
+    session.set_language("en".to_string());
+
+    assert!(session.set_variable(&calculater, "budget", VariableValue::Money(5000.0, "usd".to_string())).is_ok());
+    assert!(session.set_variable(&calculater, "tax rate", VariableValue::Number(2.0)).is_ok());
+    assert!(session.set_variable(&calculater, "trip", VariableValue::Duration(chrono::Duration::days(3))).is_ok());
+    assert!(session.set_variable(&calculater, "start", VariableValue::Date(chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap())).is_ok());
+    assert!(session.set_variable(&calculater, "tax2", VariableValue::Number(2.0)).is_err());
+    assert_eq!(session.set_variable(&calculater, "price", VariableValue::Money(1.0, "xyz".to_string())), Err(SmartCalcError::UnknownCurrency { currency: "xyz".to_string(), span: Default::default() }));
+
+    session.set_text("budget / tax rate\nstart + trip".to_string());
+    let results = calculater.execute_session(&session);
+    assert_eq!(results.lines[0].as_ref().unwrap().result.as_ref().unwrap().output, "$2.500,00");
+    assert_eq!(results.lines[1].as_ref().unwrap().result.as_ref().unwrap().output, "4 Jan 2020");
+
+    assert_eq!(session.variables(&calculater), vec![
+        SessionVariable { name: "budget".to_string(), type_name: "MONEY".to_string(), value: "$5.000,00".to_string() },
+        SessionVariable { name: "start".to_string(), type_name: "DATE".to_string(), value: "1 Jan 2020".to_string() },
+        SessionVariable { name: "tax rate".to_string(), type_name: "NUMBER".to_string(), value: "2".to_string() },
+        SessionVariable { name: "trip".to_string(), type_name: "DURATION".to_string(), value: "3 days".to_string() }
+    ]);
+
+    assert!(session.set_variable(&calculater, "budget", VariableValue::Number(10.0)).is_ok());
+    let results = calculater.execute_incremental(&session);
+    assert_eq!(results.changed_lines, vec![0]);
+    assert_eq!(results.lines[0].as_ref().unwrap().result.as_ref().unwrap().output, "5");
+
+    assert!(session.remove_variable("Tax Rate"));
+    assert!(!session.remove_variable("tax rate"));
+    assert_eq!(session.variables(&calculater).len(), 3);
+
+    session.clear_variables();
+    assert!(session.variables(&calculater).is_empty());
+}
+
+#[test]
+fn session_json_round_trip() {
+    use crate::{Session, SmartCalcError};
+
+    let calculater = SmartCalc::default();
+    let mut session = Session::new();
+    session.set_language("en".to_string());
+    session.set_text(r"price = $1.250
+rate = %15
+length = 10 cm
+meeting = 11:30 EST
+deadline = 1 jan 2021
+trip = 3 days 4 hours
+count = 0x1F
+speed = 60 km/h
+tax 2 = 5".to_string());
+    calculater.execute_session(&session);
+
+    let json = session.to_json().unwrap();
+    let restored = Session::from_json(&calculater, &json).unwrap();
+    assert_eq!(restored.get_language(), "en");
+    assert_eq!(restored.variables(&calculater), session.variables(&calculater));
+    assert_eq!(restored.to_json().unwrap(), json);
+    assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+
+    let mut restored = restored;
+    assert!(restored.update_line(9, "length + 5 mm".to_string()));
+    assert!(restored.update_line(10, "tax 2 * 2".to_string()));
+    let results = calculater.execute_incremental(&restored);
+    assert_eq!(results.lines[9].as_ref().unwrap().result.as_ref().unwrap().output, "10,50 cm");
+    assert_eq!(results.lines[10].as_ref().unwrap().result.as_ref().unwrap().output, "10");
+    assert!(restored.to_json().unwrap().contains(r#""unit":"km/h""#));
+
+    assert_eq!(Session::from_json(&calculater, &json.replace("USD", "XYZ")).err(), Some(SmartCalcError::UnknownCurrency { currency: "XYZ".to_string(), span: Default::default() }));
+    assert!(Session::from_json(&calculater, &json.replace("metric-length", "unknown")).is_err());
+    assert!(Session::from_json(&calculater, "{").is_err());
+}
+
+#[test]
+fn session_locale() {
+    use crate::{Session, Locale, CurrencyPlacement, DateOrder};
+
+    let calculater = SmartCalc::default();
+    let output = |session: &Session, index: usize| calculater.execute_session(session).lines[index].as_ref().unwrap().result.as_ref().unwrap().output.to_string();
+
+    let mut indian = Session::new();
+    indian.set_language("en".to_string());
+    indian.set_locale(Locale::indian().with_currency_placement(CurrencyPlacement::LeftWithSpace));
+    indian.set_text("1,00,000.50 + 2,00,000\n1,50,000 inr\n$12,34,567.5".to_string());
+
+    let mut swiss = Session::new();
+    swiss.set_language("en".to_string());
+    swiss.set_locale(Locale::swiss().with_date_order(DateOrder::MonthDayYear));
+    swiss.set_text("1'234.50 * 2\n3/12/2021\n%12.5 of 1'000".to_string());
+
+    let mut french = Session::new();
+    french.set_language("fr".to_string());
+    french.set_locale(Locale::non_breaking_space().with_currency_placement(CurrencyPlacement::RightWithSpace));
+    french.set_text("1\u{a0}234,5 + 1\n$1\u{a0}000".to_string());
+
+    let mut default = Session::new();
+    default.set_language("en".to_string());
+    default.set_text("1.234,5 * 2".to_string());
+
+    assert_eq!(output(&indian, 0), "3,00,000.50");
+    assert_eq!(output(&indian, 1), "₹ 1,50,000.00");
+    assert_eq!(output(&indian, 2), "$ 12,34,567.50");
+    assert_eq!(output(&swiss, 0), "2'469");
+    assert_eq!(output(&swiss, 1), "Mar 12, 2021");
+    assert_eq!(output(&swiss, 2), "125");
+    assert_eq!(output(&french, 0), "1\u{a0}235,50");
+    assert_eq!(output(&french, 1), "1\u{a0}000,00 $");
+    assert_eq!(output(&default, 0), "2.469");
+
+    swiss.clear_locale();
+    assert_eq!(swiss.get_locale(), None);
+    assert_eq!(output(&swiss, 1), "3 Dec 2021");
+}
+
+#[test]
+fn session_complete() {
+    use crate::{CompletionKind, Session};
+
+    let calculater = SmartCalc::default();
+    let mut session = Session::new();
+    session.set_language("en".to_string());
+    session.set_text("total price = 10\n10 usd to \n10 usd t\n10 k\ntot\n12 mar\n10\n\n12:00 to Europe/Ber\n12:00 to ber\nber\ntotal p\ntotal price + tot\n10 gb to m\n10 km to m\n10 eu\n12:00 eu".to_string());
+    calculater.execute_session(&session);
+
+    let completions = calculater.complete(&session, 1, 10);
+    assert!(completions.iter().any(|completion| completion.text == "EUR"));
+    assert!(completions.iter().all(|completion| completion.kind == CompletionKind::Currency || completion.text == "on"));
+
+    let completions = calculater.complete(&session, 2, 8);
+    assert!(completions.iter().take(2).any(|completion| completion.text == "to" && completion.kind == CompletionKind::Keyword));
+    assert_eq!((completions[0].start, completions[0].end), (7, 8));
+
+    let completions = calculater.complete(&session, 3, 4);
+    assert_eq!(completions.iter().take(4).map(|completion| (&completion.text[..], completion.kind)).collect::<alloc::vec::Vec<_>>(),
+        [("kr", CompletionKind::Currency), ("kb", CompletionKind::Unit), ("kg", CompletionKind::Unit), ("km", CompletionKind::Unit)]);
+
+    let completions = calculater.complete(&session, 4, 3);
+    assert_eq!(completions.len(), 1);
+    assert_eq!((&completions[0].text[..], completions[0].kind, completions[0].start, completions[0].end), ("total price", CompletionKind::Variable, 0, 3));
+
+    let completions = calculater.complete(&session, 5, 6);
+    assert_eq!((&completions[0].text[..], completions[0].kind), ("mar", CompletionKind::Month));
+    assert!(completions.iter().any(|completion| completion.text == "march"));
+
+    assert!(calculater.complete(&session, 6, 2).is_empty());
+    assert!(calculater.complete(&session, 7, 0).is_empty());
+
+    /* IANA names and cities, cities only where a timezone is expected */
+    let completions = calculater.complete(&session, 8, 19);
+    assert_eq!((&completions[0].text[..], completions[0].kind, completions[0].start), ("Europe/Berlin", CompletionKind::Timezone, 9));
+    let completions = calculater.complete(&session, 9, 12);
+    assert_eq!((&completions[0].text[..], completions[0].kind, completions[0].start), ("Berlin", CompletionKind::Timezone, 9));
+    assert!(calculater.complete(&session, 10, 3).iter().all(|completion| completion.text != "Berlin"));
+
+    /* Multi word variables match the words before the cursor and come first where a value is expected */
+    let completions = calculater.complete(&session, 11, 7);
+    assert_eq!((&completions[0].text[..], completions[0].kind, completions[0].start), ("total price", CompletionKind::Variable, 0));
+    let completions = calculater.complete(&session, 12, 17);
+    assert_eq!((&completions[0].text[..], completions[0].kind, completions[0].start), ("total price", CompletionKind::Variable, 14));
+
+    /* Units of the same dimension come first after a conversion word */
+    let completions = calculater.complete(&session, 13, 10);
+    assert_eq!(completions.iter().take(3).map(|completion| &completion.text[..]).collect::<alloc::vec::Vec<_>>(), ["mb", "mega", "megabyte"]);
+    let completions = calculater.complete(&session, 14, 10);
+    assert_eq!(completions.iter().take(2).map(|completion| &completion.text[..]).collect::<alloc::vec::Vec<_>>(), ["m", "mm"]);
+
+    /* Currencies come before the timezones after a number, timezones only come first after a time */
+    let completions = calculater.complete(&session, 15, 5);
+    assert_eq!((&completions[0].text[..], completions[0].kind), ("EUR", CompletionKind::Currency));
+    let completions = calculater.complete(&session, 16, 8);
+    assert_eq!(completions[0].kind, CompletionKind::Timezone);
+}
+
@@ -20,7 +20,8 @@ pub enum UiTokenType {
     Comment,
     VariableDefination,
     VariableUse,
-    Month
+    Month,
+    Function
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
@@ -90,6 +91,16 @@ impl UiTokenCollection {
         }
     }
 
+    pub fn add_from_location(&mut self, start: usize, end: usize, token_type: UiTokenType) {
+        if start < end && self.check_collision(start, end) {
+            self.tokens.push(UiToken {
+                start: self.get_position(start),
+                end: self.get_position(end),
+                ui_type: token_type
+            });
+        }
+    }
+
     pub fn iter(&self) -> UiTokenIterator {
         UiTokenIterator { iter: self.tokens.iter() }
     }
@@ -6,15 +6,24 @@
 
 use alloc::vec::Vec;
 use alloc::string::ToString;
+use core::ops::Deref;
 
 use crate::types::TokenType;
+use crate::explain::ExplainStep;
 
-use super::{Tokinizer, regex_tokinizer::get_atom};
+use super::{Tokinizer, TokenInfo, regex_tokinizer::get_atom};
 
- 
+/* Values keep their value, even if a separator like "1'234" or "aujourd'hui" is also an alias */
+fn is_value(token: &TokenInfo) -> bool {
+    matches!(token.token_type.borrow().deref(), Some(TokenType::Number(_, _)) | Some(TokenType::Money(_, _)) | Some(TokenType::Percent(_)) | Some(TokenType::Date(_, _)) | Some(TokenType::Time(_, _)))
+}
+
+fn trace_alias(tokinizer: &Tokinizer, token: &TokenInfo, data: &str) {
+    tokinizer.session.trace(|| ExplainStep::Alias { text: token.original_text.to_string(), replacement: data.to_string() });
+}
 
 pub fn alias_tokinizer(tokinizer: &mut Tokinizer) {
-    for token in tokinizer.token_infos.iter() {
+    for token in tokinizer.token_infos.iter().filter(|token| !is_value(token)) {
         for (re, data) in tokinizer.config.alias_regex.iter() {
             if re.is_match(&token.original_text.to_lowercase()) {
                 let new_values = match tokinizer.config.token_parse_regex.get("atom") {
@@ -26,11 +35,13 @@ pub fn alias_tokinizer(tokinizer: &mut Tokinizer) {
                     1 => {
                         if let Some(token_type) = &new_values[0].2 {
                             *token.token_type.borrow_mut() = Some(token_type.clone());
+                            trace_alias(tokinizer, token, &token_type.to_string());
                             break;
                         }
                     },
                     0 => {
                         *token.token_type.borrow_mut() = Some(TokenType::Text(data.to_string()));
+                        trace_alias(tokinizer, token, data);
                         break;
                     },
                     _ => log::warn!("{} has multiple atoms. It is not allowed", data)
@@ -39,7 +50,7 @@ pub fn alias_tokinizer(tokinizer: &mut Tokinizer) {
         }
     }
 
-    for token in tokinizer.token_infos.iter() {
+    for token in tokinizer.token_infos.iter().filter(|token| !is_value(token)) {
         for (re, data) in tokinizer.config.language_alias_regex.get(&tokinizer.language).unwrap().iter() {
             if re.is_match(&token.original_text.to_lowercase()) {
                 let new_values = match tokinizer.config.token_parse_regex.get("atom") {
@@ -51,11 +62,13 @@ pub fn alias_tokinizer(tokinizer: &mut Tokinizer) {
                     1 => {
                         if let Some(token_type) = &new_values[0].2 {
                             *token.token_type.borrow_mut() = Some(token_type.clone());
+                            trace_alias(tokinizer, token, &token_type.to_string());
                             break;
                         }
                     },
                     0 => {
                         *token.token_type.borrow_mut() = Some(TokenType::Text(data.to_string()));
+                        trace_alias(tokinizer, token, data);
                         break;
                     },
                     _ => log::warn!("{} has multiple atoms. It is not allowed", data)
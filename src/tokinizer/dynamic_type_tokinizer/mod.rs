@@ -9,6 +9,7 @@ use core::{ops::Deref, cell::{RefCell, Cell}};
 use alloc::string::ToString;
 
 use crate::{types::TokenType, UiTokenType};
+use crate::tools::number_to_f64;
 
 use super::{Tokinizer, TokenInfoStatus, TokenInfo, get_number};
 
@@ -34,9 +35,9 @@ pub fn dynamic_type_tokinizer(tokinizer: &mut Tokinizer) {
 
                         if let Some(token_type) = &token.token_type.borrow().deref() {
                             if let TokenType::Variable(variable) = &token_type {
-                                let is_same = TokenType::variable_compare(&rule_tokens[rule_token_index], variable.data.borrow().clone());
+                                let is_same = rule_tokens[rule_token_index].matches_variable(variable.data.borrow().clone());
                                 if is_same {
-                                    match TokenType::get_field_name(&rule_tokens[rule_token_index]) {
+                                    match rule_tokens[rule_token_index].field_name() {
                                         Some(field_name) => fields.insert(field_name.to_string(), token.clone()),
                                         None => None
                                     };
@@ -47,21 +48,21 @@ pub fn dynamic_type_tokinizer(tokinizer: &mut Tokinizer) {
                                     start_token_index   = target_token_index;
                                 }
                             }
-                            else if token == &rule_tokens[rule_token_index] {
-                                match TokenType::get_field_name(&rule_tokens[rule_token_index]) {
+                            else if rule_tokens[rule_token_index].matches(token) {
+                                match rule_tokens[rule_token_index].field_name() {
                                     Some(field_name) => fields.insert(field_name.to_string(), token.clone()),
                                     None => None
                                 };
 
                                 if cfg!(feature="debug-rules") {
-                                    log::debug!("Ok, {:?} == {:?}", token.token_type, &rule_tokens[rule_token_index].token_type);
+                                    log::debug!("Ok, {:?} == {:?}", token.token_type, &rule_tokens[rule_token_index]);
                                 }
 
                                 rule_token_index   += 1;
                             }
                             else {
                                 if cfg!(feature="debug-rules") {
-                                    log::debug!("No, {:?} == {:?}", token.token_type, &rule_tokens[rule_token_index].token_type);
+                                    log::debug!("No, {:?} == {:?}", token.token_type, &rule_tokens[rule_token_index]);
                                 }
                                 rule_token_index    = 0;
                                 start_token_index   = target_token_index;
@@ -84,7 +85,7 @@ pub fn dynamic_type_tokinizer(tokinizer: &mut Tokinizer) {
                             tokinizer.token_infos[index].status.set(TokenInfoStatus::Removed);
                         }
                         
-                        let value = get_number("value", &fields).unwrap();
+                        let value = number_to_f64(get_number("value", &fields).unwrap());
                         if let Some(data) = fields.get("type") {
                             tokinizer.ui_tokens.update_tokens(data.start, data.end, UiTokenType::Symbol2)
                         }
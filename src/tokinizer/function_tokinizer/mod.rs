@@ -0,0 +1,74 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use core::ops::Deref;
+use alloc::string::ToString;
+
+use crate::types::TokenType;
+use crate::token::ui_token::UiTokenType;
+
+use super::Tokinizer;
+
+pub fn function_tokinizer(tokinizer: &mut Tokinizer) {
+    for index in 1..tokinizer.token_infos.len() {
+        let function_name = match (tokinizer.token_infos[index - 1].token_type.borrow().deref(), tokinizer.token_infos[index].token_type.borrow().deref()) {
+            (Some(TokenType::Text(text)), Some(TokenType::Operator('('))) => text.to_lowercase(),
+            _ => continue
+        };
+
+        if tokinizer.config.functions.contains_key(&function_name) {
+            let token = tokinizer.token_infos[index - 1].clone();
+            *token.token_type.borrow_mut() = Some(TokenType::Function(function_name.to_string()));
+            tokinizer.ui_tokens.update_tokens(token.start, token.end, UiTokenType::Function);
+            restore_separators(tokinizer, index);
+        }
+    }
+}
+
+/* ";" separates the arguments when "," is the decimal separator, like "max(3;5)". It is an alias of nothing elsewhere, so it is restored inside the parentheses */
+fn restore_separators(tokinizer: &Tokinizer, open_index: usize) {
+    let mut depth = 0;
+    for token in tokinizer.token_infos[open_index..].iter() {
+        match token.token_type.borrow().deref() {
+            Some(TokenType::Operator('(')) => depth += 1,
+            Some(TokenType::Operator(')')) => depth -= 1,
+            _ => ()
+        };
+
+        if depth == 0 {
+            break;
+        }
+
+        if depth == 1 && token.original_text == ";" {
+            *token.token_type.borrow_mut() = Some(TokenType::Operator(';'));
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn function_test_1() {
+    use alloc::string::ToString;
+    use crate::tokinizer::test::get_executed_raw_tokens;
+
+    let tokens = get_executed_raw_tokens("sqrt(16) + round(12,345)".to_string());
+    assert_eq!(tokens.len(), 9);
+    assert_eq!(*tokens[0], TokenType::Function("sqrt".to_string()));
+    assert_eq!(*tokens[1], TokenType::Operator('('));
+    assert_eq!(*tokens[4], TokenType::Operator('+'));
+    assert_eq!(*tokens[5], TokenType::Function("round".to_string()));
+}
+
+#[cfg(test)]
+#[test]
+fn function_test_2() {
+    use alloc::string::ToString;
+    use crate::tokinizer::test::get_executed_raw_tokens;
+
+    let tokens = get_executed_raw_tokens("unknown(16)".to_string());
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(*tokens[0], TokenType::Operator('('));
+}
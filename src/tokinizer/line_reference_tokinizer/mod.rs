@@ -0,0 +1,185 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use core::cell::{Cell, RefCell};
+use core::ops::Deref;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::compiler::{DataItem, OperationType};
+use crate::compiler::number::NumberItem;
+use crate::constants::ConstantType;
+use crate::error::{SmartCalcError, Span};
+use crate::explain::ExplainStep;
+use crate::token::ui_token::UiTokenType;
+use crate::tools::{number_from_i64, number_to_f64};
+use crate::types::{TokenType, NumberType};
+
+use super::{Tokinizer, TokenInfo, TokenInfoStatus};
+
+fn is_value_token(token_type: &TokenType) -> bool {
+    !matches!(token_type, TokenType::Text(_) | TokenType::Operator(_) | TokenType::Function(_) | TokenType::Field(_) | TokenType::Month(_)) || matches!(token_type, TokenType::Operator(')'))
+}
+
+/* Only lines of the same kind are added, "$10" and "2 hours" have no sum */
+fn add_items(tokinizer: &Tokinizer, left: &Rc<dyn DataItem>, right: &Rc<dyn DataItem>) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+    match left.type_name() == right.type_name() {
+        true => left.calculate(tokinizer.config, tokinizer.session, true, right.deref(), OperationType::Add),
+        false => Err(SmartCalcError::IncompatibleTypes { left: left.type_name().to_string(), right: right.type_name().to_string(), span: Span::default() })
+    }
+}
+
+/* Aggregates the block of calculated lines just above the current line. Blank lines and lines without a value (headers, comments, errors) end the block,
+   earlier sum and average lines are skipped as their lines are already in the block. */
+fn aggregate(tokinizer: &mut Tokinizer, token: &TokenInfo, line_index: usize, average: bool) -> Result<Option<Rc<dyn DataItem>>, SmartCalcError> {
+    tokinizer.aggregate_line = true;
+
+    let mut items = Vec::new();
+    for index in (0..line_index).rev() {
+        tokinizer.line_references.insert(index);
+        if tokinizer.session.is_aggregate_line(index) {
+            continue;
+        }
+
+        match tokinizer.session.get_line_result(index) {
+            Some(item) => items.push((index, item)),
+            None => break
+        };
+    }
+    items.reverse();
+
+    let mut lines = items.iter();
+    let mut total = match lines.next() {
+        Some((_, item)) => item.clone(),
+        None => return Ok(None)
+    };
+
+    for (_, item) in lines {
+        total = add_items(tokinizer, &total, item)?;
+    }
+
+    if average {
+        total = total.calculate(tokinizer.config, tokinizer.session, true, &NumberItem(number_from_i64(items.len() as i64), NumberType::Decimal), OperationType::Div)?;
+    }
+
+    /* The word is also an alias of "+", the aggregation replaces that step */
+    let (config, session) = (tokinizer.config, tokinizer.session);
+    session.retract_trace(|step| matches!(step, ExplainStep::Alias { text, .. } if *text == token.original_text));
+    session.trace(|| ExplainStep::Aggregate {
+        text: token.original_text.to_string(),
+        lines: items.iter().map(|(index, item)| (index + 1, item.print(config, session))).collect(),
+        result: total.print(config, session)
+    });
+    Ok(Some(total))
+}
+
+pub fn line_reference_tokinizer(tokinizer: &mut Tokinizer) {
+    let constants = match tokinizer.config.constant_pair.get(&tokinizer.language) {
+        Some(constants) => constants,
+        None => return
+    };
+
+    let line_index = tokinizer.session.current_line_index();
+    let mut token_start_index = 0;
+
+    for (index, token) in tokinizer.token_infos.iter().enumerate() {
+        if let Some(TokenType::Operator('=')) = &token.token_type.borrow().deref() {
+            token_start_index = index + 1;
+            break;
+        }
+    }
+
+    let mut index = token_start_index;
+    while index < tokinizer.token_infos.len() {
+        let token = tokinizer.token_infos[index].clone();
+        index += 1;
+
+        if token.status.get() != TokenInfoStatus::Active {
+            continue;
+        }
+
+        let constant = match (token.token_type.borrow().deref(), constants.get(&token.original_text.to_lowercase())) {
+            (Some(TokenType::Variable(_)), _) => continue,
+            (_, Some(constant)) => constant.clone(),
+            _ => continue
+        };
+
+        /* "5 sum 3" is an addition, not a reference */
+        let previous_token = tokinizer.token_infos[token_start_index..index - 1].iter().rev().find(|item| item.status.get() == TokenInfoStatus::Active && item.token_type.borrow().is_some());
+        if let Some(previous_token) = previous_token {
+            if is_value_token(previous_token.token_type.borrow().as_ref().unwrap()) {
+                continue;
+            }
+        }
+
+        let mut end_index = index - 1;
+        let item = match constant {
+            ConstantType::Previous => match line_index {
+                0 => None,
+                _ => {
+                    tokinizer.line_references.insert(line_index - 1);
+                    tokinizer.session.get_line_result(line_index - 1)
+                }
+            },
+            ConstantType::Sum | ConstantType::Average => match aggregate(tokinizer, &token, line_index, matches!(constant, ConstantType::Average)) {
+                Ok(item) => item,
+                Err(error) => {
+                    let span = tokinizer.char_span(token.start, token.end);
+                    tokinizer.token_error.get_or_insert(error.with_span(span));
+                    continue
+                }
+            },
+            ConstantType::Line => {
+                let line_number = match tokinizer.token_infos.get(index).map(|item| item.token_type.borrow().clone()) {
+                    Some(Some(TokenType::Number(number, _))) => number_to_f64(number),
+                    _ => continue
+                };
+
+                end_index = index;
+                match line_number.fract() == 0.0 && line_number >= 1.0 && (line_number as usize) <= line_index {
+                    true => {
+                        tokinizer.line_references.insert(line_number as usize - 1);
+                        tokinizer.session.get_line_result(line_number as usize - 1)
+                    },
+                    false => None
+                }
+            },
+            _ => continue
+        };
+
+        /* A reference without a value, like "prev" after an error or "line9" of a shorter text, fails the line */
+        let item = match item {
+            Some(item) => item,
+            None => {
+                let end = tokinizer.token_infos[end_index].end;
+                if tokinizer.token_error.is_none() {
+                    let reference = tokinizer.data[token.start..end].to_string();
+                    tokinizer.token_error = Some(SmartCalcError::UnknownLineReference { reference, span: tokinizer.char_span(token.start, end) });
+                }
+                continue
+            }
+        };
+
+        let start = token.start;
+        let end = tokinizer.token_infos[end_index].end;
+        let mut original_text = String::new();
+        for reference_token in tokinizer.token_infos[index - 1..=end_index].iter() {
+            original_text.push_str(&reference_token.original_text);
+            reference_token.status.set(TokenInfoStatus::Removed);
+        }
+
+        tokinizer.ui_tokens.update_tokens(start, end, UiTokenType::VariableUse);
+        tokinizer.token_infos[index - 1] = Rc::new(TokenInfo {
+            start,
+            end,
+            token_type: RefCell::new(Some(item.as_token_type())),
+            original_text,
+            status: Cell::new(TokenInfoStatus::Active)
+        });
+        index = end_index + 1;
+    }
+}
@@ -6,8 +6,11 @@
 
 mod regex_tokinizer;
 mod alias_tokinizer;
+mod function_tokinizer;
+mod line_reference_tokinizer;
 mod rule_tokinizer;
 mod dynamic_type_tokinizer;
+mod unit_tokinizer;
 mod tools;
 
 pub use self::rule_tokinizer::rules::date_rules::small_date;
@@ -15,7 +18,10 @@ pub use self::rule_tokinizer::rules::date_rules::small_date;
 pub use self::regex_tokinizer::regex_tokinizer;
 pub use self::regex_tokinizer::language_tokinizer;
 pub use self::alias_tokinizer::alias_tokinizer;
+pub use self::function_tokinizer::function_tokinizer;
+pub use self::line_reference_tokinizer::line_reference_tokinizer;
 pub use self::dynamic_type_tokinizer::dynamic_type_tokinizer;
+pub use self::unit_tokinizer::unit_tokinizer;
 pub use self::tools::*;
 pub use self::rule_tokinizer::{rule_tokinizer, RuleType, RuleItemList, RULE_FUNCTIONS};
 
@@ -24,6 +30,7 @@ use core::ops::Deref;
 use core::cell::RefCell;
 
 use alloc::rc::Rc;
+use alloc::collections::BTreeSet;
 use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::string::ToString;
@@ -35,6 +42,8 @@ use crate::session::Session;
 use crate::config::SmartCalcConfig;
 use crate::variable::update_token_variables;
 use crate::{token::ui_token::UiTokenCollection, types::*};
+use crate::error::{SmartCalcError, Span};
+use crate::unit::Unit;
 
 
 pub struct Tokinizer<'a> {
@@ -50,6 +59,12 @@ pub struct Tokinizer<'a> {
     pub language: String,
     pub token_infos: Vec<Rc<TokenInfo>>,
     pub tokens: Vec<Rc<TokenType>>,
+    pub token_spans: Vec<Span>,
+    pub line_references: BTreeSet<usize>,
+    pub aggregate_line: bool,
+    pub token_error: Option<SmartCalcError>,
+    pub rule_error: Option<SmartCalcError>,
+    pub target_unit: Option<(Rc<Unit>, Span)>,
 }
 
 #[derive(Debug)]
@@ -71,8 +86,6 @@ pub struct TokenInfo {
     pub status: Cell<TokenInfoStatus>
 }
 
-unsafe impl Send for TokenInfo {}
-unsafe impl Sync for TokenInfo {}
 
 impl<'a> Tokinizer<'a> {
     pub fn new(config: &'a SmartCalcConfig, session: &'a Session) -> Tokinizer<'a> {
@@ -88,11 +101,18 @@ impl<'a> Tokinizer<'a> {
             session,
             language: session.get_language(),
             token_infos: Vec::new(),
-            tokens: Vec::new()
+            tokens: Vec::new(),
+            token_spans: Vec::new(),
+            line_references: BTreeSet::new(),
+            aggregate_line: false,
+            token_error: None,
+            rule_error: None,
+            target_unit: None
         }
     }
 
-    pub fn token_infos(config: &'a SmartCalcConfig, session: &'a Session) -> Vec<Rc<TokenInfo>> {
+    /// Compiles the current line of the session into the pattern of a rule or dynamic type.
+    pub fn pattern(config: &'a SmartCalcConfig, session: &'a Session) -> Vec<PatternToken> {
         let mut tokinizer = Tokinizer {
             column: 0,
             iter: session.current_line().chars().collect(),
@@ -105,13 +125,19 @@ impl<'a> Tokinizer<'a> {
             session,
             language: session.get_language(),
             token_infos: Vec::new(),
-            tokens: Vec::new()
+            tokens: Vec::new(),
+            token_spans: Vec::new(),
+            line_references: BTreeSet::new(),
+            aggregate_line: false,
+            token_error: None,
+            rule_error: None,
+            target_unit: None
         };
 
         language_tokinizer(&mut tokinizer);
         regex_tokinizer(&mut tokinizer);
         alias_tokinizer(&mut tokinizer);
-        tokinizer.token_infos
+        tokinizer.token_infos.iter().filter_map(|token| token.token_type.borrow().as_ref().map(PatternToken::new)).collect()
     }
 
     pub fn tokinize(&mut self) -> bool {
@@ -121,8 +147,14 @@ impl<'a> Tokinizer<'a> {
         log::debug!(" > regex_tokinizer");
         alias_tokinizer(self);
         log::debug!(" > alias_tokinizer");
+        function_tokinizer(self);
+        log::debug!(" > function_tokinizer");
         update_token_variables(self);
         log::debug!(" > update_token_variables");
+        line_reference_tokinizer(self);
+        log::debug!(" > line_reference_tokinizer");
+        unit_tokinizer(self);
+        log::debug!(" > unit_tokinizer");
         dynamic_type_tokinizer(self);
         log::debug!(" > dynamic_type_tokinizer");
         rule_tokinizer(self);
@@ -139,6 +171,19 @@ impl<'a> Tokinizer<'a> {
         !self.token_infos.is_empty()
     }
 
+    /// Tokenize a line that is still being typed, without adding the missing tokens.
+    pub(crate) fn partial_tokinize(&mut self) {
+        language_tokinizer(self);
+        regex_tokinizer(self);
+        alias_tokinizer(self);
+        function_tokinizer(self);
+        update_token_variables(self);
+        line_reference_tokinizer(self);
+        unit_tokinizer(self);
+        dynamic_type_tokinizer(self);
+        rule_tokinizer(self);
+    }
+
     pub fn basic_tokinize(&mut self) -> bool {
         regex_tokinizer(self);
         log::debug!(" > regex_tokinizer");
@@ -179,18 +224,31 @@ impl<'a> Tokinizer<'a> {
         true
     }
 
+    pub fn char_span(&self, start: usize, end: usize) -> Span {
+        let position = |index: usize| match self.data.get(..index) {
+            Some(data) => data.chars().count(),
+            None => index
+        };
+        Span::new(position(start), position(end))
+    }
+
+    pub fn line_span(&self) -> Span {
+        Span::new(0, self.data.chars().count())
+    }
+
     pub fn token_generator(&mut self) {
         let mut tokens = Vec::new();
         for token_location in self.token_infos.iter() {
             if token_location.status.get() == TokenInfoStatus::Active {
                 if let Some(token_type) = &token_location.token_type.borrow().deref() {
-                    tokens.push(token_type.clone());
+                    tokens.push((token_type.clone(), self.char_span(token_location.start, token_location.end)));
                 }
             }
         }
         
-        for token in tokens {
+        for (token, span) in tokens {
             self.tokens.push(Rc::new(token));
+            self.token_spans.push(span);
         }
     }
 
@@ -207,6 +265,7 @@ impl<'a> Tokinizer<'a> {
             match self.tokens[index].deref() {
                 TokenType::Text(_) => {
                     self.tokens.remove(index);
+                    self.token_spans.remove(index);
                 },
                 _ => index += 1
             };
@@ -241,8 +300,11 @@ impl<'a> Tokinizer<'a> {
 
         let mut operator_required = false;
 
-        if let TokenType::Operator(_) = self.tokens[index].deref() {
-            self.tokens.insert(index, Rc::new(TokenType::Number(0.0, NumberType::Decimal)));
+        /* Empty argument list of a function call, like "stock()", has no missing operand */
+        if matches!(self.tokens[index].deref(), TokenType::Operator(operator) if *operator != ')') {
+            let span = Span::new(self.token_spans[index].start, self.token_spans[index].start);
+            self.tokens.insert(index, Rc::new(TokenType::Number(Number::default(), NumberType::Decimal)));
+            self.token_spans.insert(index, span);
         }
 
         while index < self.tokens.len() {
@@ -251,7 +313,9 @@ impl<'a> Tokinizer<'a> {
                 _ => {
                     if operator_required {
                         log::debug!("Added missing operator between two token");
+                        let span = Span::new(self.token_spans[index].start, self.token_spans[index].start);
                         self.tokens.insert(index, Rc::new(TokenType::Operator('+')));
+                        self.token_spans.insert(index, span);
                         index += 1;
                     }
                     operator_required = true;
@@ -37,7 +37,7 @@ pub fn get_atom(config: &SmartCalcConfig, data: &str, group_item: &[Regex]) -> V
                 "MONEY" => {
                     let splited_data: Vec<&str> = data.split(';').collect();
                     match config.get_currency(splited_data[1].to_string()) {
-                        Some(currency_info) => TokenType::Money(splited_data[0].parse::<f64>().unwrap(), currency_info.clone()),
+                        Some(currency_info) => TokenType::Money(splited_data[0].parse::<Number>().unwrap(), currency_info.clone()),
                         None => {
                             log::info!("Currency information not found, {}", splited_data[1]);
                             continue
@@ -45,7 +45,7 @@ pub fn get_atom(config: &SmartCalcConfig, data: &str, group_item: &[Regex]) -> V
                     }
                 },
                 "NUMBER" => {
-                    let number = data.parse::<f64>().unwrap();
+                    let number = data.parse::<Number>().unwrap();
                     TokenType::Number(number, NumberType::Decimal)
                 },
                 "PERCENT" => {
@@ -103,9 +103,9 @@ fn operator_test() {
 
     assert_eq!(tokens[3].start, 43);
     assert_eq!(tokens[3].end, 60);
-    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Number(-222.333, NumberType::Decimal)));
+    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Number(number!(-222.333), NumberType::Decimal)));
 
     assert_eq!(tokens[4].start, 61);
     assert_eq!(tokens[4].end, 76);
-    assert_eq!(tokens[4].token_type.borrow().deref(), &Some(TokenType::Money(200.0, config.get_currency("try".to_string()).unwrap())));
+    assert_eq!(tokens[4].token_type.borrow().deref(), &Some(TokenType::Money(number!(200.0), config.get_currency("try".to_string()).unwrap())));
 }
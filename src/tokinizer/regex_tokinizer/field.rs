@@ -4,7 +4,7 @@
  * Licensed under the GNU General Public License v2.0.
  */
 
-use alloc::rc::Rc;
+use crate::shared::Shared;
 use alloc::string::ToString;
 use alloc::borrow::ToOwned;
 use crate::config::SmartCalcConfig;
@@ -56,7 +56,7 @@ pub fn field_regex_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer, g
             let name  = capture.name("NAME").unwrap().as_str();
 
             if let Some(field) = get_field_type(config, field_type, name, &tokinizer.language, &capture) {
-                tokinizer.add_token_from_match(&capture.get(0), Some(TokenType::Field(Rc::new(field))));
+                tokinizer.add_token_from_match(&capture.get(0), Some(TokenType::Field(Shared::new(field))));
             }
         }
     }
@@ -80,11 +80,11 @@ fn field_test() {
     assert_eq!(tokens.len(), 2);
     assert_eq!(tokens[0].start, 0);
     assert_eq!(tokens[0].end, 14);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Field(Rc::new(FieldType::Text("merhaba".to_string(), None)))));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Field(Shared::new(FieldType::Text("merhaba".to_string(), None)))));
 
-    assert_ne!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Field(Rc::new(FieldType::Text("test".to_string(), None)))));
+    assert_ne!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Field(Shared::new(FieldType::Text("test".to_string(), None)))));
 
     assert_eq!(tokens[1].start, 15);
     assert_eq!(tokens[1].end, 32);
-    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Field(Rc::new(FieldType::Percent("percent".to_string())))));
+    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Field(Shared::new(FieldType::Percent("percent".to_string())))));
 }
@@ -6,19 +6,21 @@
 
 use alloc::string::ToString;
 use regex::Regex;
-use alloc::borrow::ToOwned;
 use crate::config::SmartCalcConfig;
 use crate::tokinizer::{Tokinizer, read_currency};
-use crate::types::{TokenType};
+use crate::types::{TokenType, Number};
+use crate::tools::{do_number_multiplication, number_from_f64};
 use crate::token::ui_token::{UiTokenType};
 
 pub fn money_regex_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer, group_item: &[Regex]) {
+    let locale = tokinizer.session.locale(config);
+    let data = locale.mask_separators(&tokinizer.data);
     for re in group_item.iter() {
-        for capture in re.captures_iter(&tokinizer.data.to_owned()) {
+        for capture in re.captures_iter(&data) {
             /* Check price value */
-            let price = match capture.name("PRICE").unwrap().as_str().replace(&config.thousand_separator[..], "").replace(&config.decimal_seperator[..], ".").parse::<f64>() {
+            let price = match locale.normalize_number(&tokinizer.data[capture.name("PRICE").unwrap().range()]).parse::<Number>() {
                 Ok(price) => match capture.name("NOTATION") {
-                    Some(notation) => price * match notation.as_str() {
+                    Some(notation) => match number_from_f64(match notation.as_str() {
                         "k" | "K" => 1_000.0,
                         "M" => 1_000_000.0,
                         "G" => 1_000_000_000.0,
@@ -27,6 +29,9 @@ pub fn money_regex_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer, g
                         "Z" => 1_000_000_000_000_000_000.0,
                         "Y" => 1_000_000_000_000_000_000_000.0,
                         _ => 1.0
+                    }).and_then(|multiplier| do_number_multiplication(price, multiplier)) {
+                        Ok(price) => price,
+                        Err(_) => continue
                     },
                     _ => price
                 },
@@ -49,7 +54,7 @@ pub fn money_regex_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer, g
                 _ => capture.name("CURRENCY").unwrap().end()
             };
 
-            if tokinizer.add_token_location(capture.get(0).unwrap().start(), end, Some(TokenType::Money(price, currency.clone())), capture.name("PRICE").unwrap().as_str().to_string()) {
+            if tokinizer.add_token_location(capture.get(0).unwrap().start(), end, Some(TokenType::Money(price, currency.clone())), tokinizer.data[capture.name("PRICE").unwrap().range()].to_string()) {
                 tokinizer.add_uitoken_from_match(capture.name("PRICE"), UiTokenType::Number);
                 tokinizer.add_uitoken_from_match(capture.name("CURRENCY"), UiTokenType::Symbol1);
                 tokinizer.add_uitoken_from_match(capture.name("NOTATION"), UiTokenType::Symbol2);
@@ -76,27 +81,27 @@ fn money_test_1() {
     assert_eq!(tokens.len(), 6);
     assert_eq!(tokens[0].start, 0);
     assert_eq!(tokens[0].end, 7);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(1000.0, config.get_currency("try".to_string()).unwrap())));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(number!(1000.0), config.get_currency("try".to_string()).unwrap())));
     
     assert_eq!(tokens[1].start, 8);
     assert_eq!(tokens[1].end, 15);
-    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Money(1000.0, config.get_currency("try".to_string()).unwrap())));
+    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Money(number!(1000.0), config.get_currency("try".to_string()).unwrap())));
     
     assert_eq!(tokens[2].start, 16);
     assert_eq!(tokens[2].end, 24);
-    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Money(1000.0, config.get_currency("try".to_string()).unwrap())));
+    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Money(number!(1000.0), config.get_currency("try".to_string()).unwrap())));
     
     assert_eq!(tokens[3].start, 25);
     assert_eq!(tokens[3].end, 32);
-    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Money(1000.0, config.get_currency("try".to_string()).unwrap())));
+    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Money(number!(1000.0), config.get_currency("try".to_string()).unwrap())));
     
     assert_eq!(tokens[4].start, 33);
     assert_eq!(tokens[4].end, 41);
-    assert_eq!(tokens[4].token_type.borrow().deref(), &Some(TokenType::Money(1000.0, config.get_currency("try".to_string()).unwrap())));
+    assert_eq!(tokens[4].token_type.borrow().deref(), &Some(TokenType::Money(number!(1000.0), config.get_currency("try".to_string()).unwrap())));
     
     assert_eq!(tokens[5].start, 42);
     assert_eq!(tokens[5].end, 49);
-    assert_eq!(tokens[5].token_type.borrow().deref(), &Some(TokenType::Money(1000.0, config.get_currency("try".to_string()).unwrap())));
+    assert_eq!(tokens[5].token_type.borrow().deref(), &Some(TokenType::Money(number!(1000.0), config.get_currency("try".to_string()).unwrap())));
 }
 
 #[cfg(test)]
@@ -117,5 +122,5 @@ fn money_test_2() {
     assert_eq!(tokens.len(), 1);
     assert_eq!(tokens[0].start, 0);
     assert_eq!(tokens[0].end, 3);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(2000.0, config.get_currency("usd".to_string()).unwrap())));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(number!(2000.0), config.get_currency("usd".to_string()).unwrap())));
 }
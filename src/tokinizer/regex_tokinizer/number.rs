@@ -5,51 +5,53 @@
  */
 
 use alloc::string::ToString;
-use alloc::borrow::ToOwned;
 use crate::config::SmartCalcConfig;
 use crate::types::*;
+use crate::tools::{do_number_multiplication, number_from_f64, number_from_i64};
 use crate::tokinizer::{Tokinizer};
 use regex::Regex;
 use crate::token::ui_token::{UiTokenType};
 
 pub fn number_regex_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer, group_item: &[Regex]) {
+    let locale = tokinizer.session.locale(config);
+    let data = locale.mask_separators(&tokinizer.data);
     for re in group_item.iter() {
-        for capture in re.captures_iter(&tokinizer.data.to_owned()) {
+        for capture in re.captures_iter(&data) {
             let mut parse_end = 0;
             let mut number_match = None;
             let mut notation_match = None;
 
             /* Check price value */
-            let mut number = 0.0;
+            let mut number = Number::default();
             let mut number_type = NumberType::Decimal;
 
             if let Some(binary) = capture.name("BINARY") {
                 parse_end = binary.end();
-                number = i64::from_str_radix(binary.as_str(), 2).unwrap() as f64;
+                number = number_from_i64(i64::from_str_radix(binary.as_str(), 2).unwrap());
                 number_type = NumberType::Binary;
                 number_match = capture.name("BINARY_FULL");
             }
             else if let Some(hex) = capture.name("HEX") { 
                 parse_end = hex.end();
-                number = i64::from_str_radix(hex.as_str(), 16).unwrap() as f64;
+                number = number_from_i64(i64::from_str_radix(hex.as_str(), 16).unwrap());
                 number_type = NumberType::Hexadecimal;
                 number_match = capture.name("HEX_FULL");
             }
             else if let Some(octal) = capture.name("OCTAL") { 
                 parse_end = octal.end();
-                number = i64::from_str_radix(octal.as_str(), 8).unwrap() as f64;
+                number = number_from_i64(i64::from_str_radix(octal.as_str(), 8).unwrap());
                 number_type = NumberType::Octal;
                 number_match = capture.name("OCTAL_FULL");
             }
             else if let Some(decimal) = capture.name("DECIMAL") {
                 parse_end = decimal.end();
-                number = match decimal.as_str().replace(&config.thousand_separator[..], "").replace(&config.decimal_seperator[..], ".").parse::<f64>() {
+                number = match locale.normalize_number(&tokinizer.data[decimal.range()]).parse::<Number>() {
                     Ok(num) => {
                         number_match = Some(decimal);
                         match capture.name("NOTATION") {
                             Some(notation) => {
                                 notation_match = Some(notation);
-                                num * match notation.as_str() {
+                                let multiplier = number_from_f64(match notation.as_str() {
                                     "k" | "K" => 1_000.0,
                                     "M" => 1_000_000.0,
                                     "G" => 1_000_000_000.0,
@@ -58,6 +60,12 @@ pub fn number_regex_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer,
                                     "Z" => 1_000_000_000_000_000_000.0,
                                     "Y" => 1_000_000_000_000_000_000_000.0,
                                     _ => 1.0
+                                });
+
+                                /* Too big for the number backend */
+                                match multiplier.and_then(|multiplier| do_number_multiplication(num, multiplier)) {
+                                    Ok(number) => number,
+                                    Err(_) => continue
                                 }
                             },
                             _ => num
@@ -67,7 +75,7 @@ pub fn number_regex_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer,
                 };
             }
 
-            if tokinizer.add_token_location(capture.get(0).unwrap().start(), parse_end, Some(TokenType::Number(number, number_type)), capture.get(0).unwrap().as_str().to_string()) {
+            if tokinizer.add_token_location(capture.get(0).unwrap().start(), parse_end, Some(TokenType::Number(number, number_type)), tokinizer.data[capture.get(0).unwrap().range()].to_string()) {
                 tokinizer.add_uitoken_from_match(number_match, UiTokenType::Number);
                 tokinizer.add_uitoken_from_match(notation_match, UiTokenType::Symbol2);
             }
@@ -95,19 +103,19 @@ fn number_test_1() {
     assert_eq!(tokens.len(), 4);
     assert_eq!(tokens[0].start, 0);
     assert_eq!(tokens[0].end, 4);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(1024.0, NumberType::Decimal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(1024.0), NumberType::Decimal)));
     
     assert_eq!(tokens[1].start, 5);
     assert_eq!(tokens[1].end, 10);
-    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Number(-1024.0, NumberType::Decimal)));
+    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Number(number!(-1024.0), NumberType::Decimal)));
     
     assert_eq!(tokens[2].start, 11);
     assert_eq!(tokens[2].end, 17);
-    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Number(1024.1, NumberType::Decimal)));
+    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Number(number!(1024.1), NumberType::Decimal)));
     
     assert_eq!(tokens[3].start, 18);
     assert_eq!(tokens[3].end, 25);
-    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Number(-1024.1, NumberType::Decimal)));
+    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Number(number!(-1024.1), NumberType::Decimal)));
 }
 
 #[cfg(test)]
@@ -128,27 +136,27 @@ fn number_test_2() {
     assert_eq!(tokens.len(), 6);
     assert_eq!(tokens[0].start, 0);
     assert_eq!(tokens[0].end, 5);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(256.0, NumberType::Hexadecimal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(256.0), NumberType::Hexadecimal)));
     
     assert_eq!(tokens[1].start, 6);
     assert_eq!(tokens[1].end, 11);
-    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Number(256.0, NumberType::Hexadecimal)));
+    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Number(number!(256.0), NumberType::Hexadecimal)));
     
     assert_eq!(tokens[2].start, 12);
     assert_eq!(tokens[2].end, 15);
-    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Number(1.0, NumberType::Hexadecimal)));
+    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Number(number!(1.0), NumberType::Hexadecimal)));
     
     assert_eq!(tokens[3].start, 16);
     assert_eq!(tokens[3].end, 19);
-    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Number(1.0, NumberType::Hexadecimal)));
+    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Number(number!(1.0), NumberType::Hexadecimal)));
     
     assert_eq!(tokens[4].start, 20);
     assert_eq!(tokens[4].end, 23);
-    assert_eq!(tokens[4].token_type.borrow().deref(), &Some(TokenType::Number(0.0, NumberType::Hexadecimal)));
+    assert_eq!(tokens[4].token_type.borrow().deref(), &Some(TokenType::Number(number!(0.0), NumberType::Hexadecimal)));
     
     assert_eq!(tokens[5].start, 24);
     assert_eq!(tokens[5].end, 27);
-    assert_eq!(tokens[5].token_type.borrow().deref(), &Some(TokenType::Number(0.0, NumberType::Hexadecimal)));
+    assert_eq!(tokens[5].token_type.borrow().deref(), &Some(TokenType::Number(number!(0.0), NumberType::Hexadecimal)));
 }
 
 #[cfg(test)]
@@ -169,27 +177,27 @@ fn number_test_3() {
     assert_eq!(tokens.len(), 6);
     assert_eq!(tokens[0].start, 0);
     assert_eq!(tokens[0].end, 5);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(4.0, NumberType::Binary)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(4.0), NumberType::Binary)));
     
     assert_eq!(tokens[1].start, 6);
     assert_eq!(tokens[1].end, 11);
-    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Number(4.0, NumberType::Binary)));
+    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Number(number!(4.0), NumberType::Binary)));
     
     assert_eq!(tokens[2].start, 12);
     assert_eq!(tokens[2].end, 15);
-    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Number(1.0, NumberType::Binary)));
+    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Number(number!(1.0), NumberType::Binary)));
     
     assert_eq!(tokens[3].start, 16);
     assert_eq!(tokens[3].end, 19);
-    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Number(1.0, NumberType::Binary)));
+    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Number(number!(1.0), NumberType::Binary)));
     
     assert_eq!(tokens[4].start, 20);
     assert_eq!(tokens[4].end, 23);
-    assert_eq!(tokens[4].token_type.borrow().deref(), &Some(TokenType::Number(0.0, NumberType::Binary)));
+    assert_eq!(tokens[4].token_type.borrow().deref(), &Some(TokenType::Number(number!(0.0), NumberType::Binary)));
     
     assert_eq!(tokens[5].start, 24);
     assert_eq!(tokens[5].end, 27);
-    assert_eq!(tokens[5].token_type.borrow().deref(), &Some(TokenType::Number(0.0, NumberType::Binary)));
+    assert_eq!(tokens[5].token_type.borrow().deref(), &Some(TokenType::Number(number!(0.0), NumberType::Binary)));
 }
 
 
@@ -211,25 +219,25 @@ fn number_test_4() {
     assert_eq!(tokens.len(), 6);
     assert_eq!(tokens[0].start, 0);
     assert_eq!(tokens[0].end, 5);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(64.0, NumberType::Octal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(64.0), NumberType::Octal)));
     
     assert_eq!(tokens[1].start, 6);
     assert_eq!(tokens[1].end, 11);
-    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Number(64.0, NumberType::Octal)));
+    assert_eq!(tokens[1].token_type.borrow().deref(), &Some(TokenType::Number(number!(64.0), NumberType::Octal)));
     
     assert_eq!(tokens[2].start, 12);
     assert_eq!(tokens[2].end, 15);
-    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Number(1.0, NumberType::Octal)));
+    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Number(number!(1.0), NumberType::Octal)));
     
     assert_eq!(tokens[3].start, 16);
     assert_eq!(tokens[3].end, 19);
-    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Number(1.0, NumberType::Octal)));
+    assert_eq!(tokens[3].token_type.borrow().deref(), &Some(TokenType::Number(number!(1.0), NumberType::Octal)));
     
     assert_eq!(tokens[4].start, 20);
     assert_eq!(tokens[4].end, 23);
-    assert_eq!(tokens[4].token_type.borrow().deref(), &Some(TokenType::Number(0.0, NumberType::Octal)));
+    assert_eq!(tokens[4].token_type.borrow().deref(), &Some(TokenType::Number(number!(0.0), NumberType::Octal)));
     
     assert_eq!(tokens[5].start, 24);
     assert_eq!(tokens[5].end, 27);
-    assert_eq!(tokens[5].token_type.borrow().deref(), &Some(TokenType::Number(0.0, NumberType::Octal)));
+    assert_eq!(tokens[5].token_type.borrow().deref(), &Some(TokenType::Number(number!(0.0), NumberType::Octal)));
 }
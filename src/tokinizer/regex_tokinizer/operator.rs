@@ -14,7 +14,13 @@ use crate::token::ui_token::UiTokenType;
 pub fn operator_regex_parser(_: &SmartCalcConfig, tokinizer: &mut Tokinizer, group_item: &[Regex]) {
     for re in group_item.iter() {
         for capture in re.captures_iter(&tokinizer.data.to_owned()) {
-            if tokinizer.add_token_from_match(&capture.get(0), Some(TokenType::Operator(capture.get(0).unwrap().as_str().chars().next().unwrap())))  {
+            let operator = match capture.get(0).unwrap().as_str() {
+                "**" => '^',
+                "//" => '\\',
+                operator => operator.chars().next().unwrap()
+            };
+
+            if tokinizer.add_token_from_match(&capture.get(0), Some(TokenType::Operator(operator)))  {
                 tokinizer.add_uitoken_from_match(capture.get(0), UiTokenType::Operator);
             }
         }
@@ -91,4 +97,33 @@ mod tests {
         assert_eq!(tokinizer.token_infos[5].end, 10);
         assert_eq!(tokinizer.token_infos[5].token_type.borrow().deref(), &Some(TokenType::Operator(',')));
     }
-}
\ No newline at end of file
+
+    #[cfg(test)]
+    #[test]
+    fn operator_test_3() {
+        use core::ops::Deref;
+        use crate::tokinizer::regex_tokinizer;
+        use crate::tokinizer::test::setup_tokinizer;
+        use alloc::string::ToString;
+        use crate::config::SmartCalcConfig;
+        use crate::session::Session;
+        let mut session = Session::new();
+        let config = SmartCalcConfig::default();
+        let mut tokinizer = setup_tokinizer("** // ^".to_string(), &mut session, &config);
+
+        regex_tokinizer(&mut tokinizer);
+
+        assert_eq!(tokinizer.token_infos.len(), 3);
+        assert_eq!(tokinizer.token_infos[0].start, 0);
+        assert_eq!(tokinizer.token_infos[0].end, 2);
+        assert_eq!(tokinizer.token_infos[0].token_type.borrow().deref(), &Some(TokenType::Operator('^')));
+
+        assert_eq!(tokinizer.token_infos[1].start, 3);
+        assert_eq!(tokinizer.token_infos[1].end, 5);
+        assert_eq!(tokinizer.token_infos[1].token_type.borrow().deref(), &Some(TokenType::Operator('\\')));
+
+        assert_eq!(tokinizer.token_infos[2].start, 6);
+        assert_eq!(tokinizer.token_infos[2].end, 7);
+        assert_eq!(tokinizer.token_infos[2].token_type.borrow().deref(), &Some(TokenType::Operator('^')));
+    }
+}
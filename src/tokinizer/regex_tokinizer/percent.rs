@@ -4,7 +4,7 @@
  * Licensed under the GNU General Public License v2.0.
  */
 
-use alloc::borrow::ToOwned;
+use alloc::string::ToString;
 use crate::config::SmartCalcConfig;
 use crate::types::*;
 use crate::tokinizer::Tokinizer;
@@ -12,9 +12,17 @@ use regex::Regex;
 use crate::token::ui_token::{UiTokenType};
 
 pub fn percent_regex_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer, group_item: &[Regex]) {
+    let locale = tokinizer.session.locale(config);
+    let data = locale.mask_separators(&tokinizer.data);
     for re in group_item.iter() {
-        for capture in re.captures_iter(&tokinizer.data.to_owned()) {
-            if tokinizer.add_token_from_match(&capture.get(0), Some(TokenType::Percent(capture.name("NUMBER").unwrap().as_str().replace(&config.thousand_separator[..], "").replace(&config.decimal_seperator[..], ".").parse::<f64>().unwrap()))) {
+        for capture in re.captures_iter(&data) {
+            let number = match locale.normalize_number(&tokinizer.data[capture.name("NUMBER").unwrap().range()]).parse::<f64>() {
+                Ok(number) => number,
+                _ => continue
+            };
+
+            let percent = capture.get(0).unwrap();
+            if tokinizer.add_token_location(percent.start(), percent.end(), Some(TokenType::Percent(number)), tokinizer.data[percent.range()].to_string()) {
                 tokinizer.add_uitoken_from_match(capture.name("NUMBER"), UiTokenType::Number);
                 tokinizer.add_uitoken_from_match(capture.name("PERCENT"), UiTokenType::Symbol2);
             }
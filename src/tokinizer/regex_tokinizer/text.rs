@@ -4,6 +4,7 @@
  * Licensed under the GNU General Public License v2.0.
  */
 
+use alloc::format;
 use alloc::string::ToString;
 use alloc::borrow::ToOwned;
 use chrono::{Duration, Utc};
@@ -14,22 +15,48 @@ use crate::token::ui_token::{UiTokenType};
 use regex::{Regex};
 use crate::constants::ConstantType;
 
+fn constant_token(config: &SmartCalcConfig, constant: &ConstantType) -> Option<TokenType> {
+    match constant {
+        ConstantType::Today     => Some(TokenType::Date(Utc::today().naive_utc(), config.get_time_offset())),
+        ConstantType::Tomorrow  => Some(TokenType::Date(Utc::today().naive_utc() + Duration::days(1), config.get_time_offset())),
+        ConstantType::Yesterday => Some(TokenType::Date(Utc::today().naive_utc() + Duration::days(-1), config.get_time_offset())),
+        ConstantType::Now       => Some(TokenType::Time(Utc::now().naive_utc(), config.get_time_offset())),
+        _ => None
+    }
+}
+
+/* Words are letters only, constants like "aujourd'hui" are found as a whole before the words */
+fn apostrophe_constant_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer) {
+    let constants = match config.constant_pair.get(&tokinizer.language) {
+        Some(constants) => constants,
+        None => return
+    };
+
+    let data = tokinizer.data.to_owned();
+    for (name, constant) in constants.iter().filter(|(name, _)| !name.chars().all(char::is_alphabetic)) {
+        let re = match Regex::new(&format!(r"(?i)\b{}\b", regex::escape(name))) {
+            Ok(re) => re,
+            Err(_) => continue
+        };
+
+        for found in re.find_iter(&data) {
+            if tokinizer.add_token_from_match(&Some(found), constant_token(config, constant)) {
+                tokinizer.add_uitoken_from_match(Some(found), UiTokenType::DateTime);
+            }
+        }
+    }
+}
+
 pub fn text_regex_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer, group_item: &[Regex]) {
+    apostrophe_constant_parser(config, tokinizer);
+
     for re in group_item.iter() {
         for capture in re.captures_iter(&tokinizer.data.to_owned()) {
             let text = capture.name("TEXT").unwrap().as_str();
             if !text.trim().is_empty() {
 
-                if let Some(constant) = config.constant_pair.get(&tokinizer.language).unwrap().get(&text.to_string()) {
-
-                    let token = match constant {
-                        ConstantType::Today     => Some(TokenType::Date(Utc::today().naive_utc(), config.get_time_offset())),
-                        ConstantType::Tomorrow  => Some(TokenType::Date(Utc::today().naive_utc() + Duration::days(1), config.get_time_offset())),
-                        ConstantType::Yesterday => Some(TokenType::Date(Utc::today().naive_utc() + Duration::days(-1), config.get_time_offset())),
-                        ConstantType::Now       => Some(TokenType::Time(Utc::now().naive_utc(), config.get_time_offset())),
-                        _ => None
-                    };
-
+                if let Some(constant) = config.constant_pair.get(&tokinizer.language).unwrap().get(&text.to_lowercase()) {
+                    let token = constant_token(config, constant);
                     if token.is_some() && tokinizer.add_token_from_match(&capture.get(0), token) {
                         tokinizer.add_uitoken_from_match(capture.get(0), UiTokenType::DateTime);
                     }
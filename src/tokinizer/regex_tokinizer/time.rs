@@ -49,7 +49,11 @@ pub fn time_regex_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer, gr
             };
             
             let date = Utc::today().naive_utc();
-            let datetime = FixedOffset::east(time_offset.offset * 60).ymd(date.year(), date.month(), date.day()).and_hms(hour as u32, minute as u32, second as u32);
+            let offset = match date.and_hms_opt(hour as u32, minute as u32, second as u32) {
+                Some(local) => time_offset.offset_for_local(&local),
+                None => time_offset.offset
+            };
+            let datetime = FixedOffset::east(offset * 60).ymd(date.year(), date.month(), date.day()).and_hms(hour as u32, minute as u32, second as u32);
             let date_as_utc = Utc.from_utc_datetime(&datetime.naive_utc()).naive_utc();
             
             if tokinizer.add_token_location(capture.get(0).unwrap().start(), end_position, Some(TokenType::Time(date_as_utc, time_offset)), capture.get(0).unwrap().as_str().to_string()) {
@@ -5,16 +5,87 @@
  */
 
 use alloc::borrow::ToOwned;
+use alloc::string::ToString;
+use alloc::string::String;
+use alloc::vec::Vec;
 use crate::config::SmartCalcConfig;
 use crate::types::*;
 use crate::tokinizer::Tokinizer;
 use regex::Regex;
 use crate::token::ui_token::{UiTokenType};
-use crate::tools::parse_timezone;
+use crate::tools::{current_timezone_offset, find_timezone, parse_timezone, IANA_TIMEZONES};
+
+/* Longest IANA name or city starting at the given word, city names may have up to three words ("Port of Spain") */
+fn find_timezone_name(words: &[(usize, &str)]) -> Option<usize> {
+    (1..=words.len().min(3)).rev().find(|count| IANA_TIMEZONES.contains_key(&words[..*count].iter().map(|(_, word)| word.to_lowercase()).collect::<Vec<_>>().join("_")))
+}
+
+fn strip_last_word(text: &str, accept: impl Fn(&str) -> bool) -> &str {
+    match text.rsplit_once(' ') {
+        Some((rest, word)) if accept(word) => rest.trim_end(),
+        None if accept(text) => "",
+        _ => text
+    }
+}
+
+/* Text without the timezone at its end, an IANA name, a city ("New York") or an abbreviation */
+fn strip_last_timezone<'a>(config: &SmartCalcConfig, text: &'a str) -> &'a str {
+    let words = text.split(' ').filter(|word| !word.is_empty()).collect::<Vec<_>>();
+    if words.last().is_some_and(|word| config.timezones.contains_key(&word.to_uppercase())) {
+        return strip_last_word(text, |_| true);
+    }
+
+    match (1..=words.len().min(3)).rev().find(|count| find_timezone(&words[words.len() - count..].join(" ")).is_some()) {
+        Some(count) => (0..count).fold(text, |text, _| strip_last_word(text, |_| true)),
+        None => text
+    }
+}
+
+/* City names are common words, only accept them after a time, optionally with its timezone and a conversion word ("3pm Berlin", "3pm Istanbul to New York") */
+fn timezone_expected(config: &SmartCalcConfig, tokinizer: &Tokinizer, text: &str, start: usize) -> bool {
+    let conversion_words = config.word_group.get(&tokinizer.language).and_then(|groups| groups.get("conversion_group"));
+    let before = strip_last_word(text[..start].trim_end(), |word| conversion_words.is_some_and(|words| words.iter().any(|item| item.eq_ignore_ascii_case(word))));
+    let before = strip_last_timezone(config, before);
+
+    config.token_parse_regex.get("time").into_iter().flatten().any(|re| re.find_iter(before).any(|time| time.end() == before.len()))
+}
 
 pub fn timezone_regex_parser(config: &SmartCalcConfig, tokinizer: &mut Tokinizer, group_item: &[Regex]) {
+    let text: String = tokinizer.data.to_owned();
+    let words = text.split(' ').scan(0, |position, word| {
+        let start = *position;
+        *position += word.len() + 1;
+        Some((start, word))
+    }).filter(|(_, word)| !word.is_empty()).collect::<Vec<_>>();
+
+    let mut index = 0;
+    while index < words.len() {
+        let count = match find_timezone_name(&words[index..]) {
+            Some(count) => count,
+            None => {
+                index += 1;
+                continue;
+            }
+        };
+
+        let (start, _) = words[index];
+        let (last_start, last_word) = words[index + count - 1];
+        let end = last_start + last_word.len();
+        index += count;
+
+        if !text[start..end].contains('/') && !timezone_expected(config, tokinizer, &text, start) {
+            continue;
+        }
+
+        if let Some(timezone) = find_timezone(&text[start..end]) {
+            if tokinizer.add_token_location(start, end, Some(TokenType::Timezone(timezone.name().to_string(), current_timezone_offset(&timezone))), text[start..end].to_string()) {
+                tokinizer.ui_tokens.add_from_location(start, end, UiTokenType::Symbol1);
+            }
+        }
+    }
+
     for re in group_item.iter() {
-        for capture in re.captures_iter(&tokinizer.data.to_owned().to_uppercase()) {
+        for capture in re.captures_iter(&text.to_uppercase()) {
             if let Some((timezone, offset)) = parse_timezone(config, &capture) {
                 if tokinizer.add_token_from_match(&capture.get(0), Some(TokenType::Timezone(timezone, offset))) {
                     tokinizer.add_uitoken_from_match(capture.name("timezone"), UiTokenType::Symbol1);
@@ -102,4 +173,20 @@ mod test {
         assert_eq!(tokens[8].end, 61);
         assert_eq!(tokens[8].token_type.borrow().deref(), &Some(TokenType::Timezone("GMT1".to_string(), 60)));
     }
+
+    #[test]
+    fn timezone_test_3() {
+        let mut session = Session::new();
+        session.set_language("en".to_string());
+        let config = SmartCalcConfig::default();
+        let mut tokinizer_mut = setup_tokinizer("3pm europe/berlin to New York davis".to_string(), &mut session, &config);
+
+        regex_tokinizer(&mut tokinizer_mut);
+        let timezones = tokinizer_mut.token_infos.iter().filter_map(|token| match token.token_type.borrow().deref() {
+            Some(TokenType::Timezone(name, _)) => Some((token.start, token.end, name.to_string())),
+            _ => None
+        }).collect::<alloc::vec::Vec<_>>();
+
+        assert_eq!(timezones, alloc::vec![(4, 17, "Europe/Berlin".to_string()), (21, 29, "America/New_York".to_string())]);
+    }
 }
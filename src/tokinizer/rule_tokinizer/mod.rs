@@ -18,7 +18,9 @@ use core::cell::RefCell;
 
 use crate::RuleTrait;
 use crate::UiTokenType;
-use crate::types::TokenType;
+use crate::types::{TokenType, PatternToken};
+use crate::explain::ExplainStep;
+use crate::formatter::format_token;
 use crate::types::{ExpressionFunc};
 use crate::tokinizer::{TokenInfo};
 
@@ -33,16 +35,17 @@ use self::rules::dynamic_type_rules::*;
 
 use super::TokenInfoStatus;
 use super::Tokinizer;
+use crate::shared::Shared;
 
 pub enum RuleType {
     Internal { 
         function_name: String,
         function: ExpressionFunc,
-        tokens_list: Vec<Vec<Rc<TokenInfo>>>
+        tokens_list: Vec<Vec<PatternToken>>
     },
     API {
-        tokens_list: Vec<Vec<Rc<TokenInfo>>>, 
-        rule: Rc<dyn RuleTrait>
+        tokens_list: Vec<Vec<PatternToken>>, 
+        rule: Shared<dyn RuleTrait>
     }
 }
 
@@ -51,6 +54,7 @@ pub type RuleItemList = Vec<RuleType>;
 lazy_static! {
         pub static ref RULE_FUNCTIONS: BTreeMap<String, ExpressionFunc> = {
         let mut m = BTreeMap::new();
+        m.insert("small_date".to_string(),         small_date as ExpressionFunc);
         m.insert("percent_calculator".to_string(), percent_calculator as ExpressionFunc);
         m.insert("convert_timezone".to_string(),   convert_timezone as ExpressionFunc);
         m.insert("time_with_timezone".to_string(), time_with_timezone as ExpressionFunc);
@@ -82,7 +86,7 @@ lazy_static! {
     };
 }
 
-fn find_match(name: &String, rule_tokens: &Vec<Rc<TokenInfo>>, tokinizer: &Tokinizer) -> (usize, usize, usize, usize, BTreeMap<String, Rc<TokenInfo>>) {
+fn find_match(name: &String, rule_tokens: &[PatternToken], tokinizer: &Tokinizer) -> (usize, usize, usize, usize, BTreeMap<String, Rc<TokenInfo>>) {
     let total_rule_token       = rule_tokens.len();
     let mut rule_token_index   = 0;
     let mut target_token_index = 0;
@@ -98,9 +102,9 @@ fn find_match(name: &String, rule_tokens: &Vec<Rc<TokenInfo>>, tokinizer: &Tokin
         if let Some(token_type) = &token.token_type.borrow().deref() {
 
             if let TokenType::Variable(variable) = &token_type {
-                let is_same = TokenType::variable_compare(&rule_tokens[rule_token_index], variable.data.borrow().clone());
+                let is_same = rule_tokens[rule_token_index].matches_variable(variable.data.borrow().clone());
                 if is_same {
-                    match TokenType::get_field_name(&rule_tokens[rule_token_index]) {
+                    match rule_tokens[rule_token_index].field_name() {
                         Some(field_name) => fields.insert(field_name.to_string(), token.clone()),
                         None => None
                     };
@@ -111,21 +115,21 @@ fn find_match(name: &String, rule_tokens: &Vec<Rc<TokenInfo>>, tokinizer: &Tokin
                     start_token_index   = target_token_index;
                 }
             }
-            else if token == &rule_tokens[rule_token_index] {
-                match TokenType::get_field_name(&rule_tokens[rule_token_index]) {
+            else if rule_tokens[rule_token_index].matches(token) {
+                match rule_tokens[rule_token_index].field_name() {
                     Some(field_name) => fields.insert(field_name.to_string(), token.clone()),
                     None => None
                 };
 
                 if cfg!(feature="debug-rules") {
-                    log::debug!("Ok, {:?} == {:?}", token.token_type, &rule_tokens[rule_token_index].token_type);
+                    log::debug!("Ok, {:?} == {:?}", token.token_type, &rule_tokens[rule_token_index]);
                 }
 
                 rule_token_index   += 1;
             }
             else {
                 if cfg!(feature="debug-rules") {
-                    log::debug!("No, {:?} == {:?}", token.token_type, &rule_tokens[rule_token_index].token_type);
+                    log::debug!("No, {:?} == {:?}", token.token_type, &rule_tokens[rule_token_index]);
                 }
                 rule_token_index    = 0;
                 start_token_index   = target_token_index;
@@ -141,8 +145,16 @@ fn find_match(name: &String, rule_tokens: &Vec<Rc<TokenInfo>>, tokinizer: &Tokin
     (total_rule_token, rule_token_index, start_token_index, target_token_index, fields)
 }
 
+fn trace_rule(tokinizer: &Tokinizer, name: &str, fields: &BTreeMap<String, Rc<TokenInfo>>, token: &TokenType) {
+    tokinizer.session.trace(|| ExplainStep::Rule {
+        name: name.to_string(),
+        fields: fields.iter().map(|(key, value)| (key.to_string(), value.token_type.borrow().as_ref().map(|token| format_token(tokinizer.config, tokinizer.session, token)).unwrap_or_default())).collect(),
+        result: format_token(tokinizer.config, tokinizer.session, token)
+    });
+}
+
 pub fn rule_tokinizer(tokinizer: &mut Tokinizer) {    
-    if let Some(language) = tokinizer.config.rule.get(&tokinizer.language) {
+    if let Some(language) = tokinizer.config.get_rules(&tokinizer.language) {
 
         let mut execute_rules = true;
         while execute_rules {
@@ -167,6 +179,8 @@ pub fn rule_tokinizer(tokinizer: &mut Tokinizer) {
                                         if cfg!(feature="debug-rules") {
                                             log::debug!("Rule function success with new token: {:?}", token);
                                         }
+
+                                        trace_rule(tokinizer, function_name, &fields, &token);
         
                                         let text_start_position = tokinizer.token_infos[start_token_index].start;
                                         let text_end_position   = tokinizer.token_infos[target_token_index - 1].end;
@@ -189,7 +203,15 @@ pub fn rule_tokinizer(tokinizer: &mut Tokinizer) {
                                         }));
                                         break;
                                     },
-                                    Err(error) => log::info!("Rule execution error, {}", error)
+                                    Err(error) => {
+                                        log::info!("Rule execution error, {}", error);
+
+                                        /* Other rules may still match, the error is only reported if the line can not be calculated */
+                                        if tokinizer.rule_error.is_none() {
+                                            let span = tokinizer.char_span(tokinizer.token_infos[start_token_index].start, tokinizer.token_infos[target_token_index - 1].end);
+                                            tokinizer.rule_error = Some(error.with_span(span));
+                                        }
+                                    }
                                 }
                             }
                         }
@@ -204,6 +226,7 @@ pub fn rule_tokinizer(tokinizer: &mut Tokinizer) {
                                 let simple_fields = fields.iter().map(|(key, value)| (key.to_string(), value.token_type.borrow().as_ref().unwrap().clone())).collect::<BTreeMap<_, _>>();
                                 if let Some(token) = rule.call(tokinizer.config, &simple_fields) {
                                     log::debug!("Rule function success with new token: {:?}", rule.name());
+                                    trace_rule(tokinizer, &rule.name(), &fields, &token);
                                     
                                     let text_start_position = tokinizer.token_infos[start_token_index].start;
                                     let text_end_position   = tokinizer.token_infos[target_token_index - 1].end;
@@ -14,8 +14,9 @@ use crate::config::SmartCalcConfig;
 use crate::{tokinizer::Tokinizer, types::{TokenType}};
 use crate::tokinizer::{TokenInfo};
 use crate::{types::{SmartCalcAstType}};
+use crate::error::SmartCalcError;
 
-pub fn division_cleanup(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn division_cleanup(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if (fields.contains_key("data")) && fields.contains_key("text") {
         return match &fields.get(&"data".to_string()).unwrap().token_type.borrow().deref()  {
             Some(token) => match &token {
@@ -25,15 +26,15 @@ pub fn division_cleanup(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<St
                 TokenType::Variable(variable) => {
                     match variable.data.borrow().deref().deref() {
                         SmartCalcAstType::Item(item) => Ok(item.as_token_type()),
-                        _ => Err("Data type not valid".to_string())
+                        _ => Err(SmartCalcError::invalid_argument("Data type not valid"))
                     }
                 },
-                _ => Err("Data type not valid".to_string())
+                _ => Err(SmartCalcError::invalid_argument("Data type not valid"))
             },
-            _ => Err("Data type not valid".to_string())
+            _ => Err(SmartCalcError::invalid_argument("Data type not valid"))
         }
     }
-    Err("Data type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Data type not valid"))
 }
 
 
@@ -49,7 +50,7 @@ fn cleanup_rules() {
     let conf = SmartCalcConfig::default();
     assert_eq!(tokens.len(), 3);
     
-    assert_eq!(*tokens[0], TokenType::Money(25.0, conf.get_currency("usd".to_string()).unwrap()));
+    assert_eq!(*tokens[0], TokenType::Money(number!(25.0), conf.get_currency("usd".to_string()).unwrap()));
     assert_eq!(*tokens[1], TokenType::Operator('*'));
     assert_eq!(*tokens[2], TokenType::Duration(Duration::hours(14)));
 }
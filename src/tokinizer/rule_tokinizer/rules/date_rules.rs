@@ -4,71 +4,118 @@
  * Licensed under the GNU General Public License v2.0.
  */
 
+use alloc::format;
 use alloc::rc::Rc;
 use alloc::string::String;
-use alloc::string::ToString;
+use alloc::vec::Vec;
 use alloc::collections::btree_map::BTreeMap;
 
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use chrono::{NaiveDate, Datelike};
 use chrono::Timelike;
 
+use core::ops::Deref;
+
 use crate::config::SmartCalcConfig;
+use crate::locale::DateOrder;
+use crate::tools::number_to_f64;
 use crate::tokinizer::get_date;
 use crate::tokinizer::get_number;
 use crate::tokinizer::get_number_or_month;
 use crate::tokinizer::get_number_or_time;
+use crate::tokinizer::get_time;
+use crate::tokinizer::get_timezone;
+use crate::types::TimeOffset;
 use crate::{tokinizer::Tokinizer, types::TokenType};
 use crate::tokinizer::{TokenInfo};
+use crate::error::SmartCalcError;
+
+/* Numbers of a date like "3/12/2021", in the order they are written */
+fn numeric_date_parts(fields: &BTreeMap<String, Rc<TokenInfo>>) -> Option<[f64; 3]> {
+    let mut parts = Vec::new();
+    for name in ["day", "month", "year"] {
+        let field = fields.get(name)?;
+        match field.token_type.borrow().deref() {
+            Some(TokenType::Number(number, _)) => parts.push((field.start, number_to_f64(*number))),
+            _ => return None
+        };
+    }
+
+    parts.sort_by_key(|(start, _)| *start);
+    Some([parts[0].1, parts[1].1, parts[2].1])
+}
 
-pub fn small_date(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn small_date(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if (fields.contains_key("day")) && fields.contains_key("month") {
         let day = match get_number("day", fields) {
-            Some(number) => number,
-            _ => return Err("Number information not valid".to_string())
+            Some(number) => number_to_f64(number),
+            _ => return Err(SmartCalcError::invalid_argument("Number information not valid"))
         };
 
         let month = match get_number_or_month("month", fields) {
             Some(number) => number,
-            _ => return Err("Month information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Month information not valid"))
         };
 
         let year = match get_number("year", fields) {
-            Some(number) => number as i32,
+            Some(number) => number_to_f64(number) as i32,
             _ => Utc::now().date().year() as i32
         };
 
+        /* The date order of the session locale wins over the order of the rule */
+        let (day, month, year) = match (tokinizer.session.get_locale().and_then(|locale| locale.date_order), numeric_date_parts(fields)) {
+            (Some(DateOrder::DayMonthYear), Some([first, second, third])) => (first, second as u32, third as i32),
+            (Some(DateOrder::MonthDayYear), Some([first, second, third])) => (second, first as u32, third as i32),
+            (Some(DateOrder::YearMonthDay), Some([first, second, third])) => (third, second as u32, first as i32),
+            _ => (day, month, year)
+        };
+
         return match NaiveDate::from_ymd_opt(year, month, day as u32) {
             Some(date) => {
                 Ok(TokenType::Date(date, config.get_time_offset()))
             },
-            None => Err("Date is not valid".to_string())
+            None => Err(SmartCalcError::invalid_argument("Date is not valid"))
         };
     }
-    Err("Date type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Date type not valid"))
 }
 
-pub fn at_date(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn at_date(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if (fields.contains_key("source")) && fields.contains_key("time") {
         let (date, date_tz) = match get_date("source", fields) {
             Some(number) => number,
-            _ => return Err("Date information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Date information not valid"))
         };
         
+        /* Times keep UTC, their wall clock time is placed on the date in the given timezone ("1 jul 2021 12:00 Europe/Berlin") */
+        if let Some((time, time_offset)) = get_time("time", fields) {
+            let local = date.and_time((time + Duration::minutes(time_offset.offset_at(&time) as i64)).time());
+            let timezone = match get_timezone("timezone", fields) {
+                Some((name, offset)) => TimeOffset { name, offset },
+                None => time_offset
+            };
+            let offset = match timezone.checked_offset_for_local(&local) {
+                Some(offset) => offset,
+                None => return Err(SmartCalcError::invalid_argument(format!("{} is skipped by daylight saving time in {}", local.format("%d %b %Y %H:%M"), timezone.name)))
+            };
+            return Ok(TokenType::DateTime(local - Duration::minutes(offset as i64), TimeOffset { offset, ..timezone }));
+        }
+
         //todo: convert timezone informations
         let (time, _) = match get_number_or_time(config, "time", fields) {
             Some(number) => number,
-            _ => return Err("Date information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Date information not valid"))
         };
         return Ok(TokenType::DateTime(date.and_hms(time.hour(), time.minute(), time.second()), date_tz));
     }
-    Err("Date type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Date type not valid"))
 }
 
 
 #[cfg(test)]
 #[test]
 fn small_date_test_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -81,12 +128,13 @@ fn small_date_test_1() {
 #[cfg(test)]
 #[test]
 fn small_date_test_2() {
+    use alloc::string::ToString;
     use crate::{tokinizer::test::get_executed_raw_tokens, types::NumberType};
     
     let tokens = get_executed_raw_tokens("32 january".to_string());
     assert_eq!(tokens.len(), 3);
     
-    assert_eq!(*tokens[0], TokenType::Number(32.0, NumberType::Decimal));
+    assert_eq!(*tokens[0], TokenType::Number(number!(32.0), NumberType::Decimal));
     assert_eq!(*tokens[1], TokenType::Operator('+'));
     assert_eq!(*tokens[2], TokenType::Month(1));
 }
@@ -94,6 +142,7 @@ fn small_date_test_2() {
 #[cfg(test)]
 #[test]
 fn small_date_test_3() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -107,6 +156,7 @@ fn small_date_test_3() {
 #[cfg(test)]
 #[test]
 fn small_date_test_4() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -6,10 +6,8 @@
 
 use alloc::rc::Rc;
 use alloc::string::String;
-use alloc::string::ToString;
-use chrono::FixedOffset;
+use chrono::Duration;
 use chrono::NaiveDateTime;
-use chrono::TimeZone;
 
 use alloc::collections::btree_map::BTreeMap;
 
@@ -23,32 +21,32 @@ use crate::types::NumberType;
 use crate::types::TimeOffset;
 use crate::{tokinizer::Tokinizer, types::{TokenType}};
 use crate::tokinizer::{TokenInfo};
+use crate::tools::{number_from_i64, number_to_f64};
+use crate::error::SmartCalcError;
 
-pub fn time_with_timezone(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn time_with_timezone(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("time") && fields.contains_key("timezone") {
         
         let (time, current_offset) = get_time("time", fields).unwrap();
         let (target_timezone, target_offset) = get_timezone("timezone", fields).unwrap();
 
         // To source timezone
-        let timezone_offset = FixedOffset::east(current_offset.offset * 60);
-        let date_with_timezone = timezone_offset.from_utc_datetime(&time);
-        let new_time = chrono::Local.from_local_datetime(&date_with_timezone.naive_local()).unwrap().naive_local();
+        let local_time = time + Duration::minutes(current_offset.offset_at(&time) as i64);
 
-        // To target timezone
-        let timezone_offset = FixedOffset::east(target_offset * 60);
-        let date_with_timezone = timezone_offset.from_local_datetime(&new_time).unwrap();
-        let new_time = chrono::Utc.from_utc_datetime(&date_with_timezone.naive_utc()).naive_utc();
-
-        return Ok(TokenType::Time(new_time, TimeOffset { 
-            name: target_timezone.to_uppercase(),
+        // To target timezone, with the offset valid for that day
+        let mut timezone = TimeOffset { 
+            name: target_timezone,
             offset: target_offset
-        }));
+        };
+        timezone.offset = timezone.offset_for_local(&local_time);
+        let new_time = local_time - Duration::minutes(timezone.offset as i64);
+
+        return Ok(TokenType::Time(new_time, timezone));
     }
-    Err("Timezone or time informations not found".to_string())
+    Err(SmartCalcError::invalid_argument("Timezone or time informations not found"))
 }
 
-pub fn to_unixtime(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn to_unixtime(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("data") {
         let timestamp = match get_time("data", fields) {
             Some((time, _)) => time.timestamp(),
@@ -61,45 +59,49 @@ pub fn to_unixtime(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String,
             }
         };
 
-        return Ok(TokenType::Number(timestamp as f64, NumberType::Raw));
+        return Ok(TokenType::Number(number_from_i64(timestamp), NumberType::Raw));
     }
-    Err("Date with time/date/time information not found".to_string())
+    Err(SmartCalcError::invalid_argument("Date with time/date/time information not found"))
 }
 
-pub fn from_unixtime(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn from_unixtime(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("number") {
-        let timestamp = get_number("number", fields).unwrap();
+        let timestamp = number_to_f64(get_number("number", fields).unwrap());
         let date = NaiveDateTime::from_timestamp(timestamp as i64, 0);
         
         return match get_timezone("timezone", fields) {
-            Some((target_timezone, target_offset)) => Ok(TokenType::DateTime(date, TimeOffset { 
-                name: target_timezone.to_uppercase(),
-                offset: target_offset
-            })),
+            Some((target_timezone, target_offset)) => {
+                let timezone = TimeOffset { 
+                    name: target_timezone,
+                    offset: target_offset
+                };
+                Ok(TokenType::DateTime(date, TimeOffset { offset: timezone.offset_at(&date), ..timezone }))
+            },
             None => Ok(TokenType::DateTime(date, config.get_time_offset()))
         };
     }
-    Err("Date with time/date/time information not found".to_string())
+    Err(SmartCalcError::invalid_argument("Date with time/date/time information not found"))
 }
 
-pub fn convert_timezone(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn convert_timezone(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("time") && fields.contains_key("timezone") {
         let (target_timezone, target_offset) = get_timezone("timezone", fields).unwrap();
         let offset = TimeOffset { 
-            name: target_timezone.to_uppercase(),
+            name: target_timezone,
             offset: target_offset
         };
         
+        /* The offset of an IANA timezone depends on the converted date */
         return match get_time("time", fields) {
-            Some((time, _)) => Ok(TokenType::Time(time, offset)),
+            Some((time, _)) => Ok(TokenType::Time(time, TimeOffset { offset: offset.offset_at(&time), ..offset })),
             None => match get_date("time", fields) {
-                Some((date, _)) => Ok(TokenType::Date(date, offset)),
+                Some((date, _)) => Ok(TokenType::Date(date, TimeOffset { offset: offset.offset_at(&date.and_hms_opt(0, 0, 0).unwrap_or_default()), ..offset })),
                 None => match get_date_time("time", fields) {
-                    Some((date_time, _)) => Ok(TokenType::DateTime(date_time, offset)),
-                    None => Err("Timezone or time informations not found".to_string())
+                    Some((date_time, _)) => Ok(TokenType::DateTime(date_time, TimeOffset { offset: offset.offset_at(&date_time), ..offset })),
+                    None => Err(SmartCalcError::invalid_argument("Timezone or time informations not found"))
                 }
             }
         };
     }
-    Err("Timezone or time informations not found".to_string())
+    Err(SmartCalcError::invalid_argument("Timezone or time informations not found"))
 }
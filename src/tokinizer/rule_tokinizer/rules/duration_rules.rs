@@ -6,7 +6,6 @@
 
 use alloc::rc::Rc;
 use alloc::string::String;
-use alloc::string::ToString;
 use alloc::collections::btree_map::BTreeMap;
 use core::ops::Deref;
 
@@ -21,22 +20,24 @@ use crate::tokinizer::get_time;
 use crate::{constants::ConstantType, tokinizer::Tokinizer, types::TokenType};
 use crate::tokinizer::TokenInfo;
 use crate::formatter::{MINUTE, HOUR, DAY, WEEK, MONTH, YEAR};
+use crate::tools::number_to_f64;
+use crate::error::SmartCalcError;
 
-pub fn duration_parse(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn duration_parse(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if (fields.contains_key("duration")) && fields.contains_key("type") {
         let duration = match get_number("duration", fields) {
-            Some(number) => number as i64,
-            _ => return Err("Duration information not valid".to_string())
+            Some(number) => number_to_f64(number) as i64,
+            _ => return Err(SmartCalcError::invalid_argument("Duration information not valid"))
         };
 
         let duration_type = match get_text("type", fields) {
             Some(number) => number,
-            _ => return Err("Duration type information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Duration type information not valid"))
         };
 
-        let constant_type = match config.constant_pair.get(&tokinizer.language).unwrap().get(&duration_type) {
+        let constant_type = match config.constant_pair.get(&tokinizer.language).unwrap().get(&duration_type.to_lowercase()) {
             Some(constant) => constant.clone(),
-            None => return Err("Duration type not valid".to_string())
+            None => return Err(SmartCalcError::invalid_argument("Duration type not valid"))
         };
 
         let calculated_duration = match constant_type {
@@ -58,22 +59,22 @@ pub fn duration_parse(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &
             ConstantType::Hour => Duration::hours(duration),
             ConstantType::Minute => Duration::minutes(duration),
             ConstantType::Second => Duration::seconds(duration),            
-            _ => return Err("Duration type not valid".to_string()) 
+            _ => return Err(SmartCalcError::invalid_argument("Duration type not valid")) 
         };
 
         return Ok(TokenType::Duration(calculated_duration));
     }
-    Err("Date type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Date type not valid"))
 }
 
-pub fn combine_durations(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn combine_durations(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if (fields.contains_key("1")) && fields.contains_key("2") {
         let mut sum_duration = Duration::zero();
 
         for key in fields.keys() {
             let duration = match get_duration(key, fields) {
                 Some(duration) => duration,
-                _ => return Err("Duration information not valid".to_string())
+                _ => return Err(SmartCalcError::invalid_argument("Duration information not valid"))
             };
 
             sum_duration = sum_duration + duration;
@@ -81,19 +82,19 @@ pub fn combine_durations(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<S
 
         return Ok(TokenType::Duration(sum_duration));
     }
-    Err("Date type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Date type not valid"))
 }
 
-pub fn as_duration(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn as_duration(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if (fields.contains_key("source")) && fields.contains_key("type") {
         let duration_type = match get_text("type", fields) {
             Some(number) => number,
-            _ => return Err("Duration type information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Duration type information not valid"))
         };
 
-        let constant_type = match config.constant_pair.get(&tokinizer.language).unwrap().get(&duration_type) {
+        let constant_type = match config.constant_pair.get(&tokinizer.language).unwrap().get(&duration_type.to_lowercase()) {
             Some(constant) => constant.clone(),
-            None => return Err("Duration type not valid".to_string())
+            None => return Err(SmartCalcError::invalid_argument("Duration type not valid"))
         };
 
         match fields.get("source") {
@@ -107,7 +108,7 @@ pub fn as_duration(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &BTr
                         ConstantType::Minute => Ok(TokenType::Duration(Duration::minutes(seconds / MINUTE as i64))),
                         ConstantType::Hour => Ok(TokenType::Duration(Duration::hours(seconds / HOUR as i64))),
                         ConstantType::Week => Ok(TokenType::Duration(Duration::weeks(seconds / WEEK as i64))),
-                        _ => return Err("Duration type not valid".to_string()) 
+                        _ => return Err(SmartCalcError::invalid_argument("Duration type not valid")) 
                     };
                 },
                 Some(TokenType::Time(time, _)) => {
@@ -122,18 +123,18 @@ pub fn as_duration(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &BTr
                         ConstantType::Hour => Ok(TokenType::Duration(Duration::hours(seconds / HOUR as i64))),
                         ConstantType::Week => Ok(TokenType::Duration(Duration::weeks(seconds / WEEK as i64))),
 
-                        _ => return Err("Duration type not valid".to_string()) 
+                        _ => return Err(SmartCalcError::invalid_argument("Duration type not valid")) 
                     };
                 }
                 _ => ()
             },
-            None => return Err("Source information not valid".to_string())
+            None => return Err(SmartCalcError::invalid_argument("Source information not valid"))
         };
         
         
         let duration = match get_number("duration", fields) {
-            Some(number) => number as i64,
-            _ => return Err("Duration information not valid".to_string())
+            Some(number) => number_to_f64(number) as i64,
+            _ => return Err(SmartCalcError::invalid_argument("Duration information not valid"))
         };
 
         let calculated_duration = match constant_type {
@@ -143,15 +144,15 @@ pub fn as_duration(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &BTr
             ConstantType::Second => Duration::seconds(duration),
             ConstantType::Minute => Duration::minutes(duration),
             ConstantType::Hour => Duration::hours(duration),
-            _ => return Err("Duration type not valid".to_string()) 
+            _ => return Err(SmartCalcError::invalid_argument("Duration type not valid")) 
         };
 
         return Ok(TokenType::Duration(calculated_duration));
     }
-    Err("Date type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Date type not valid"))
 }
 
-pub fn to_duration(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn to_duration(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if (fields.contains_key("source")) && fields.contains_key("target") {
         //todo: calculate with timezone
         if let (Some((source, _)), Some((target, _))) = (get_time("source", fields), get_time("target", fields)) {
@@ -165,16 +166,17 @@ pub fn to_duration(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String,
                 let diff = if target > source { target - source } else { source - target};
                 return Ok(TokenType::Duration(diff));
             },
-            _ => Err("Time information not valid".to_string())
+            _ => Err(SmartCalcError::invalid_argument("Time information not valid"))
         }
     }
 
-    Err("Time diff not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Time diff not valid"))
 }
 
 #[cfg(test)]
 #[test]
 fn duration_parse_test_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -188,6 +190,7 @@ fn duration_parse_test_1() {
 #[cfg(test)]
 #[test]
 fn duration_parse_test_2() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -201,6 +204,7 @@ fn duration_parse_test_2() {
 #[cfg(test)]
 #[test]
 fn duration_parse_test_3() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -214,6 +218,7 @@ fn duration_parse_test_3() {
 #[cfg(test)]
 #[test]
 fn duration_parse_test_4() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -226,6 +231,7 @@ fn duration_parse_test_4() {
 #[cfg(test)]
 #[test]
 fn duration_parse_test_5() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -239,6 +245,7 @@ fn duration_parse_test_5() {
 #[cfg(test)]
 #[test]
 fn duration_parse_test_6() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -252,6 +259,7 @@ fn duration_parse_test_6() {
 #[cfg(test)]
 #[test]
 fn duration_parse_test_7() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -265,6 +273,7 @@ fn duration_parse_test_7() {
 #[cfg(test)]
 #[test]
 fn to_duration_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -277,6 +286,7 @@ fn to_duration_1() {
 #[cfg(test)]
 #[test]
 fn to_duration_2() {
+    use alloc::string::ToString;
     use crate::tokinizer::test::execute;
     
     let tokens = execute("20:45 to 17:30".to_string());
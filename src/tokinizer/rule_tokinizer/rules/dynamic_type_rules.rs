@@ -6,7 +6,6 @@
 
 use alloc::rc::Rc;
 use alloc::string::String;
-use alloc::string::ToString;
 use alloc::collections::btree_map::BTreeMap;
 
 use crate::config::SmartCalcConfig;
@@ -15,16 +14,17 @@ use crate::tokinizer::get_dynamic_type;
 use crate::tokinizer::get_text;
 use crate::{tokinizer::Tokinizer, types::{TokenType}};
 use crate::tokinizer::{TokenInfo};
+use crate::error::SmartCalcError;
 
-pub fn dynamic_type_convert(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn dynamic_type_convert(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("source") && fields.contains_key("type") {
         let target_type = match get_text("type", fields) {
             Some(data) => data,
-            None => return Err("Dynamic type not valid".to_string())
+            None => return Err(SmartCalcError::invalid_argument("Dynamic type not valid"))
         };
         let (number, source_type) = match get_dynamic_type("source", fields) {
             Some(data) => data,
-            None => return Err("Dynamic type not valid".to_string())
+            None => return Err(SmartCalcError::invalid_argument("Dynamic type not valid"))
         };
         
         if let Some((new_number, new_type)) = DynamicTypeItem::convert(config, number, source_type, target_type) {
@@ -32,5 +32,5 @@ pub fn dynamic_type_convert(config: &SmartCalcConfig, _: &Tokinizer, fields: &BT
         };
     }
 
-    Err("Dynamic type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Dynamic type not valid"))
 }
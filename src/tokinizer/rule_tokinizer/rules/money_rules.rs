@@ -4,49 +4,52 @@
  * Licensed under the GNU General Public License v2.0.
  */
 
+use alloc::format;
 use alloc::rc::Rc;
 use alloc::string::String;
-use alloc::string::ToString;
 use alloc::collections::btree_map::BTreeMap;
 
 use crate::config::SmartCalcConfig;
 use crate::tokinizer::get_currency;
+use crate::tokinizer::get_date;
 use crate::tokinizer::get_money;
-use crate::{tokinizer::Tokinizer, types::{TokenType}};
+use crate::{tokinizer::Tokinizer, types::{CurrencyInfo, TokenType}};
 use crate::tokinizer::TokenInfo;
-use crate::tools::do_divition;
+use crate::tools::convert_price;
+use crate::error::{SmartCalcError, Span};
+use crate::shared::Shared;
 
-pub fn convert_money(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn convert_money(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("money") && fields.contains_key("currency") {
         let money = match get_money(config, "money", fields) {
             Some(money) => money,
-            _ => return Err("Money information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Money information not valid"))
         };
 
         let to_currency = match get_currency(config, "currency", fields) {
             Some(to_currency) => to_currency,
-            _ => return Err("Currency information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Currency information not valid"))
         };
 
-        let as_usd = match config.currency_rate.get(&money.get_currency()) {
-            Some(l_rate) => do_divition(money.get_price(), *l_rate),
-            _ => return Err("Currency information not valid".to_string())
-        };
-
-        let calculated_price = match config.currency_rate.get(&to_currency) {
-            Some(r_rate) => as_usd * r_rate,
-            _ => return Err("Currency information not valid".to_string())
+        /* "100 eur to usd on 12 march 2021" uses the rates of that day */
+        let date = get_date("date", fields).map(|(date, _)| date);
+        let rate = |currency: &Shared<CurrencyInfo>| match (config.get_line_currency_rate(tokinizer.session, currency, date), date) {
+            (Some(rate), _) => Ok(rate),
+            (None, Some(date)) => Err(SmartCalcError::invalid_argument(format!("No rate for {} on {}", currency.code, date))),
+            (None, None) => Err(SmartCalcError::UnknownCurrency { currency: currency.code.clone(), span: Span::default() })
         };
 
+        let calculated_price = convert_price(money.get_price(), rate(&money.get_currency())?, rate(&to_currency)?)?;
         return Ok(TokenType::Money(calculated_price, to_currency));
     }
 
-    Err("Money type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Money type not valid"))
 }
 
 #[cfg(test)]
 #[test]
 fn convert_money_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -55,7 +58,7 @@ fn convert_money_1() {
     let tokens = execute("10 usd as try".to_string());
 
     assert_eq!(tokens.len(), 4);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(70.727697572, conf.get_currency("try".to_string()).unwrap())));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(number!(70.727697572), conf.get_currency("try".to_string()).unwrap())));
 
 }
 
@@ -63,6 +66,7 @@ fn convert_money_1() {
 #[cfg(test)]
 #[test]
 fn convert_money_2() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -71,13 +75,14 @@ fn convert_money_2() {
     let tokens = execute("10 usd try".to_string());
 
     assert_eq!(tokens.len(), 3);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(70.727697572, conf.get_currency("try".to_string()).unwrap())));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(number!(70.727697572), conf.get_currency("try".to_string()).unwrap())));
 
 }
 
 #[cfg(test)]
 #[test]
 fn convert_money_3() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -86,7 +91,7 @@ fn convert_money_3() {
     let tokens = execute("10 usd into try".to_string());
 
     assert_eq!(tokens.len(), 4);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(70.727697572, conf.get_currency("try".to_string()).unwrap())));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(number!(70.727697572), conf.get_currency("try".to_string()).unwrap())));
 
 }
 
@@ -94,6 +99,7 @@ fn convert_money_3() {
 #[cfg(test)]
 #[test]
 fn convert_money_4() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -103,7 +109,12 @@ fn convert_money_4() {
 
     assert_eq!(tokens.len(), 5);
     assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Text("salary".to_string())));
-    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Money(134.4772867837901, conf.get_currency("eur".to_string()).unwrap())));
+    #[cfg(not(feature = "decimal"))]
+    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Money(number!(134.4772867837901), conf.get_currency("eur".to_string()).unwrap())));
+
+    /* Decimal rates keep all digits */
+    #[cfg(feature = "decimal")]
+    assert_eq!(tokens[2].token_type.borrow().deref(), &Some(TokenType::Money(number!(134.47728678379009443235185452), conf.get_currency("eur".to_string()).unwrap())));
 
 }
 
@@ -112,6 +123,7 @@ fn convert_money_4() {
 #[cfg(test)]
 #[test]
 fn convert_money_5() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -119,7 +131,7 @@ fn convert_money_5() {
     let conf = SmartCalcConfig::default();
     let tokens = execute("$9 in Euro".to_string());
 
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(7.5106400733, conf.get_currency("eur".to_string()).unwrap())));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(number!(7.5106400733), conf.get_currency("eur".to_string()).unwrap())));
 
 }
 
@@ -127,6 +139,7 @@ fn convert_money_5() {
 #[cfg(test)]
 #[test]
 fn convert_money_6() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -138,13 +151,14 @@ fn convert_money_6() {
     assert_eq!(tokens.len(), 3);
 
     let token = tokens[0].token_type.borrow().deref().clone();
-    assert_eq!(token, Some(TokenType::Money(2_000_000.0, conf.get_currency("eur".to_string()).unwrap())));
+    assert_eq!(token, Some(TokenType::Money(number!(2_000_000.0), conf.get_currency("eur".to_string()).unwrap())));
 }
 
 
 #[cfg(test)]
 #[test]
 fn money_on_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -152,13 +166,14 @@ fn money_on_1() {
     let conf = SmartCalcConfig::default();
     let tokens = execute("6% on 40 EUR".to_string());
 
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(42.4, conf.get_currency("eur".to_string()).unwrap())));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(number!(42.4), conf.get_currency("eur".to_string()).unwrap())));
 }
 
 
 #[cfg(test)]
 #[test]
 fn money_of_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -166,13 +181,14 @@ fn money_of_1() {
     let conf = SmartCalcConfig::default();
     let tokens = execute("6% of 40 EUR".to_string());
 
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(2.4, conf.get_currency("eur".to_string()).unwrap())));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(number!(2.4), conf.get_currency("eur".to_string()).unwrap())));
 }
 
 
 #[cfg(test)]
 #[test]
 fn money_off_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
@@ -180,5 +196,5 @@ fn money_off_1() {
     let conf = SmartCalcConfig::default();
     let tokens = execute("6% off 40 EUR".to_string());
 
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(37.6, conf.get_currency("eur".to_string()).unwrap())));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Money(number!(37.6), conf.get_currency("eur".to_string()).unwrap())));
 }
@@ -6,7 +6,6 @@
 
 use alloc::rc::Rc;
 use alloc::string::String;
-use alloc::string::ToString;
 use alloc::collections::btree_map::BTreeMap;
 
 use crate::config::SmartCalcConfig;
@@ -18,85 +17,91 @@ use crate::tokinizer::get_text;
 use crate::types::NumberType;
 use crate::{tokinizer::Tokinizer, types::{TokenType}};
 use crate::tokinizer::{TokenInfo};
-use crate::tools::do_divition;
+use crate::tools::{do_number_addition, do_number_divition, do_number_multiplication, do_number_subtraction, number_from_f64, number_from_i64};
+use crate::error::SmartCalcError;
+use crate::types::Number;
 
-pub fn number_on(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+fn percent_of(number: Number, percent: f64) -> core::result::Result<Number, SmartCalcError> {
+    do_number_divition(do_number_multiplication(number, number_from_f64(percent)?)?, number_from_i64(100))
+}
+
+pub fn number_on(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("number") && fields.contains_key("p") {
         let number = match get_number_or_price(config, "number", fields) {
             Some(number) => number,
-            _ => return Err("Number information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Number information not valid"))
         };
 
         let percent = match get_percent("p", fields) {
             Some(percent) => percent,
-            _ => return Err("Percent information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Percent information not valid"))
         };
 
-        let calculated_number = number + do_divition(number * percent, 100.0);
+        let calculated_number = do_number_addition(number, percent_of(number, percent)?)?;
         return Ok(match get_currency(config, "number", fields) {
             Some(currency) => TokenType::Money(calculated_number, currency),
             None => TokenType::Number(calculated_number, NumberType::Decimal)
         });
     }
 
-    Err("Number type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Number type not valid"))
 }
 
 
-pub fn number_of(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn number_of(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("number") && fields.contains_key("p") {
         let number = match get_number_or_price(config, "number", fields) {
             Some(number) => number,
-            _ => return Err("Number information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Number information not valid"))
         };
 
         let percent = match get_percent("p", fields) {
             Some(percent) => percent,
-            _ => return Err("Percent information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Percent information not valid"))
         };
 
-        let calculated_number = do_divition(number * percent, 100.0);
+        let calculated_number = percent_of(number, percent)?;
         return Ok(match get_currency(config, "number", fields) {
             Some(currency) => TokenType::Money(calculated_number, currency),
             None => TokenType::Number(calculated_number, NumberType::Decimal)
         });
     }
 
-    Err("Number type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Number type not valid"))
 }
 
 
-pub fn number_off(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn number_off(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("number") && fields.contains_key("p") {
         let number = match get_number_or_price(config, "number", fields) {
             Some(number) => number,
-            _ => return Err("Number information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Number information not valid"))
         };
 
         let percent = match get_percent("p", fields) {
             Some(percent) => percent,
-            _ => return Err("Percent information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Percent information not valid"))
         };
 
-        let calculated_number = number - do_divition(number * percent, 100.0);
+        let calculated_number = do_number_subtraction(number, percent_of(number, percent)?)?;
         return Ok(match get_currency(config, "number", fields) {
             Some(currency) => TokenType::Money(calculated_number, currency),
             None => TokenType::Number(calculated_number, NumberType::Decimal)
         });
     }
 
-    Err("Number type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Number type not valid"))
 }
 
-pub fn number_type_convert(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn number_type_convert(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("number") && fields.contains_key("type") {
         let number = match get_number("number", fields) {
             Some(number) => number.round(),
-            None => return Err("Number type not valid".to_string())
+            None => return Err(SmartCalcError::invalid_argument("Number type not valid"))
         };
         let number_type = match get_text("type", fields) {
             Some(text) => text,
-            None => return Err("Number type not valid".to_string())
+            None => return Err(SmartCalcError::invalid_argument("Number type not valid"))
         };
         
         let number_type = match &number_type[..] {
@@ -104,116 +109,126 @@ pub fn number_type_convert(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap
             "octal"               => NumberType::Octal,
             "binary"              => NumberType::Binary,
             "decimal"             => NumberType::Decimal,
-            _ => return Err("Target number type not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Target number type not valid"))
         };
 
         return Ok(TokenType::Number(number, number_type));
     }
 
-    Err("Number type not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Number type not valid"))
 }
 
 #[cfg(test)]
 #[test]
 fn number_on_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
     let tokens = execute("6% on 40".to_string());
     
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(42.4, NumberType::Decimal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(42.4), NumberType::Decimal)));
 }
 
 
 #[cfg(test)]
 #[test]
 fn number_of_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
     let tokens = execute("6% of 40".to_string());
 
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(2.4, NumberType::Decimal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(2.4), NumberType::Decimal)));
 }
 
 
 #[cfg(test)]
 #[test]
 fn number_off_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
     let tokens = execute("6% off 40".to_string());
 
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(37.6, NumberType::Decimal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(37.6), NumberType::Decimal)));
 }
 
 #[cfg(test)]
 #[test]
 fn number_type_convert_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
 
     let tokens = execute("100 to hex".to_string());
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(100.0, NumberType::Hexadecimal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(100.0), NumberType::Hexadecimal)));
 }
 
 #[cfg(test)]
 #[test]
 fn number_type_convert_2() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
     let tokens = execute("100,0 to hex".to_string());
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(100.0, NumberType::Hexadecimal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(100.0), NumberType::Hexadecimal)));
 }
 
 #[cfg(test)]
 #[test]
 fn number_type_convert_3() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
     let tokens = execute("100,0 to hexadecimal".to_string());
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(100.0, NumberType::Hexadecimal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(100.0), NumberType::Hexadecimal)));
 }
 
 #[cfg(test)]
 #[test]
 fn number_type_convert_4() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
     let tokens = execute("100,0 to octal".to_string());
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(100.0, NumberType::Octal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(100.0), NumberType::Octal)));
 }
 
 #[cfg(test)]
 #[test]
 fn number_type_convert_5() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
     let tokens = execute("100,0 to oct".to_string());
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(100.0, NumberType::Octal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(100.0), NumberType::Octal)));
 }
 
 #[cfg(test)]
 #[test]
 fn number_type_convert_6() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
     let tokens = execute("100,0 to bin".to_string());
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(100.0, NumberType::Binary)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(100.0), NumberType::Binary)));
 }
 
 #[cfg(test)]
 #[test]
 fn number_type_convert_7() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
     let tokens = execute("100,0 to binary".to_string());
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(100.0, NumberType::Binary)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(100.0), NumberType::Binary)));
 }
@@ -6,7 +6,6 @@
 
 use alloc::rc::Rc;
 use alloc::string::String;
-use alloc::string::ToString;
 use alloc::collections::btree_map::BTreeMap;
 use crate::config::SmartCalcConfig;
 use crate::tokinizer::get_currency;
@@ -16,93 +15,98 @@ use crate::tokinizer::get_percent;
 use crate::types::NumberType;
 use crate::{tokinizer::{TokenInfo, Tokinizer}, types::TokenType};
 
-use crate::tools::do_divition;
+use crate::tools::{do_number_divition, do_number_multiplication, number_from_f64, number_from_i64, number_to_f64};
+use crate::error::SmartCalcError;
 
-pub fn percent_calculator(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn percent_calculator(_: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("p") && fields.contains_key("number") {
         let number = match get_number("number", fields) {
             Some(number) => number,
-            _ => return Err("Number information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Number information not valid"))
         };
 
         let percent = match get_percent("p", fields) {
             Some(number) => number,
-            _ => return Err("Percent information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Percent information not valid"))
         };
-        return Ok(TokenType::Number(do_divition(percent * number, 100.0), NumberType::Decimal));
+        return Ok(TokenType::Number(do_number_divition(do_number_multiplication(number_from_f64(percent)?, number)?, number_from_i64(100))?, NumberType::Decimal));
     }
 
-    Err("Percent not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Percent not valid"))
 }
 
-pub fn find_numbers_percent(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn find_numbers_percent(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("part") && fields.contains_key("total") {
         let total = match get_number_or_price(config, "total", fields) {
             Some(number) => number,
-            _ => return Err("Total number information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Total number information not valid"))
         };
 
         let part = match get_number_or_price(config, "part", fields) {
             Some(number) => number,
-            _ => return Err("Part number information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Part number information not valid"))
         };
         
-        return Ok(TokenType::Percent(do_divition(part * 100.0, total)));
+        return Ok(TokenType::Percent(number_to_f64(do_number_divition(do_number_multiplication(part, number_from_i64(100))?, total)?)));
     }
 
-    Err("Find percent not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Find percent not valid"))
 }
 
-pub fn find_total_from_percent(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String> {
+pub fn find_total_from_percent(config: &SmartCalcConfig, _: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError> {
     if fields.contains_key("number_part") && fields.contains_key("percent_part") {
         let number_part = match get_number_or_price(config, "number_part", fields) {
             Some(number) => number,
-            _ => return Err("Number part information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Number part information not valid"))
         };
 
         let percent_part = match get_percent("percent_part", fields) {
             Some(percent) => percent,
-            _ => return Err("Percent part information not valid".to_string())
+            _ => return Err(SmartCalcError::invalid_argument("Percent part information not valid"))
         };
 
+        let total = do_number_divition(do_number_multiplication(number_part, number_from_i64(100))?, number_from_f64(percent_part)?)?;
         return Ok(match get_currency(config, "number_part", fields) {
-            Some(currency) => TokenType::Money(do_divition(number_part * 100.0, percent_part), currency),
-            None => TokenType::Number(do_divition(number_part * 100.0, percent_part), NumberType::Decimal)
+            Some(currency) => TokenType::Money(total, currency),
+            None => TokenType::Number(total, NumberType::Decimal)
         });
     }
 
-    Err("Find percent not valid".to_string())
+    Err(SmartCalcError::invalid_argument("Find percent not valid"))
 }
 
 
 #[cfg(test)]
 #[test]
 fn find_percent_to_number_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
 
     let tokens = execute("20 is 10% of what".to_string());
     assert_eq!(tokens.len(), 6);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(200.0, NumberType::Decimal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(200.0), NumberType::Decimal)));
 
 }
 
 #[cfg(test)]
 #[test]
 fn find_percent_to_number_2() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
     let tokens = execute("180 is 10% of what".to_string());
 
     assert_eq!(tokens.len(), 6);
-    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(1800.0, NumberType::Decimal)));
+    assert_eq!(tokens[0].token_type.borrow().deref(), &Some(TokenType::Number(number!(1800.0), NumberType::Decimal)));
 
 }
 
 #[cfg(test)]
 #[test]
 fn find_numbers_percent_1() {
+    use alloc::string::ToString;
     use core::ops::Deref;
     use crate::tokinizer::test::execute;
     
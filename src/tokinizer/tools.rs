@@ -25,26 +25,28 @@ use chrono::{Duration, NaiveDate};
 use crate::config::SmartCalcConfig;
 use crate::config::DynamicType;
 use crate::types::CurrencyInfo;
-use crate::types::Money;
+use crate::types::{Money, Number};
 use crate::types::{TokenType, SmartCalcAstType};
 use crate::tokinizer::TokenInfo;
 use crate::compiler::money::MoneyItem;
+use crate::tools::number_to_f64;
+use crate::shared::Shared;
 
-pub fn read_currency(config: &SmartCalcConfig, currency: &'_ str) -> Option<Rc<CurrencyInfo>> {
+pub fn read_currency(config: &SmartCalcConfig, currency: &'_ str) -> Option<Shared<CurrencyInfo>> {
     match config.currency_alias.get(&currency.to_lowercase()) {
         Some(symbol) => Some(symbol.clone()),
         _ => config.currency.get(&currency.to_lowercase()).cloned()
     }
 }
 
-pub fn get_number(field_name: &str, fields: &BTreeMap<String, Rc<TokenInfo>>) -> Option<f64> {
+pub fn get_number(field_name: &str, fields: &BTreeMap<String, Rc<TokenInfo>>) -> Option<Number> {
     return match fields.get(field_name) {
         Some(data) => match data.token_type.borrow().deref() {
             Some(token) => match &token {
                 TokenType::Number(number, _) => Some(*number),
                 TokenType::Variable(variable) => {
                     match variable.data.borrow().deref().deref() {
-                        SmartCalcAstType::Item(item) => item.as_any().downcast_ref::<NumberItem>().map(|number| number.get_underlying_number()),
+                        SmartCalcAstType::Item(item) => item.as_any().downcast_ref::<NumberItem>().map(|number| number.0),
                         _ => None
                     }
                 },
@@ -143,7 +145,7 @@ pub fn get_text(field_name: &str, fields: &BTreeMap<String, Rc<TokenInfo>>) -> O
     }
 }
 
-pub fn get_dynamic_type(field_name: &str, fields: &BTreeMap<String, Rc<TokenInfo>>) -> Option<(f64, Rc<DynamicType>)> {
+pub fn get_dynamic_type(field_name: &str, fields: &BTreeMap<String, Rc<TokenInfo>>) -> Option<(f64, Shared<DynamicType>)> {
     return match &fields.get(field_name) {
         Some(data) =>match &data.token_type.borrow().deref() {
             Some(token) => match &token {
@@ -195,14 +197,14 @@ pub fn get_number_or_time(config: &SmartCalcConfig, field_name: &str, fields: &B
     match get_number(field_name, fields) {
         Some(number) => {
             let date = Utc::now().naive_local().date();
-            let time = chrono::NaiveTime::from_hms(number as u32, 0, 0);
+            let time = chrono::NaiveTime::from_hms(number_to_f64(number) as u32, 0, 0);
             Some((NaiveDateTime::new(date, time), config.get_time_offset()))
         },
         None => get_time(field_name, fields)
     }
 }
 
-pub fn get_number_or_price(config: &SmartCalcConfig, field_name: &str, fields: &BTreeMap<String, Rc<TokenInfo>>) -> Option<f64> {
+pub fn get_number_or_price(config: &SmartCalcConfig, field_name: &str, fields: &BTreeMap<String, Rc<TokenInfo>>) -> Option<Number> {
     match get_number(field_name, fields) {
         Some(number) => Some(number),
         None => get_money(config, field_name, fields).map(|money| money.get_price())
@@ -211,7 +213,7 @@ pub fn get_number_or_price(config: &SmartCalcConfig, field_name: &str, fields: &
 
 pub fn get_number_or_month(field_name: &str, fields: &BTreeMap<String, Rc<TokenInfo>>) -> Option<u32> {
     match get_number(field_name, fields) {
-        Some(number) => Some(number as u32),
+        Some(number) => Some(number_to_f64(number) as u32),
         None => get_month(field_name, fields)
     }
 }
@@ -236,7 +238,7 @@ pub fn get_money(_: &SmartCalcConfig, field_name: &str, fields: &BTreeMap<String
     }
 }
 
-pub fn get_currency(config: &SmartCalcConfig, field_name: &str, fields: &BTreeMap<String, Rc<TokenInfo>>) -> Option<Rc<CurrencyInfo>> {
+pub fn get_currency(config: &SmartCalcConfig, field_name: &str, fields: &BTreeMap<String, Rc<TokenInfo>>) -> Option<Shared<CurrencyInfo>> {
     match &fields.get(field_name) {
         Some(data) => match &data.token_type.borrow().deref() {
             Some(token) => match &token {
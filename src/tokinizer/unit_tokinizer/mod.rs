@@ -0,0 +1,216 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use core::cell::{Cell, RefCell};
+use core::ops::Deref;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::UiTokenType;
+use crate::compiler::quantity::check_absolute;
+use crate::tools::number_to_f64;
+use crate::types::{TokenType, PatternToken};
+use crate::unit::{Unit, dynamic_type_for_unit};
+
+use super::{Tokinizer, TokenInfo, TokenInfoStatus, RuleType};
+
+fn active_token(tokinizer: &Tokinizer, index: usize) -> Option<TokenType> {
+    let token = tokinizer.token_infos.get(index)?;
+    match token.status.get() {
+        TokenInfoStatus::Active => token.token_type.borrow().clone(),
+        TokenInfoStatus::Removed => None
+    }
+}
+
+fn next_active_index(tokinizer: &Tokinizer, index: usize) -> Option<usize> {
+    (index..tokinizer.token_infos.len()).find(|index| active_token(tokinizer, *index).is_some())
+}
+
+/* Longest run of adjacent tokens starting at "start" that reads as a unit, like "km", "m/s^2", "kg·m²" or "°C" */
+fn parse_unit(tokinizer: &Tokinizer, start: usize) -> Option<(usize, Unit)> {
+    let mut end = start;
+    while let Some(token) = active_token(tokinizer, end + 1) {
+        let is_unit_token = match token {
+            TokenType::Text(_) => true,
+            TokenType::Operator(operator) => matches!(operator, '/' | '*' | '·' | '^' | '²' | '³' | '(' | ')' | '°'),
+            TokenType::Number(_, _) => matches!(active_token(tokinizer, end), Some(TokenType::Operator('^'))),
+            _ => false
+        };
+
+        if !is_unit_token || tokinizer.token_infos[end].end != tokinizer.token_infos[end + 1].start {
+            break;
+        }
+        end += 1;
+    }
+
+    for end in (start..=end).rev() {
+        let text = &tokinizer.data[tokinizer.token_infos[start].start..tokinizer.token_infos[end].end];
+        if let Some(unit) = Unit::parse(tokinizer.config, text) {
+            return Some((end, unit));
+        }
+    }
+
+    None
+}
+
+fn is_dynamic_type_name(tokinizer: &Tokinizer, word: &str) -> bool {
+    let word = word.to_lowercase();
+    tokinizer.config.types.values().flat_map(|group| group.values()).any(|dynamic_type| dynamic_type.names.contains(&word))
+}
+
+fn is_duration_word(tokinizer: &Tokinizer, word: &str) -> bool {
+    tokinizer.config.word_group.get(&tokinizer.language)
+        .and_then(|word_group| word_group.get("duration_group"))
+        .is_some_and(|words| words.contains(&word.to_lowercase()))
+}
+
+/* Words like "um" in "1. jan 2021 um 10:00" belong to the rules of the language, even if they are unit symbols */
+fn is_rule_word(tokinizer: &Tokinizer, word: &str) -> bool {
+    let rules = match tokinizer.config.get_rules(&tokinizer.language) {
+        Some(rules) => rules,
+        None => return false
+    };
+
+    let word = word.to_lowercase();
+    rules.iter()
+        .flat_map(|rule| match rule {
+            RuleType::Internal { tokens_list, .. } => tokens_list.iter(),
+            RuleType::API { tokens_list, .. } => tokens_list.iter()
+        })
+        .flatten()
+        .any(|token| matches!(token, PatternToken::Text(text) if text.to_lowercase() == word))
+}
+
+fn is_reserved_word(tokinizer: &Tokinizer, word: &str) -> bool {
+    is_dynamic_type_name(tokinizer, word) || is_duration_word(tokinizer, word) || is_rule_word(tokinizer, word)
+}
+
+fn unit_token(tokinizer: &Tokinizer, value: f64, unit: Unit) -> TokenType {
+    match dynamic_type_for_unit(tokinizer.config, &unit) {
+        Some(dynamic_type) => TokenType::DynamicType(value, dynamic_type),
+        None => TokenType::Quantity(value, Rc::new(unit))
+    }
+}
+
+fn quantity_tokinizer(tokinizer: &mut Tokinizer) {
+    let mut index = 0;
+    while index < tokinizer.token_infos.len() {
+        let value = match active_token(tokinizer, index) {
+            Some(TokenType::Number(number, _)) => number_to_f64(number),
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+
+        let unit_start = match next_active_index(tokinizer, index + 1) {
+            Some(unit_start) if matches!(active_token(tokinizer, unit_start), Some(TokenType::Text(_)) | Some(TokenType::Operator('°'))) => unit_start,
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+
+        let (unit_end, unit) = match parse_unit(tokinizer, unit_start) {
+            /* Single words are left to the dynamic types and the language rules */
+            Some((unit_end, _)) if unit_end == unit_start && is_reserved_word(tokinizer, &tokinizer.token_infos[unit_start].original_text) => {
+                index += 1;
+                continue;
+            },
+            Some(unit) => unit,
+            None => {
+                index += 1;
+                continue;
+            }
+        };
+
+        let start = tokinizer.token_infos[index].start;
+        let end = tokinizer.token_infos[unit_end].end;
+        tokinizer.ui_tokens.update_tokens(tokinizer.token_infos[unit_start].start, end, UiTokenType::Symbol2);
+
+        for token in tokinizer.token_infos[index..=unit_end].iter() {
+            token.status.set(TokenInfoStatus::Removed);
+        }
+
+        if let Err(error) = check_absolute(value, &unit) {
+            let span = tokinizer.char_span(start, end);
+            tokinizer.token_error.get_or_insert(error.with_span(span));
+        }
+
+        let token_type = unit_token(tokinizer, value, unit);
+        tokinizer.token_infos.insert(index, Rc::new(TokenInfo {
+            start,
+            end,
+            token_type: RefCell::new(Some(token_type)),
+            original_text: tokinizer.data[start..end].to_string(),
+            status: Cell::new(TokenInfoStatus::Active)
+        }));
+        index = unit_end + 2;
+    }
+}
+
+/// Trailing "in N" or "to km/h" converts the result of the whole expression.
+///
+/// Conversions between two dynamic types or durations like "10 cm in m" are left to the rules,
+/// unless the line is an expression.
+fn conversion_tokinizer(tokinizer: &mut Tokinizer) {
+    let conversion_words = match tokinizer.config.word_group.get(&tokinizer.language).and_then(|word_group| word_group.get("conversion_group")) {
+        Some(words) => words.clone(),
+        None => return
+    };
+
+    let mut expression_start = 0;
+    for (index, token) in tokinizer.token_infos.iter().enumerate() {
+        if let Some(TokenType::Operator('=')) = token.token_type.borrow().deref() {
+            expression_start = index + 1;
+        }
+    }
+
+    let active: Vec<usize> = (expression_start..tokinizer.token_infos.len()).filter(|index| active_token(tokinizer, *index).is_some()).collect();
+    for (position, conversion_index) in active.iter().enumerate().rev() {
+        let is_conversion_word = match active_token(tokinizer, *conversion_index) {
+            Some(TokenType::Text(text)) => conversion_words.contains(&text.to_lowercase()),
+            _ => false
+        };
+
+        let unit_start = match (is_conversion_word, active.get(position + 1)) {
+            (true, Some(unit_start)) if position > 0 => *unit_start,
+            _ => continue
+        };
+
+        let (unit_end, unit) = match parse_unit(tokinizer, unit_start) {
+            Some((unit_end, unit)) if Some(&unit_end) == active.last() => (unit_end, unit),
+            _ => return
+        };
+
+        let word = match unit_start == unit_end {
+            true => tokinizer.token_infos[unit_start].original_text.to_string(),
+            false => String::new()
+        };
+        let is_expression = active[..position].iter().any(|index| matches!(active_token(tokinizer, *index), Some(TokenType::Operator(operator)) if !matches!(operator, '(' | ')')));
+        let is_quantity = matches!(active_token(tokinizer, active[position - 1]), Some(TokenType::Quantity(_, _)));
+
+        if is_duration_word(tokinizer, &word) || (is_dynamic_type_name(tokinizer, &word) && !is_expression && !is_quantity) {
+            return;
+        }
+
+        let start = tokinizer.token_infos[*conversion_index].start;
+        let end = tokinizer.token_infos[unit_end].end;
+        tokinizer.ui_tokens.update_tokens(tokinizer.token_infos[unit_start].start, end, UiTokenType::Symbol2);
+        for token in tokinizer.token_infos[*conversion_index..=unit_end].iter() {
+            token.status.set(TokenInfoStatus::Removed);
+        }
+
+        tokinizer.target_unit = Some((Rc::new(unit), tokinizer.char_span(start, end)));
+        return;
+    }
+}
+
+pub fn unit_tokinizer(tokinizer: &mut Tokinizer) {
+    quantity_tokinizer(tokinizer);
+    conversion_tokinizer(tokinizer);
+}
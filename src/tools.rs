@@ -4,8 +4,15 @@
  * Licensed under the GNU General Public License v2.0.
  */
 
+use alloc::collections::BTreeMap;
 use alloc::string::{ToString, String};
+use alloc::vec::Vec;
+use chrono::{Offset, TimeZone, Utc};
+use chrono_tz::{Tz, TZ_VARIANTS};
 use crate::config::SmartCalcConfig;
+use crate::types::Number;
+use crate::error::SmartCalcError;
+use lazy_static::*;
  
 pub fn do_divition(left: f64, right: f64) -> f64 {
     let mut calculation = left / right;
@@ -15,6 +22,231 @@ pub fn do_divition(left: f64, right: f64) -> f64 {
     calculation
 }
 
+pub fn check_divider(config: &SmartCalcConfig, divider: f64) -> Result<(), SmartCalcError> {
+    match divider == 0.0 && !config.division_by_zero_as_zero {
+        true => Err(SmartCalcError::DivisionByZero { span: Default::default() }),
+        false => Ok(())
+    }
+}
+
+/* Zero dividers are checked by the caller, they give 0 with `division_by_zero_as_zero` */
+#[cfg(not(feature = "decimal"))]
+pub fn do_number_divition(left: Number, right: Number) -> Result<Number, SmartCalcError> {
+    match right == 0.0 {
+        true => Ok(0.0),
+        false => check_number(left / right)
+    }
+}
+
+#[cfg(feature = "decimal")]
+pub fn do_number_divition(left: Number, right: Number) -> Result<Number, SmartCalcError> {
+    match right.is_zero() {
+        true => Ok(Number::default()),
+        false => left.checked_div(right).ok_or_else(number_out_of_range)
+    }
+}
+
+/* Floored like the integer division, the result has the sign of the divider and "a // b * b + a % b" is "a" */
+pub fn do_modulo(left: f64, right: f64) -> f64 {
+    let mut calculation = left % right;
+    if calculation.is_infinite() || calculation.is_nan() {
+        calculation = 0.0;
+    }
+
+    match calculation != 0.0 && (calculation < 0.0) != (right < 0.0) {
+        true => calculation + right,
+        false => calculation
+    }
+}
+
+pub fn do_power(left: f64, right: f64) -> Result<f64, SmartCalcError> {
+    let calculation = left.powf(right);
+    match calculation.is_finite() {
+        true => Ok(calculation),
+        false => Err(SmartCalcError::invalid_argument("Power result is not a finite real number"))
+    }
+}
+
+pub fn do_integer_divition(left: f64, right: f64) -> f64 {
+    do_divition(left, right).floor()
+}
+
+pub fn number_out_of_range() -> SmartCalcError {
+    SmartCalcError::invalid_argument("Number is out of range")
+}
+
+#[cfg(not(feature = "decimal"))]
+fn check_number(number: Number) -> Result<Number, SmartCalcError> {
+    match number.is_finite() {
+        true => Ok(number),
+        false => Err(number_out_of_range())
+    }
+}
+
+#[cfg(not(feature = "decimal"))]
+pub fn do_number_addition(left: Number, right: Number) -> Result<Number, SmartCalcError> {
+    check_number(left + right)
+}
+
+#[cfg(feature = "decimal")]
+pub fn do_number_addition(left: Number, right: Number) -> Result<Number, SmartCalcError> {
+    left.checked_add(right).ok_or_else(number_out_of_range)
+}
+
+#[cfg(not(feature = "decimal"))]
+pub fn do_number_subtraction(left: Number, right: Number) -> Result<Number, SmartCalcError> {
+    check_number(left - right)
+}
+
+#[cfg(feature = "decimal")]
+pub fn do_number_subtraction(left: Number, right: Number) -> Result<Number, SmartCalcError> {
+    left.checked_sub(right).ok_or_else(number_out_of_range)
+}
+
+#[cfg(not(feature = "decimal"))]
+pub fn do_number_multiplication(left: Number, right: Number) -> Result<Number, SmartCalcError> {
+    check_number(left * right)
+}
+
+#[cfg(feature = "decimal")]
+pub fn do_number_multiplication(left: Number, right: Number) -> Result<Number, SmartCalcError> {
+    left.checked_mul(right).ok_or_else(number_out_of_range)
+}
+
+#[cfg(not(feature = "decimal"))]
+pub fn do_number_modulo(left: Number, right: Number) -> Number {
+    do_modulo(left, right)
+}
+
+#[cfg(feature = "decimal")]
+pub fn do_number_modulo(left: Number, right: Number) -> Number {
+    let calculation = left.checked_rem(right).unwrap_or_default();
+    match !calculation.is_zero() && calculation.is_sign_negative() != right.is_sign_negative() {
+        true => calculation + right,
+        false => calculation
+    }
+}
+
+#[cfg(not(feature = "decimal"))]
+pub fn do_number_power(left: Number, right: Number) -> Result<Number, SmartCalcError> {
+    do_power(left, right)
+}
+
+#[cfg(feature = "decimal")]
+pub fn do_number_power(left: Number, right: Number) -> Result<Number, SmartCalcError> {
+    use rust_decimal::MathematicalOps;
+    /* Roots of negative numbers are not real */
+    let result = match left.is_sign_negative() && !right.fract().is_zero() {
+        true => None,
+        false => left.checked_powd(right)
+    };
+    result.ok_or_else(|| SmartCalcError::invalid_argument("Power result is not a finite real number"))
+}
+
+pub fn do_number_integer_divition(left: Number, right: Number) -> Result<Number, SmartCalcError> {
+    Ok(do_number_divition(left, right)?.floor())
+}
+
+#[cfg(not(feature = "decimal"))]
+pub fn number_from_f64(number: f64) -> Result<Number, SmartCalcError> {
+    Ok(number)
+}
+
+/* Infinite, NaN or too big values can not be stored in a decimal */
+#[cfg(feature = "decimal")]
+pub fn number_from_f64(number: f64) -> Result<Number, SmartCalcError> {
+    use rust_decimal::prelude::FromPrimitive;
+    Number::from_f64(number).ok_or_else(number_out_of_range)
+}
+
+/* Rates are relative to USD, decimal numbers are converted without going through f64 */
+pub fn convert_price(price: Number, from_rate: f64, to_rate: f64) -> Result<Number, SmartCalcError> {
+    do_number_multiplication(do_number_divition(price, number_from_f64(from_rate)?)?, number_from_f64(to_rate)?)
+}
+
+#[cfg(not(feature = "decimal"))]
+pub fn number_from_i64(number: i64) -> Number {
+    number as f64
+}
+
+#[cfg(feature = "decimal")]
+pub fn number_from_i64(number: i64) -> Number {
+    Number::from(number)
+}
+
+#[cfg(not(feature = "decimal"))]
+pub fn number_to_f64(number: Number) -> f64 {
+    number
+}
+
+#[cfg(feature = "decimal")]
+pub fn number_to_f64(number: Number) -> f64 {
+    use rust_decimal::prelude::ToPrimitive;
+    number.to_f64().unwrap_or_default()
+}
+
+#[cfg(not(feature = "decimal"))]
+pub fn round_number(number: Number, digits: i32) -> Result<Number, SmartCalcError> {
+    let multiplier = 10_f64.powi(digits);
+    check_number((number * multiplier).round() / multiplier)
+}
+
+#[cfg(feature = "decimal")]
+pub fn round_number(number: Number, digits: i32) -> Result<Number, SmartCalcError> {
+    use rust_decimal::RoundingStrategy;
+    match digits >= 0 {
+        true => Ok(number.round_dp_with_strategy(digits as u32, RoundingStrategy::MidpointAwayFromZero)),
+        false => {
+            let multiplier = number_from_f64(10_f64.powi(-digits))?;
+            do_number_multiplication((number / multiplier).round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero), multiplier)
+        }
+    }
+}
+
+#[cfg(test)]
+pub fn number_from_literal(literal: &str) -> Number {
+    literal.replace('_', "").parse::<Number>().unwrap_or_default()
+}
+
+/* Backward compatible links that look like canonical "Area/City" zones */
+const TIMEZONE_LINKS: &[&str] = &["Australia/ACT", "Australia/Canberra", "Australia/LHI", "Australia/NSW", "Australia/North", "Australia/Queensland",
+    "Australia/South", "Australia/Tasmania", "Australia/Victoria", "Australia/West", "Australia/Yancowinna", "Antarctica/South_Pole",
+    "America/Atka", "America/Ensenada", "America/Fort_Wayne", "America/Knox_IN", "America/Porto_Acre", "America/Rosario", "America/Shiprock",
+    "America/Virgin", "Atlantic/Jan_Mayen", "Pacific/Ponape", "Pacific/Samoa", "Pacific/Truk", "Pacific/Yap"];
+
+const TIMEZONE_AREAS: &[&str] = &["Africa", "America", "Antarctica", "Asia", "Atlantic", "Australia", "Europe", "Indian", "Pacific"];
+
+lazy_static! {
+    /* Canonical zones by lowercase name ("europe/berlin") and by lowercase city ("new_york"), the first zone of a city wins */
+    pub static ref IANA_TIMEZONES: BTreeMap<String, Tz> = {
+        let mut timezones = BTreeMap::new();
+        let canonicals = TZ_VARIANTS.iter().filter(|timezone| {
+            let name = timezone.name();
+            !TIMEZONE_LINKS.contains(&name) && name.split_once('/').is_some_and(|(area, _)| TIMEZONE_AREAS.contains(&area))
+        });
+
+        for timezone in canonicals {
+            let name = timezone.name().to_lowercase();
+            if let Some((_, city)) = name.rsplit_once('/') {
+                timezones.entry(city.to_string()).or_insert(*timezone);
+            }
+            timezones.insert(name, *timezone);
+        }
+        timezones
+    };
+}
+
+/// Find a canonical IANA timezone by its name ("Europe/Berlin") or by its city ("berlin", "new york"), case insensitive.
+pub fn find_timezone(name: &str) -> Option<Tz> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join("_").to_lowercase();
+    IANA_TIMEZONES.get(&name).copied()
+}
+
+/// Current offset of the IANA timezone in minutes.
+pub fn current_timezone_offset(timezone: &Tz) -> i32 {
+    timezone.offset_from_utc_datetime(&Utc::now().naive_utc()).fix().local_minus_utc() / 60
+}
+
 pub fn parse_timezone<'t>(config: &SmartCalcConfig, capture: &regex::Captures<'t>) -> Option<(String, i32)> {
     match capture.name("timezone_1") {
         Some(tz) => {
@@ -11,30 +11,51 @@ use alloc::string::ToString;
 use alloc::string::String;
 use alloc::format;
 use core::ops::Deref;
-use chrono::{NaiveDateTime, TimeZone};
+use chrono::{NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
 
 use serde_derive::{Deserialize, Serialize};
 use alloc::collections::btree_map::BTreeMap;
 use chrono::{Duration, NaiveDate};
 use crate::compiler::DataItem;
+use crate::compiler::date::DateItem;
+use crate::compiler::date_time::DateTimeItem;
+use crate::compiler::duration::DurationItem;
 use crate::compiler::dynamic_type::DynamicTypeItem;
+use crate::compiler::money::MoneyItem;
+use crate::compiler::number::NumberItem;
+use crate::compiler::percent::PercentItem;
+use crate::compiler::quantity::QuantityItem;
+use crate::compiler::time::TimeItem;
 use crate::config::DynamicType;
 use crate::config::SmartCalcConfig;
+use crate::session::Session;
+use crate::error::{SmartCalcError, Span};
+use crate::unit::Unit;
 
+use crate::tools::find_timezone;
 use crate::tokinizer::TokenInfoStatus;
 use crate::tokinizer::{TokenInfo, Tokinizer};
 use crate::variable::VariableInfo;
+use crate::shared::Shared;
 
-pub type ExpressionFunc     = fn(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, String>;
-pub type AstResult          = Result<SmartCalcAstType, (&'static str, u16, u16)>;
+pub type ExpressionFunc     = fn(config: &SmartCalcConfig, tokinizer: &Tokinizer, fields: &BTreeMap<String, Rc<TokenInfo>>) -> core::result::Result<TokenType, SmartCalcError>;
+pub type CallFunc           = fn(config: &SmartCalcConfig, session: &Session, arguments: &[Rc<dyn DataItem>]) -> core::result::Result<Rc<dyn DataItem>, SmartCalcError>;
+pub type AstResult          = Result<SmartCalcAstType, SmartCalcError>;
 
-pub struct Money(pub f64, pub Rc<CurrencyInfo>);
+#[cfg(not(feature = "decimal"))]
+pub type Number = f64;
+
+#[cfg(feature = "decimal")]
+pub type Number = rust_decimal::Decimal;
+
+pub struct Money(pub Number, pub Shared<CurrencyInfo>);
 impl Money {
-    pub fn get_price(&self) -> f64 {
+    pub fn get_price(&self) -> Number {
         self.0
     }
     
-    pub fn get_currency(&self) -> Rc<CurrencyInfo> {
+    pub fn get_currency(&self) -> Shared<CurrencyInfo> {
         self.1.clone()
     }
 }
@@ -58,9 +79,6 @@ pub enum FieldType {
     DynamicType(String, Option<String>)
 }
 
-unsafe impl Send for FieldType {}
-unsafe impl Sync for FieldType {}
-
 impl FieldType {
     pub fn type_name(&self) -> String {
         match self {
@@ -151,13 +169,46 @@ impl Ord for CurrencyInfo {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TimeOffset {
     pub name: String,
     pub offset: i32
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+impl TimeOffset {
+    fn get_timezone(&self) -> Option<Tz> {
+        match self.name.contains('/') {
+            true => find_timezone(&self.name),
+            false => None
+        }
+    }
+
+    /// Offset in minutes at the given UTC time. IANA timezones follow daylight saving time, abbreviations have a fixed offset.
+    pub fn offset_at(&self, utc: &NaiveDateTime) -> i32 {
+        match self.get_timezone() {
+            Some(timezone) => timezone.offset_from_utc_datetime(utc).fix().local_minus_utc() / 60,
+            None => self.offset
+        }
+    }
+
+    /// Offset in minutes of a wall clock time in this timezone. A time skipped by daylight saving
+    /// time gets the offset before the change, see [`TimeOffset::checked_offset_for_local`].
+    pub fn offset_for_local(&self, local: &NaiveDateTime) -> i32 {
+        self.checked_offset_for_local(local).unwrap_or_else(|| self.offset_at(local))
+    }
+
+    /// Offset in minutes of a wall clock time in this timezone, `None` if daylight saving time
+    /// skips the time, like 2:30 on 28 March 2021 in Europe/Berlin. Repeated times get the
+    /// earlier offset.
+    pub fn checked_offset_for_local(&self, local: &NaiveDateTime) -> Option<i32> {
+        match self.get_timezone() {
+            Some(timezone) => timezone.offset_from_local_datetime(local).earliest().map(|offset| offset.fix().local_minus_utc() / 60),
+            None => Some(self.offset)
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum NumberType {
     Decimal,
     Octal,
@@ -168,20 +219,22 @@ pub enum NumberType {
 
 #[derive(Debug, Clone)]
 pub enum TokenType {
-    Number(f64, NumberType),
+    Number(Number, NumberType),
     Text(String),
     Time(NaiveDateTime, TimeOffset),
     Date(NaiveDate, TimeOffset),
     DateTime(NaiveDateTime, TimeOffset),
     Operator(char),
-    Field(Rc<FieldType>),
+    Field(Shared<FieldType>),
     Percent(f64),
-    DynamicType(f64, Rc<DynamicType>),
-    Money(f64, Rc<CurrencyInfo>),
+    DynamicType(f64, Shared<DynamicType>),
+    Quantity(f64, Rc<Unit>),
+    Money(Number, Shared<CurrencyInfo>),
     Variable(Rc<VariableInfo>),
     Month(u32),
     Duration(Duration),
-    Timezone(String, i32)
+    Timezone(String, i32),
+    Function(String)
 }
 
 
@@ -200,6 +253,7 @@ impl PartialEq for TokenType {
             (TokenType::Duration(l_value),     TokenType::Duration(r_value)) => l_value == r_value,
             (TokenType::Date(l_value, l_tz),     TokenType::Date(r_value, r_tz)) => l_value == r_value && l_tz == r_tz,
             (TokenType::Field(l_value),    TokenType::Field(r_value)) => l_value.deref() == r_value.deref(),
+            (TokenType::Function(l_value),    TokenType::Function(r_value)) => l_value == r_value,
             (_, _)  => false
         }
     }
@@ -209,20 +263,21 @@ impl ToString for TokenType {
     fn to_string(&self) -> String {
         match &self {
             TokenType::DynamicType(number, dynamic_type) => dynamic_type.format.replace("{value}", &number.to_string()),
+            TokenType::Quantity(number, unit) => format!("{} {}", number, unit),
             TokenType::Number(number, _) => number.to_string(),
             TokenType::Text(text) => text.to_string(),
             TokenType::Time(time, tz) => {
-                let tz_offset = chrono::FixedOffset::east(tz.offset * 60);
+                let tz_offset = chrono::FixedOffset::east(tz.offset_at(time) * 60);
                 let datetime = tz_offset.from_utc_datetime(time);
                 alloc::format!("{} {}", datetime.format("%H:%M:%S").to_string(), tz.name)
             },
             TokenType::Date(date, tz) => {
-                let tz_offset = chrono::FixedOffset::east(tz.offset * 60);
+                let tz_offset = chrono::FixedOffset::east(tz.offset_at(&date.and_hms_opt(0, 0, 0).unwrap_or_default()) * 60);
                 let datetime = tz_offset.from_utc_date(date);
                 alloc::format!("{} {}", datetime.format("%d/%m/%Y").to_string(), tz.name)
             },
             TokenType::DateTime(datetime, tz) => {
-                let tz_offset = chrono::FixedOffset::east(tz.offset * 60);
+                let tz_offset = chrono::FixedOffset::east(tz.offset_at(datetime) * 60);
                 let datetime = tz_offset.from_utc_datetime(datetime);
                 alloc::format!("{} {}", datetime.format("%d/%m/%Y %H:%M:%S").to_string(), tz.name)
             },
@@ -233,7 +288,8 @@ impl ToString for TokenType {
             TokenType::Variable(var) => var.to_string(),
             TokenType::Month(month) => month.to_string(),
             TokenType::Duration(duration) => duration.to_string(),
-            TokenType::Timezone(timezone, offset) => format!("{} {:?}", timezone, offset)
+            TokenType::Timezone(timezone, offset) => format!("{} {:?}", timezone, offset),
+            TokenType::Function(name) => name.to_string()
         }
     }
 }
@@ -255,10 +311,29 @@ impl TokenType {
             TokenType::Month(_) => "MONTH".to_string(),
             TokenType::Duration(_) => "DURATION".to_string(),
             TokenType::Timezone(_, _) => "TIMEZONE".to_string(),
-            TokenType::DynamicType(_, _) => "DYNAMIC_TYPE".to_string()
+            TokenType::DynamicType(_, _) => "DYNAMIC_TYPE".to_string(),
+            TokenType::Quantity(_, _) => "QUANTITY".to_string(),
+            TokenType::Function(_) => "FUNCTION".to_string()
         }
     }
 
+    /// Item of the interpreter for a value token, `None` for words, operators and fields.
+    pub(crate) fn as_item(&self) -> Option<Rc<dyn DataItem>> {
+        let item: Rc<dyn DataItem> = match self {
+            TokenType::DynamicType(number, dynamic_type) => Rc::new(DynamicTypeItem(*number, dynamic_type.clone())),
+            TokenType::Quantity(number, unit) => Rc::new(QuantityItem(*number, unit.clone())),
+            TokenType::Money(price, currency) => Rc::new(MoneyItem(*price, currency.clone())),
+            TokenType::Number(number, number_type) => Rc::new(NumberItem(*number, *number_type)),
+            TokenType::Percent(percent) => Rc::new(PercentItem(*percent)),
+            TokenType::Time(time, tz) => Rc::new(TimeItem(*time, tz.clone())),
+            TokenType::Date(date, tz) => Rc::new(DateItem(*date, tz.clone())),
+            TokenType::DateTime(date_time, tz) => Rc::new(DateTimeItem(*date_time, tz.clone())),
+            TokenType::Duration(duration) => Rc::new(DurationItem(*duration)),
+            _ => return None
+        };
+        Some(item)
+    }
+
     pub fn field_compare(&self, field: &FieldType) -> bool {
         match (field, self) {
             (FieldType::DynamicType(_, expected), TokenType::DynamicType(_, dynamic_type)) => expected.as_ref().map_or(true, |v| v.to_lowercase() == dynamic_type.group_name.to_lowercase()),
@@ -277,28 +352,48 @@ impl TokenType {
             (_, _) => false,
         }
     }
+}
 
-    pub fn variable_compare(left: &TokenInfo, right: Rc<SmartCalcAstType>) -> bool {
-        match &left.token_type.borrow().deref() {
-            Some(token) => match (&token, right.deref()) {
-                (TokenType::Text(l_value), SmartCalcAstType::Symbol(r_value)) => l_value.deref().to_lowercase() == r_value.to_lowercase(),
-                (TokenType::Timezone(l_value, l_type), SmartCalcAstType::Item(r_value)) => r_value.is_same(&(l_value.clone(), *l_type)),
-                (TokenType::Number(l_value, _), SmartCalcAstType::Item(r_value)) => r_value.is_same(l_value),
-                (TokenType::Percent(l_value), SmartCalcAstType::Item(r_value)) => r_value.is_same(l_value),
-                (TokenType::Duration(l_value), SmartCalcAstType::Item(r_value)) => r_value.is_same(l_value),
-                (TokenType::Time(l_value, l_tz), SmartCalcAstType::Item(r_value)) => r_value.is_same(&(*l_value, l_tz.clone())),
-                (TokenType::Money(l_value, l_symbol), SmartCalcAstType::Item(r_value)) => r_value.is_same(&(*l_value, l_symbol.clone())),
-                (TokenType::Date(l_value, l_tz), SmartCalcAstType::Item(r_value)) => r_value.is_same(&(*l_value, l_tz.clone())),
-                (TokenType::Field(l_value), _) => right.field_compare(l_value.deref()),
-                (_, _) => false
-            },
-            _ => false
+/// Token of a rule or dynamic type pattern like `{NUMBER:day}/{NUMBER:month}`.
+///
+/// Patterns are compiled with the configuration and shared by every session, so unlike
+/// `TokenInfo` they have no interior mutability and never refer to session variables.
+/// Tokens that a pattern can not compare, like months, are kept as `Other` and are never matched.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternToken {
+    Field(Shared<FieldType>),
+    Text(String),
+    Number(Number),
+    Percent(f64),
+    Operator(char),
+    Time(NaiveDateTime, TimeOffset),
+    Date(NaiveDate, TimeOffset),
+    Duration(Duration),
+    Money(Number, Shared<CurrencyInfo>),
+    Timezone(String, i32),
+    Other(String)
+}
+
+impl PatternToken {
+    pub fn new(token: &TokenType) -> Self {
+        match token {
+            TokenType::Field(field) => PatternToken::Field(field.clone()),
+            TokenType::Text(text) => PatternToken::Text(text.to_string()),
+            TokenType::Number(number, _) => PatternToken::Number(*number),
+            TokenType::Percent(number) => PatternToken::Percent(*number),
+            TokenType::Operator(operator) => PatternToken::Operator(*operator),
+            TokenType::Time(time, tz) => PatternToken::Time(*time, tz.clone()),
+            TokenType::Date(date, tz) => PatternToken::Date(*date, tz.clone()),
+            TokenType::Duration(duration) => PatternToken::Duration(*duration),
+            TokenType::Money(price, currency) => PatternToken::Money(*price, currency.clone()),
+            TokenType::Timezone(timezone, offset) => PatternToken::Timezone(timezone.to_string(), *offset),
+            _ => PatternToken::Other(token.type_name())
         }
     }
 
-    pub fn get_field_name(token: &TokenInfo) -> Option<String> {
-        match &token.token_type.borrow().deref() {
-            Some(TokenType::Field(field)) =>  match field.deref() {
+    pub fn field_name(&self) -> Option<String> {
+        match self {
+            PatternToken::Field(field) => match field.deref() {
                 FieldType::Text(field_name, _)    => Some(field_name.to_string()),
                 FieldType::DateTime(field_name)    => Some(field_name.to_string()),
                 FieldType::Date(field_name)    => Some(field_name.to_string()),
@@ -316,6 +411,42 @@ impl TokenType {
             _ => None
         }
     }
+
+    /// Compares an active token of the line with the pattern.
+    pub fn matches(&self, token: &TokenInfo) -> bool {
+        if token.status.get() == TokenInfoStatus::Removed {
+            return false;
+        }
+
+        match (token.token_type.borrow().deref(), self) {
+            (Some(TokenType::Text(l_value)), PatternToken::Text(r_value)) => l_value.to_lowercase() == r_value.to_lowercase(),
+            (Some(TokenType::Number(l_value, _)), PatternToken::Number(r_value)) => l_value == r_value,
+            (Some(TokenType::Percent(l_value)), PatternToken::Percent(r_value)) => l_value == r_value,
+            (Some(TokenType::Operator(l_value)), PatternToken::Operator(r_value)) => l_value == r_value,
+            (Some(TokenType::Date(l_value, l_tz)), PatternToken::Date(r_value, r_tz)) => l_value == r_value && l_tz == r_tz,
+            (Some(TokenType::Duration(l_value)), PatternToken::Duration(r_value)) => l_value == r_value,
+            (Some(TokenType::Money(l_value, l_symbol)), PatternToken::Money(r_value, r_symbol)) => l_value == r_value && l_symbol == r_symbol,
+            (Some(TokenType::Timezone(l_value, l_symbol)), PatternToken::Timezone(r_value, r_symbol)) => l_value == r_value && l_symbol == r_symbol,
+            (Some(l_token), PatternToken::Field(r_value)) => l_token.field_compare(r_value.deref()),
+            (_, _) => false
+        }
+    }
+
+    /// Compares the value of a session variable with the pattern.
+    pub fn matches_variable(&self, right: Rc<SmartCalcAstType>) -> bool {
+        match (self, right.deref()) {
+            (PatternToken::Text(l_value), SmartCalcAstType::Symbol(r_value)) => l_value.to_lowercase() == r_value.to_lowercase(),
+            (PatternToken::Timezone(l_value, l_type), SmartCalcAstType::Item(r_value)) => r_value.is_same(&(l_value.clone(), *l_type)),
+            (PatternToken::Number(l_value), SmartCalcAstType::Item(r_value)) => r_value.is_same(l_value),
+            (PatternToken::Percent(l_value), SmartCalcAstType::Item(r_value)) => r_value.is_same(l_value),
+            (PatternToken::Duration(l_value), SmartCalcAstType::Item(r_value)) => r_value.is_same(l_value),
+            (PatternToken::Time(l_value, l_tz), SmartCalcAstType::Item(r_value)) => r_value.is_same(&(*l_value, l_tz.clone())),
+            (PatternToken::Money(l_value, l_symbol), SmartCalcAstType::Item(r_value)) => r_value.is_same(&(*l_value, l_symbol.clone())),
+            (PatternToken::Date(l_value, l_tz), SmartCalcAstType::Item(r_value)) => r_value.is_same(&(*l_value, l_tz.clone())),
+            (PatternToken::Field(l_value), _) => right.field_compare(l_value.deref()),
+            (_, _) => false
+        }
+    }
 }
 
 pub fn find_location<T: PartialEq<U>, U>(tokens: &[Rc<T>], rule_tokens: &[Rc<U>]) -> Option<usize> {
@@ -421,13 +552,14 @@ impl CharTraits for char {
 #[derive(Debug)]
 pub enum SmartCalcAstType {
     None,
-    Field(Rc<FieldType>),
+    Field(Shared<FieldType>),
     Item(Rc<dyn DataItem>),
     Month(u32),
     Binary {
         left: Rc<SmartCalcAstType>,
         operator: char,
-        right: Rc<SmartCalcAstType>
+        right: Rc<SmartCalcAstType>,
+        span: Span
     },
     PrefixUnary(char, Rc<SmartCalcAstType>),
     Assignment {
@@ -435,7 +567,17 @@ pub enum SmartCalcAstType {
         expression: Rc<SmartCalcAstType>
     },
     Symbol(String),
-    Variable(Rc<VariableInfo>)
+    Variable(Rc<VariableInfo>),
+    Call {
+        name: String,
+        arguments: Vec<Rc<SmartCalcAstType>>,
+        span: Span
+    },
+    Conversion {
+        expression: Rc<SmartCalcAstType>,
+        unit: Rc<Unit>,
+        span: Span
+    }
 }
 
 impl SmartCalcAstType {
@@ -448,7 +590,8 @@ impl SmartCalcAstType {
             SmartCalcAstType::Binary {
                 left: _,
                 operator: _,
-                right: _
+                right: _,
+                span: _
             } => "BINARY".to_string(),
             SmartCalcAstType::PrefixUnary(_, ast) => ast.type_name(),
             SmartCalcAstType::Assignment {
@@ -456,7 +599,13 @@ impl SmartCalcAstType {
                 expression: _
             } => "ASSIGNMENT".to_string(),
             SmartCalcAstType::Symbol(_) => "SYMBOL".to_string(),
-            SmartCalcAstType::Variable(variable) => variable.data.borrow().type_name()
+            SmartCalcAstType::Variable(variable) => variable.data.borrow().type_name(),
+            SmartCalcAstType::Call {
+                name: _,
+                arguments: _,
+                span: _
+            } => "CALL".to_string(),
+            SmartCalcAstType::Conversion { expression, .. } => expression.type_name()
         }
     }
 
@@ -0,0 +1,594 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use core::fmt;
+use core::iter::Peekable;
+use core::str::Chars;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::SmartCalc;
+use crate::config::{DynamicType, SmartCalcConfig};
+use crate::locale::Locale;
+use crate::shared::Shared;
+
+pub const TIME_DIMENSION: &str = "time";
+
+/// Exponents of the base dimensions of a unit.
+///
+/// Base dimensions come from the `units` section of the configuration (`length`, `mass`,
+/// `time`, ...). Imported `types` groups that can not be tied to one of these units use
+/// their group name as a base dimension of their own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dimension(BTreeMap<String, i32>);
+
+impl Dimension {
+    pub fn base<T: ToString>(name: T) -> Self {
+        let mut exponents = BTreeMap::new();
+        exponents.insert(name.to_string(), 1);
+        Dimension(exponents)
+    }
+
+    /// Base dimensions with their exponents, like `length: 1, time: -1` for a speed.
+    pub fn exponents(&self) -> &BTreeMap<String, i32> {
+        &self.0
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Dimension of `self * other ^ power`.
+    pub fn combine(&self, other: &Dimension, power: i32) -> Dimension {
+        let mut exponents = self.0.clone();
+        for (name, exponent) in other.0.iter() {
+            let total = exponents.get(name).copied().unwrap_or_default() + exponent * power;
+            match total {
+                0 => exponents.remove(name),
+                _ => exponents.insert(name.to_string(), total)
+            };
+        }
+
+        Dimension(exponents)
+    }
+
+    /// Dimension of the `degree`th root, `None` if an exponent is not a multiple of it.
+    pub fn root(&self, degree: i32) -> Option<Dimension> {
+        match self.0.values().all(|exponent| exponent % degree == 0) {
+            true => Some(Dimension(self.0.iter().map(|(name, exponent)| (name.to_string(), exponent / degree)).collect())),
+            false => None
+        }
+    }
+}
+
+/// A unit with its prefix applied, raised to a power. `factor` converts one `symbol` into
+/// the coherent SI unit of its dimension, the power is not included. Units with an `offset`
+/// like `°C` start their scale somewhere else than the coherent unit, see [`Unit::offset`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitPart {
+    pub symbol: String,
+    pub factor: f64,
+    pub offset: f64,
+    pub dimension: Dimension,
+    pub power: i32
+}
+
+/// Product of unit parts like `km/h` or `kg·m/s^2`. A unit without parts is dimensionless.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Unit {
+    pub parts: Vec<UnitPart>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct UnitDefinition {
+    pub symbol: String,
+    pub factor: f64,
+    pub offset: f64,
+    pub dimension: Dimension,
+    pub prefix: bool,
+    pub binary_prefix: bool
+}
+
+/// SI prefix like `k`, or binary prefix like `Ki` which only data units accept.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct UnitPrefix {
+    pub symbol: String,
+    pub name: String,
+    pub factor: f64,
+    pub binary: bool
+}
+
+impl UnitDefinition {
+    fn accepts(&self, prefix: &UnitPrefix) -> bool {
+        match prefix.binary {
+            true => self.binary_prefix,
+            false => self.prefix
+        }
+    }
+
+    fn part(&self, prefix: Option<&UnitPrefix>) -> UnitPart {
+        match prefix {
+            Some(prefix) => UnitPart {
+                symbol: format!("{}{}", prefix.symbol, self.symbol),
+                factor: prefix.factor * self.factor,
+                offset: self.offset,
+                dimension: self.dimension.clone(),
+                power: 1
+            },
+            None => UnitPart {
+                symbol: self.symbol.to_string(),
+                factor: self.factor,
+                offset: self.offset,
+                dimension: self.dimension.clone(),
+                power: 1
+            }
+        }
+    }
+}
+
+fn same_factor(left: f64, right: f64) -> bool {
+    (left - right).abs() <= f64::EPSILON * 16.0 * left.abs().max(right.abs())
+}
+
+impl From<UnitPart> for Unit {
+    fn from(part: UnitPart) -> Self {
+        Unit { parts: alloc::vec![part] }
+    }
+}
+
+impl Unit {
+    /// Multiplier converting a value of this unit into coherent SI units.
+    pub fn factor(&self) -> f64 {
+        self.parts.iter().map(|part| part.factor.powi(part.power)).product()
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        self.parts.iter().fold(Dimension::default(), |dimension, part| dimension.combine(&part.dimension, part.power))
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.dimension().is_dimensionless()
+    }
+
+    /// Offset of an absolute scale like `°C`, in its own unit. The offset only applies to the
+    /// unit on its own, `°C/s` is a rate of temperature differences.
+    pub fn offset(&self) -> Option<f64> {
+        match &self.parts[..] {
+            [part] if part.power == 1 && part.offset != 0.0 => Some(part.offset),
+            _ => None
+        }
+    }
+
+    /// Unit of differences on the scale of this unit, `°C` gives `Δ°C`. Differences have no
+    /// offset, `5 Δ°C` is `9 Δ°F` while `5 °C` is `41 °F`.
+    pub fn difference(&self) -> Unit {
+        Unit {
+            parts: self.parts.iter().map(|part| match part.symbol.starts_with('Δ') {
+                true => part.clone(),
+                false => UnitPart { symbol: format!("Δ{}", part.symbol), offset: 0.0, ..part.clone() }
+            }).collect()
+        }
+    }
+
+    pub fn is_difference(&self) -> bool {
+        self.parts.iter().any(|part| part.symbol.starts_with('Δ'))
+    }
+
+    /// Value in the coherent SI unit, `20 °C` is `293.15 K`.
+    pub fn to_coherent(&self, value: f64) -> f64 {
+        (value + self.offset().unwrap_or_default()) * self.factor()
+    }
+
+    /// Value in this unit for a value in the coherent SI unit.
+    pub fn to_unit(&self, value: f64) -> f64 {
+        value / self.factor() - self.offset().unwrap_or_default()
+    }
+
+    pub fn power(&self, power: i32) -> Unit {
+        Unit {
+            parts: self.parts.iter().map(|part| UnitPart { power: part.power * power, ..part.clone() }).collect()
+        }
+    }
+
+    /// `degree`th root of the unit, `m^2` gives `m`. `None` if a power is not a multiple of it.
+    pub fn root(&self, degree: i32) -> Option<Unit> {
+        match self.parts.iter().all(|part| part.power % degree == 0) {
+            true => Some(Unit { parts: self.parts.iter().map(|part| UnitPart { power: part.power / degree, ..part.clone() }).collect() }),
+            false => None
+        }
+    }
+
+    fn append(&mut self, part: UnitPart) {
+        match self.parts.iter().position(|item| item.symbol == part.symbol) {
+            Some(position) => {
+                self.parts[position].power += part.power;
+                if self.parts[position].power == 0 {
+                    self.parts.remove(position);
+                }
+            },
+            None => self.parts.push(part)
+        };
+    }
+
+    /// `self * other`. Parts of `other` with the dimension of an existing part are converted
+    /// into that part, so `km/h * s` gives `km` instead of `km·s/h`. Returns the scale to
+    /// apply to the multiplied values together with the new unit.
+    pub fn multiply(&self, other: &Unit) -> (f64, Unit) {
+        let mut scale = 1.0;
+        let mut unit = self.clone();
+
+        for part in other.parts.iter() {
+            match unit.parts.iter().find(|item| item.symbol != part.symbol && item.dimension == part.dimension) {
+                Some(item) => {
+                    scale *= (part.factor / item.factor).powi(part.power);
+                    unit.append(UnitPart { power: part.power, ..item.clone() });
+                },
+                None => unit.append(part.clone())
+            };
+        }
+
+        (scale, unit)
+    }
+
+    /// Parse a unit expression like `km/h`, `m/s^2`, `kg·m²` or `J/(kg·K)`.
+    pub fn parse(config: &SmartCalcConfig, text: &str) -> Option<Unit> {
+        let mut parser = UnitParser { config, chars: text.chars().peekable() };
+        let unit = parser.product()?;
+        match parser.chars.next() {
+            Some(_) => None,
+            None => Some(unit)
+        }
+    }
+}
+
+fn part_text(part: &UnitPart, power: i32) -> String {
+    match power {
+        1 => part.symbol.to_string(),
+        _ => format!("{}^{}", part.symbol, power)
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let numerator: Vec<String> = self.parts.iter().filter(|part| part.power > 0).map(|part| part_text(part, part.power)).collect();
+        let denominator: Vec<String> = self.parts.iter().filter(|part| part.power < 0).map(|part| part_text(part, -part.power)).collect();
+
+        let numerator = match numerator.is_empty() {
+            true => "1".to_string(),
+            false => numerator.join("·")
+        };
+
+        match denominator.len() {
+            0 => write!(f, "{}", numerator),
+            1 => write!(f, "{}/{}", numerator, denominator[0]),
+            _ => write!(f, "{}/({})", numerator, denominator.join("·"))
+        }
+    }
+}
+
+struct UnitParser<'a, 'b> {
+    config: &'a SmartCalcConfig,
+    chars: Peekable<Chars<'b>>
+}
+
+impl<'a, 'b> UnitParser<'a, 'b> {
+    fn product(&mut self) -> Option<Unit> {
+        let mut unit = self.factor()?;
+        loop {
+            let power = match self.chars.peek() {
+                Some('*') | Some('·') => 1,
+                Some('/') => -1,
+                _ => return Some(unit)
+            };
+
+            self.chars.next();
+            for part in self.factor()?.power(power).parts {
+                unit.append(part);
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Option<Unit> {
+        let unit = match self.chars.peek() {
+            Some('(') => {
+                self.chars.next();
+                let unit = self.product()?;
+                match self.chars.next() {
+                    Some(')') => unit,
+                    _ => return None
+                }
+            },
+
+            /* "1/s" */
+            Some('1') => {
+                self.chars.next();
+                Unit::default()
+            },
+            _ => {
+                let mut name = String::new();
+                while let Some(ch) = self.chars.peek().filter(|ch| ch.is_alphabetic() || **ch == '°') {
+                    name.push(*ch);
+                    self.chars.next();
+                }
+
+                Unit::from(find_unit_part(self.config, &name)?)
+            }
+        };
+
+        Some(unit.power(self.exponent()?))
+    }
+
+    fn exponent(&mut self) -> Option<i32> {
+        match self.chars.peek() {
+            Some('²') => { self.chars.next(); Some(2) },
+            Some('³') => { self.chars.next(); Some(3) },
+            Some('^') => {
+                self.chars.next();
+                let mut number = String::new();
+                if let Some('-') = self.chars.peek() {
+                    number.push('-');
+                    self.chars.next();
+                }
+
+                while let Some(ch) = self.chars.peek().filter(|ch| ch.is_ascii_digit()) {
+                    number.push(*ch);
+                    self.chars.next();
+                }
+
+                number.parse::<i32>().ok().filter(|power| *power != 0)
+            },
+            _ => Some(1)
+        }
+    }
+}
+
+fn find_unit_definition<'a>(config: &'a SmartCalcConfig, name: &str) -> Option<&'a Shared<UnitDefinition>> {
+    let name = name.to_lowercase();
+    config.unit_names.get(&name).or_else(|| config.unit_names.get(name.strip_suffix('s')?))
+}
+
+/// Unit with the given symbol or name, SI prefixes are applied to units that accept them.
+/// Imported dynamic types are found by their names. Symbols typed in another case, like
+/// `kwh` or `ml`, are found if only one unit has that symbol.
+pub(crate) fn find_unit_part(config: &SmartCalcConfig, name: &str) -> Option<UnitPart> {
+    find_configured_unit_part(config, name)
+        .or_else(|| find_dynamic_type(config, &name.to_lowercase()).and_then(|dynamic_type| config.get_dynamic_type_unit(&dynamic_type)))
+        .or_else(|| find_unit_part_ignoring_case(config, name))
+}
+
+/* Units of the "units" configuration only, the dynamic types are imported with them */
+fn find_configured_unit_part(config: &SmartCalcConfig, name: &str) -> Option<UnitPart> {
+    if name.is_empty() {
+        return None;
+    }
+
+    if let Some(definition) = config.units.get(name).or_else(|| find_unit_definition(config, name)) {
+        return Some(definition.part(None));
+    }
+
+    let lowercase = name.to_lowercase();
+    for prefix in config.unit_prefixes.iter() {
+        let definition = match (name.strip_prefix(&prefix.symbol[..]), lowercase.strip_prefix(&prefix.name[..])) {
+            (Some(symbol), _) if config.units.get(symbol).is_some_and(|definition| definition.accepts(prefix)) => config.units.get(symbol),
+            (_, Some(unit_name)) => find_unit_definition(config, unit_name),
+            _ => None
+        };
+
+        if let Some(definition) = definition.filter(|definition| definition.accepts(prefix)) {
+            return Some(definition.part(Some(prefix)));
+        }
+    }
+
+    None
+}
+
+/* Unit with the symbol in any case, none if more than one unit has it */
+fn find_symbol_ignoring_case<'a>(config: &'a SmartCalcConfig, symbol: &str) -> Option<&'a Shared<UnitDefinition>> {
+    let symbol = symbol.to_lowercase();
+    let mut definitions = config.units.values().filter(|definition| definition.symbol.to_lowercase() == symbol);
+    match (definitions.next(), definitions.next()) {
+        (Some(definition), None) => Some(definition),
+        _ => None
+    }
+}
+
+/* Prefixes keep their case, "mw" is milliwatt and "ml" is milliliter */
+fn find_unit_part_ignoring_case(config: &SmartCalcConfig, name: &str) -> Option<UnitPart> {
+    if name.is_empty() {
+        return None;
+    }
+
+    if let Some(definition) = find_symbol_ignoring_case(config, name) {
+        return Some(definition.part(None));
+    }
+
+    config.unit_prefixes.iter().find_map(|prefix| {
+        let definition = find_symbol_ignoring_case(config, name.strip_prefix(&prefix.symbol[..])?)?;
+        definition.accepts(prefix).then(|| definition.part(Some(prefix)))
+    })
+}
+
+fn find_dynamic_type(config: &SmartCalcConfig, name: &str) -> Option<Shared<DynamicType>> {
+    let singular = name.strip_suffix('s');
+    config.types.values()
+        .flat_map(|group| group.values())
+        .find(|dynamic_type| dynamic_type.names.iter().any(|item| item == name || Some(&item[..]) == singular))
+        .cloned()
+}
+
+/// Dynamic type with the same unit, used to print results the way the `types` configuration formats them.
+pub(crate) fn dynamic_type_for_unit(config: &SmartCalcConfig, unit: &Unit) -> Option<Shared<DynamicType>> {
+    let part = match &unit.parts[..] {
+        [part] if part.power == 1 && part.offset == 0.0 && !unit.is_difference() => part,
+        _ => return None
+    };
+
+    for (group_name, group) in config.dynamic_type_units().iter() {
+        for (index, dynamic_type_part) in group.iter() {
+            if dynamic_type_part.dimension == part.dimension && same_factor(dynamic_type_part.factor, part.factor) {
+                return config.types.get(group_name).and_then(|group| group.get(index)).cloned();
+            }
+        }
+    }
+
+    None
+}
+
+/// Unit without prefix, scale or offset for the dimension, like `K` for temperatures.
+pub(crate) fn coherent_unit(config: &SmartCalcConfig, dimension: &Dimension) -> Option<Unit> {
+    config.units.values()
+        .find(|definition| definition.dimension == *dimension && definition.offset == 0.0 && same_factor(definition.factor, 1.0))
+        .map(|definition| Unit::from(definition.part(None)))
+}
+
+/* Codes of the configuration use "." as decimal separator, "{value} * 25.4" is not 254 in a "," locale */
+fn execute_code(config: &SmartCalcConfig, code: &str) -> anyhow::Result<f64> {
+    SmartCalc::basic_execute_with_locale(code.replace("{value}", "1"), config, Some(Locale::new(".", ",")))
+}
+
+/* Relative factors of a dynamic type group, calculated from the upgrade code of each step */
+fn dynamic_type_factors(config: &SmartCalcConfig, group: &BTreeMap<usize, Shared<DynamicType>>) -> Option<BTreeMap<usize, f64>> {
+    let mut factors = BTreeMap::new();
+    let mut previous: Option<(&Shared<DynamicType>, f64)> = None;
+
+    for (index, dynamic_type) in group.iter() {
+        let factor = match previous {
+            None => 1.0,
+            Some((previous_type, previous_factor)) => {
+                if previous_type.index + 1 != *index {
+                    return None;
+                }
+
+                match execute_code(config, &previous_type.upgrade_code) {
+                    Ok(upgraded) if upgraded != 0.0 => previous_factor / upgraded,
+                    _ => return None
+                }
+            }
+        };
+
+        factors.insert(*index, factor);
+        previous = Some((dynamic_type, factor));
+    }
+
+    Some(factors)
+}
+
+/* Symbol of the configured unit with the same scale, like "GB" for the "gb" type, otherwise the first name */
+fn dynamic_type_symbol(config: &SmartCalcConfig, dynamic_type: &DynamicType, part: &UnitPart) -> String {
+    let format = dynamic_type.format.replace("{value}", "");
+    core::iter::once(format.trim())
+        .chain(dynamic_type.names.iter().map(|name| &name[..]))
+        .filter_map(|name| find_configured_unit_part(config, name))
+        .find(|unit_part| unit_part.dimension == part.dimension && same_factor(unit_part.factor, part.factor))
+        .map(|unit_part| unit_part.symbol)
+        .unwrap_or_else(|| dynamic_type.names.first().cloned().unwrap_or_default())
+}
+
+/// Import the `types` groups into the unit system.
+///
+/// Groups with a name matching a configured unit (`km`, `lb`, ...) get its dimension and
+/// scale, the others are linked through `type_conversion` or become a dimension of their own.
+pub(crate) fn import_dynamic_types(config: &SmartCalcConfig) -> BTreeMap<String, BTreeMap<usize, UnitPart>> {
+    let mut relative_factors = BTreeMap::new();
+    for (group_name, group) in config.types.iter() {
+        match dynamic_type_factors(config, group) {
+            Some(factors) => { relative_factors.insert(group_name.to_string(), factors); },
+            None => log::warn!("Dynamic type {} could not be imported as unit", group_name)
+        };
+    }
+
+    /* Scale and dimension of the first item of each group */
+    let mut anchors: BTreeMap<String, (f64, Dimension)> = BTreeMap::new();
+    for (group_name, factors) in relative_factors.iter() {
+        let group = &config.types[group_name];
+        let anchor = factors.iter().find_map(|(index, factor)| group[index].names.iter()
+            .find_map(|name| config.units.get(name).or_else(|| find_unit_definition(config, name)).map(|definition| definition.part(None))
+                .or_else(|| config.unit_prefixes.iter().find_map(|prefix| match name.strip_prefix(&prefix.symbol[..]).and_then(|symbol| config.units.get(symbol)) {
+                    Some(definition) if definition.accepts(prefix) => Some(definition.part(Some(prefix))),
+                    _ => None
+                })))
+            .map(|part| (part.factor / factor, part.dimension)));
+
+        if let Some(anchor) = anchor {
+            anchors.insert(group_name.to_string(), anchor);
+        }
+    }
+
+    let mut updated = true;
+    while updated {
+        updated = false;
+        for type_conversion in config.type_conversion.iter() {
+            let (source, target) = (&type_conversion.source, &type_conversion.target);
+            let (source_factor, target_factor) = match (relative_factors.get(&source.name).and_then(|factors| factors.get(&source.index)), relative_factors.get(&target.name).and_then(|factors| factors.get(&target.index))) {
+                (Some(source_factor), Some(target_factor)) => (*source_factor, *target_factor),
+                _ => continue
+            };
+
+            /* One source value is "to_source_calculation" target values */
+            let ratio = match execute_code(config, &type_conversion.to_source_calculation) {
+                Ok(ratio) if ratio != 0.0 => ratio,
+                _ => continue
+            };
+
+            let anchor = match (anchors.get(&source.name), anchors.get(&target.name)) {
+                (None, Some((scale, dimension))) => (source.name.to_string(), (scale * target_factor * ratio / source_factor, dimension.clone())),
+                (Some((scale, dimension)), None) => (target.name.to_string(), (scale * source_factor / ratio / target_factor, dimension.clone())),
+                _ => continue
+            };
+
+            anchors.insert(anchor.0, anchor.1);
+            updated = true;
+        }
+    }
+
+    let mut units = BTreeMap::new();
+    for (group_name, factors) in relative_factors.into_iter() {
+        let (scale, dimension) = anchors.remove(&group_name).unwrap_or_else(|| (1.0, Dimension::base(&group_name)));
+        let group = &config.types[&group_name];
+        let parts = factors.into_iter().map(|(index, factor)| {
+            let mut part = UnitPart {
+                symbol: String::new(),
+                factor: factor * scale,
+                offset: 0.0,
+                dimension: dimension.clone(),
+                power: 1
+            };
+            part.symbol = dynamic_type_symbol(config, &group[&index], &part);
+            (index, part)
+        }).collect();
+
+        units.insert(group_name, parts);
+    }
+
+    units
+}
+
+#[cfg(test)]
+#[test]
+fn unit_parse_test() {
+    let config = SmartCalcConfig::default();
+
+    let unit = Unit::parse(&config, "km/h").unwrap();
+    assert_eq!(unit.to_string(), "km/h");
+    assert!(same_factor(unit.factor(), 1000.0 / 3600.0));
+    assert_eq!(unit.dimension(), Dimension::base("length").combine(&Dimension::base("time"), -1));
+
+    let newton = Unit::parse(&config, "N").unwrap();
+    assert_eq!(newton.dimension(), Unit::parse(&config, "kg·m/s²").unwrap().dimension());
+    assert_eq!(Unit::parse(&config, "J/(kg·K)").unwrap().to_string(), "J/(kg·K)");
+    assert_eq!(Unit::parse(&config, "kilometers").unwrap().to_string(), "km");
+    assert!(same_factor(Unit::parse(&config, "kWh").unwrap().factor(), 3_600_000.0));
+    assert!(Unit::parse(&config, "apples").is_none());
+    assert_eq!(Unit::parse(&config, "kwh").unwrap().to_string(), "kWh");
+    assert_eq!(Unit::parse(&config, "ml").unwrap().to_string(), "mL");
+    assert!(Unit::parse(&config, "m/").is_none());
+
+    let (scale, unit) = Unit::parse(&config, "km/h").unwrap().multiply(&Unit::parse(&config, "min").unwrap());
+    assert!(same_factor(scale, 1.0 / 60.0));
+    assert_eq!(unit.to_string(), "km");
+}
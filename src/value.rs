@@ -0,0 +1,103 @@
+/*
+ * smartcalc v1.0.8
+ * Copyright (c) Erhan BARIS (Ruslan Ognyanov Asenov)
+ * Licensed under the GNU General Public License v2.0.
+ */
+
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+use crate::compiler::DataItem;
+use crate::compiler::date::DateItem;
+use crate::compiler::date_time::DateTimeItem;
+use crate::compiler::duration::DurationItem;
+use crate::compiler::money::MoneyItem;
+use crate::compiler::number::NumberItem;
+use crate::compiler::percent::PercentItem;
+use crate::compiler::quantity::QuantityItem;
+use crate::compiler::time::TimeItem;
+use crate::config::SmartCalcConfig;
+use crate::error::SmartCalcError;
+use crate::tools::{number_from_f64, number_to_f64};
+use crate::types::{Number, NumberType, TimeOffset, TokenType};
+use crate::unit::{Dimension, Unit};
+
+/// Typed result of a line, for hosts that consume results without parsing the output text.
+///
+/// Times are the wall clock time of their timezone, and the offset of the `TimeOffset`
+/// is the one in effect at that moment. Quantities carry the unit symbol (`km`, `km/h`) and
+/// the dimension of the unit, when the unit is known to the unit system.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(Number),
+    Money { amount: Number, currency: String },
+    Percent(Number),
+    Date(NaiveDate),
+    Time { time: NaiveTime, timezone: TimeOffset },
+    DateTime { date_time: NaiveDateTime, timezone: TimeOffset },
+    Duration(Duration),
+    Quantity { value: Number, unit: String, dimension: Option<Dimension> }
+}
+
+impl Value {
+    pub(crate) fn from_token(config: &SmartCalcConfig, token: &TokenType) -> Option<Self> {
+        let value = match token {
+            TokenType::Number(number, _) => Value::Number(*number),
+            TokenType::Money(amount, currency) => Value::Money { amount: *amount, currency: currency.code.to_string() },
+            TokenType::Percent(percent) => Value::Percent(number_from_f64(*percent).ok()?),
+            TokenType::Date(date, _) => Value::Date(*date),
+            TokenType::Time(time, timezone) => {
+                let (time, timezone) = local_time(time, timezone);
+                Value::Time { time: time.time(), timezone }
+            },
+            TokenType::DateTime(date_time, timezone) => {
+                let (date_time, timezone) = local_time(date_time, timezone);
+                Value::DateTime { date_time, timezone }
+            },
+            TokenType::Duration(duration) => Value::Duration(*duration),
+            TokenType::Quantity(value, unit) => Value::Quantity { value: number_from_f64(*value).ok()?, unit: unit.to_string(), dimension: Some(unit.dimension()) },
+            TokenType::DynamicType(value, dynamic_type) => match config.get_dynamic_type_unit(dynamic_type) {
+                Some(part) => Value::Quantity { value: number_from_f64(*value).ok()?, unit: part.symbol, dimension: Some(part.dimension) },
+                None => Value::Quantity { value: number_from_f64(*value).ok()?, unit: dynamic_type.names.first().cloned().unwrap_or_default(), dimension: None }
+            },
+            _ => return None
+        };
+        Some(value)
+    }
+
+    pub(crate) fn from_item(config: &SmartCalcConfig, item: &dyn DataItem) -> Option<Self> {
+        Value::from_token(config, &item.as_token_type())
+    }
+
+    /// Item of the interpreter for the value. Currencies and units are looked up in the configuration,
+    /// times get the current date.
+    pub(crate) fn to_item(&self, config: &SmartCalcConfig) -> Result<Rc<dyn DataItem>, SmartCalcError> {
+        Ok(match self {
+            Value::Number(number) => Rc::new(NumberItem(*number, NumberType::Decimal)),
+            Value::Money { amount, currency } => match config.get_currency(currency.to_lowercase()) {
+                Some(currency) => Rc::new(MoneyItem(*amount, currency)),
+                None => return Err(SmartCalcError::UnknownCurrency { currency: currency.to_string(), span: Default::default() })
+            },
+            Value::Percent(percent) => Rc::new(PercentItem(number_to_f64(*percent))),
+            Value::Date(date) => Rc::new(DateItem(*date, config.get_time_offset())),
+            Value::Time { time, timezone } => Rc::new(TimeItem(utc_time(&Utc::now().date_naive().and_time(*time), timezone), timezone.clone())),
+            Value::DateTime { date_time, timezone } => Rc::new(DateTimeItem(utc_time(date_time, timezone), timezone.clone())),
+            Value::Duration(duration) => Rc::new(DurationItem(*duration)),
+            Value::Quantity { value, unit, .. } => match Unit::parse(config, unit) {
+                Some(unit) => QuantityItem::from_value(config, number_to_f64(*value), unit)?,
+                None => return Err(SmartCalcError::UnknownUnit { unit: unit.to_string(), span: Default::default() })
+            }
+        })
+    }
+}
+
+fn utc_time(local: &NaiveDateTime, timezone: &TimeOffset) -> NaiveDateTime {
+    *local - Duration::minutes(timezone.offset_for_local(local) as i64)
+}
+
+/* Items keep UTC time, values are shown in the timezone of the item */
+fn local_time(utc: &NaiveDateTime, timezone: &TimeOffset) -> (NaiveDateTime, TimeOffset) {
+    let offset = timezone.offset_at(utc);
+    (*utc + Duration::minutes(offset as i64), TimeOffset { name: timezone.name.to_string(), offset })
+}
@@ -7,9 +7,29 @@
 use core::cell::{RefCell, Cell};
 use core::ops::Deref;
 use alloc::{string::{String, ToString}, vec::Vec, rc::Rc};
+use chrono::{Duration, NaiveDate};
 use crate::types::find_location;
+use crate::explain::ExplainStep;
+use crate::formatter::format_result;
 use crate::{types::TokenType, SmartCalcAstType, tokinizer::{Tokinizer, TokenInfoStatus, TokenInfo}, UiTokenType};
 
+/// Typed value used to seed a session variable from the host application.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariableValue {
+    Number(f64),
+    Money(f64, String),
+    Date(NaiveDate),
+    Duration(Duration)
+}
+
+/// Name, type and formatted value of a session variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionVariable {
+    pub name: String,
+    pub type_name: String,
+    pub value: String
+}
+
 #[derive(Debug)]
 pub struct VariableInfo {
     pub tokens: Vec<Rc<TokenType>>,
@@ -22,8 +42,6 @@ impl PartialEq for VariableInfo {
     }
 }
 
-unsafe impl Send for VariableInfo {}
-unsafe impl Sync for VariableInfo {}
 
 
 impl ToString for VariableInfo {
@@ -84,7 +102,10 @@ pub fn update_token_variables(tokenizer: &mut Tokinizer) {
 
             tokenizer.token_infos.drain(remove_start_index..remove_end_index);
             
-            let token_type = RefCell::new(Some(TokenType::Variable(session.variables.borrow()[&name].clone())));
+            let variable = session.variables.borrow()[&name].clone();
+            session.trace(|| ExplainStep::Variable { name: name.to_string(), value: format_result(tokenizer.config, session, variable.data.borrow().clone()) });
+
+            let token_type = RefCell::new(Some(TokenType::Variable(variable)));
             
             tokenizer.token_infos.insert(remove_start_index, Rc::new(TokenInfo {
                 start: text_start_position as usize,